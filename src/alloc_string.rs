@@ -0,0 +1,1078 @@
+// Copyright 2015, The inlinable_string crate Developers. See the COPYRIGHT file
+// at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT
+// or http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A heap-allocated UTF-8 string that allocates from a custom
+//! [`allocator-api2`][allocator-api2] allocator, and the
+//! `InlinableStringIn` type built on top of it.
+//!
+//! `AllocString<A>` works with any `Allocator`, including stateful ones like
+//! an arena reference; its allocator is kept by `clone` and `split_off`. To
+//! be used as the heap type of an `InlinableString` (or anywhere else a
+//! `StringExt` is expected), the allocator must also implement `Clone` and
+//! `Default`; see [below](#allocators-without-default).
+//!
+//! An `InlinableStringIn` created with `new_in`, `with_capacity_in` or
+//! `from_str_in`, or from a heap-allocated `AllocString`, keeps its allocator
+//! while its contents are inline, and promotes them into it when they
+//! outgrow the inline storage. Its clones and the strings split off it keep
+//! the allocator as well. Other strings promote into `A::default()`.
+//!
+//! This module is only available with the `allocator-api2` feature.
+//!
+//! [allocator-api2]: https://docs.rs/allocator-api2
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate allocator_api2;
+//! # extern crate inlinable_string;
+//! use allocator_api2::alloc::Global;
+//! use inlinable_string::{AllocString, InlinableString, InlinableStringIn, StringExt};
+//!
+//! let mut s: InlinableStringIn<Global> = InlinableStringIn::from("small");
+//! assert!(s.is_inline());
+//!
+//! // Promotion allocates from `Global::default()`.
+//! s.push_str(" and now big enough to need the heap");
+//! assert!(s.is_heap());
+//!
+//! // Or from a given allocator, which is kept while the string is inline.
+//! let mut s: InlinableStringIn<Global> = InlinableStringIn::new_in(Global);
+//! s.push_str("promoted into the allocator given to `new_in`");
+//! assert!(s.allocator().is_some());
+//! ```
+//!
+//! # Allocators without `Default`
+//!
+//! `InlinableStringIn<A>` requires `A: Default` even if every string is
+//! created with `new_in`. Its heap type has to implement `StringExt`, whose
+//! constructors (`new`, `with_capacity`, `from_utf8` and friends) are not
+//! given an allocator and so create one with `A::default()`. Allocators that
+//! cannot be created out of nothing, such as an arena passed by reference
+//! (`&'a Bump`), are therefore rejected:
+//!
+//! ```compile_fail,E0277
+//! # extern crate allocator_api2;
+//! # extern crate inlinable_string;
+//! use allocator_api2::alloc::Global;
+//! use inlinable_string::InlinableStringIn;
+//!
+//! // `&Global` does not implement `Default`.
+//! let s: InlinableStringIn<&Global> = InlinableStringIn::new_in(&Global);
+//! ```
+//!
+//! They can still be used with a stand-alone `AllocString`, whose inherent
+//! methods only need `A: Allocator`:
+//!
+//! ```
+//! # extern crate allocator_api2;
+//! # extern crate inlinable_string;
+//! use allocator_api2::alloc::Global;
+//! use inlinable_string::AllocString;
+//!
+//! let mut s = AllocString::new_in(&Global);
+//! s.push_str("allocated through a reference");
+//! assert_eq!(s, "allocated through a reference");
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use allocator_api2::alloc::{Allocator, Global};
+use allocator_api2::collections::TryReserveErrorKind;
+use allocator_api2::vec::Vec as AllocVec;
+use core::borrow::{Borrow, BorrowMut};
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash;
+use core::ops::{self, RangeBounds};
+use core::str;
+use str_buf;
+use string_ext;
+use {
+    FallibleStringMut, GrowthPolicy, HeapStorage, InlinableString, NeverDemote, StrBuf, StringExt,
+    TryReserveError, INLINABLE_STRING_CAPACITY,
+};
+
+/// An `InlinableString` whose heap-allocated strings use the allocator `A`.
+///
+/// `A` must implement `Clone` and `Default`, which rules out allocators
+/// passed by reference; see the
+/// [module level documentation](./index.html#allocators-without-default).
+///
+/// See the [module level documentation](./index.html) for more.
+pub type InlinableStringIn<A, const N: usize = INLINABLE_STRING_CAPACITY, P = NeverDemote> =
+    InlinableString<N, AllocString<A>, P>;
+
+/// A growable UTF-8 string allocated with the allocator `A`.
+///
+/// See the [module level documentation](./index.html) for more.
+#[derive(Clone)]
+pub struct AllocString<A: Allocator = Global> {
+    vec: AllocVec<u8, A>,
+}
+
+impl<A: Allocator> AllocString<A> {
+    /// Creates a new empty string that will allocate from `alloc`. Does not
+    /// allocate until something is pushed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate allocator_api2;
+    /// # extern crate inlinable_string;
+    /// use allocator_api2::alloc::Global;
+    /// use inlinable_string::AllocString;
+    ///
+    /// let s = AllocString::new_in(Global);
+    /// assert_eq!(s.capacity(), 0);
+    /// ```
+    #[inline]
+    pub fn new_in(alloc: A) -> Self {
+        AllocString {
+            vec: AllocVec::new_in(alloc),
+        }
+    }
+
+    /// Creates a new empty string with room for `capacity` bytes, allocated
+    /// from `alloc`.
+    #[inline]
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        AllocString {
+            vec: AllocVec::with_capacity_in(capacity, alloc),
+        }
+    }
+
+    /// Creates a copy of `string` allocated from `alloc`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate allocator_api2;
+    /// # extern crate inlinable_string;
+    /// use allocator_api2::alloc::Global;
+    /// use inlinable_string::AllocString;
+    ///
+    /// let s = AllocString::from_str_in("hello", Global);
+    /// assert_eq!(s, "hello");
+    /// ```
+    #[inline]
+    pub fn from_str_in(string: &str, alloc: A) -> Self {
+        let mut s = AllocString::with_capacity_in(string.len(), alloc);
+        s.push_str(string);
+        s
+    }
+
+    /// Returns a reference to the underlying allocator.
+    #[inline]
+    pub fn allocator(&self) -> &A {
+        self.vec.allocator()
+    }
+
+    /// Extracts a string slice containing the entire string.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        // SAFETY: `vec` only ever holds valid UTF-8.
+        unsafe { str::from_utf8_unchecked(&self.vec) }
+    }
+
+    /// Extracts a mutable string slice containing the entire string.
+    #[inline]
+    pub fn as_mut_str(&mut self) -> &mut str {
+        // SAFETY: `vec` only ever holds valid UTF-8.
+        unsafe { str::from_utf8_unchecked_mut(&mut self.vec) }
+    }
+
+    /// Converts the string into a byte vector using the same allocator.
+    #[inline]
+    pub fn into_bytes(self) -> AllocVec<u8, A> {
+        self.vec
+    }
+
+    /// Returns this string's capacity, in bytes.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    /// Reserves capacity for at least `additional` more bytes.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.vec.reserve(additional)
+    }
+
+    /// Tries to reserve capacity for at least `additional` more bytes,
+    /// returning an error if the capacity overflows or the allocator fails.
+    #[inline]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.vec
+            .try_reserve(additional)
+            .map_err(|e| try_reserve_error(e.kind()))
+    }
+
+    /// Reserves capacity for exactly `additional` more bytes.
+    #[inline]
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.vec.reserve_exact(additional)
+    }
+
+    /// Tries to reserve capacity for exactly `additional` more bytes,
+    /// returning an error if the capacity overflows or the allocator fails.
+    #[inline]
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.vec
+            .try_reserve_exact(additional)
+            .map_err(|e| try_reserve_error(e.kind()))
+    }
+
+    /// Shrinks the capacity of this string to match its length.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.vec.shrink_to_fit()
+    }
+
+    /// Appends the given string slice onto the end of this string.
+    #[inline]
+    pub fn push_str(&mut self, string: &str) {
+        self.vec.extend_from_slice(string.as_bytes())
+    }
+
+    /// Appends the given `char` to the end of this string.
+    #[inline]
+    pub fn push(&mut self, ch: char) {
+        self.push_str(ch.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Returns the length of this string, in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    /// Returns `true` if this string has a length of zero.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    /// Truncates this string, removing all contents.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.vec.clear()
+    }
+
+    /// Shortens this string to the specified length. Does nothing if
+    /// `new_len` is greater than the string's current length.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `new_len` does not lie on a `char` boundary.
+    #[inline]
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len <= self.len() {
+            assert!(self.is_char_boundary(new_len));
+            self.vec.truncate(new_len)
+        }
+    }
+
+    /// Removes the last character from the string and returns it, or `None`
+    /// if the string is empty.
+    #[inline]
+    pub fn pop(&mut self) -> Option<char> {
+        let ch = self.chars().next_back()?;
+        let new_len = self.len() - ch.len_utf8();
+        self.vec.truncate(new_len);
+        Some(ch)
+    }
+
+    /// Removes the `char` at byte position `idx` and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is larger than or equal to the string's length, or if
+    /// it does not lie on a `char` boundary.
+    #[inline]
+    pub fn remove(&mut self, idx: usize) -> char {
+        let ch = match self[idx..].chars().next() {
+            Some(ch) => ch,
+            None => panic!("cannot remove a char from the end of a string"),
+        };
+        self.vec.drain(idx..idx + ch.len_utf8());
+        ch
+    }
+
+    /// Removes the bytes in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the starting point or end point do not lie on a `char`
+    /// boundary, or if they're out of bounds.
+    #[inline]
+    pub fn remove_range<R>(&mut self, range: R)
+    where
+        R: RangeBounds<usize>,
+    {
+        let (start, end) = self.char_range(range);
+        self.vec.drain(start..end);
+    }
+
+    /// Inserts a character into this string at byte position `idx`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is larger than the string's length, or if it does not
+    /// lie on a `char` boundary.
+    #[inline]
+    pub fn insert(&mut self, idx: usize, ch: char) {
+        self.insert_str(idx, ch.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Inserts a string slice into this string at byte position `idx`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is larger than the string's length, or if it does not
+    /// lie on a `char` boundary.
+    #[inline]
+    pub fn insert_str(&mut self, idx: usize, string: &str) {
+        self.replace_range(idx..idx, string)
+    }
+
+    /// Splits the string into two at the given byte index. The returned
+    /// string uses a clone of this string's allocator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is not on a `char` boundary, or if it is beyond the end
+    /// of the string.
+    #[inline]
+    pub fn split_off(&mut self, at: usize) -> Self
+    where
+        A: Clone,
+    {
+        assert!(self.is_char_boundary(at));
+        AllocString {
+            vec: self.vec.split_off(at),
+        }
+    }
+
+    /// Retains only the characters specified by the predicate.
+    #[inline]
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(char) -> bool,
+    {
+        /// Keeps the string valid UTF-8 if `f` panics, by cutting it off
+        /// before the bytes that have not been compacted yet.
+        struct SetLenOnDrop<'a, A: Allocator> {
+            vec: &'a mut AllocVec<u8, A>,
+            kept: usize,
+        }
+
+        impl<'a, A: Allocator> Drop for SetLenOnDrop<'a, A> {
+            fn drop(&mut self) {
+                self.vec.truncate(self.kept);
+            }
+        }
+
+        let len = self.len();
+        let mut guard = SetLenOnDrop {
+            vec: &mut self.vec,
+            kept: 0,
+        };
+        let mut idx = 0;
+        while idx < len {
+            // SAFETY: `idx` is on a char boundary and everything from `idx`
+            // on is still untouched, valid UTF-8.
+            let ch = unsafe { str::from_utf8_unchecked(&guard.vec[idx..len]) }
+                .chars()
+                .next()
+                .unwrap();
+            let ch_len = ch.len_utf8();
+            if f(ch) {
+                guard.vec.copy_within(idx..idx + ch_len, guard.kept);
+                guard.kept += ch_len;
+            }
+            idx += ch_len;
+        }
+    }
+
+    /// Removes the specified range in the string, and replaces it with the
+    /// given string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the starting point or end point do not lie on a `char`
+    /// boundary, or if they're out of bounds.
+    #[inline]
+    pub fn replace_range<R>(&mut self, range: R, replace_with: &str)
+    where
+        R: RangeBounds<usize>,
+    {
+        let (start, end) = self.char_range(range);
+        self.vec
+            .splice(start..end, replace_with.bytes())
+            .for_each(drop);
+    }
+
+    /// Resolves `range` to a pair of byte offsets on `char` boundaries.
+    #[inline]
+    fn char_range<R>(&self, range: R) -> (usize, usize)
+    where
+        R: RangeBounds<usize>,
+    {
+        let (start, end) = str_buf::byte_range(&range, self.len());
+
+        // String index does all bounds checks.
+        let _ = &self[start..end];
+        (start, end)
+    }
+}
+
+/// Converts an `allocator-api2` reservation error into this crate's error.
+#[inline]
+fn try_reserve_error(kind: TryReserveErrorKind) -> TryReserveError {
+    match kind {
+        TryReserveErrorKind::CapacityOverflow => TryReserveError::CapacityOverflow,
+        TryReserveErrorKind::AllocError { .. } => TryReserveError::AllocError,
+    }
+}
+
+impl<A: Allocator + Default> Default for AllocString<A> {
+    #[inline]
+    fn default() -> Self {
+        AllocString::new_in(A::default())
+    }
+}
+
+impl<A: Allocator + Default> From<&str> for AllocString<A> {
+    #[inline]
+    fn from(string: &str) -> Self {
+        AllocString::from_str_in(string, A::default())
+    }
+}
+
+impl<A: Allocator> From<AllocString<A>> for String {
+    #[inline]
+    fn from(string: AllocString<A>) -> String {
+        String::from(string.as_str())
+    }
+}
+
+impl<A: Allocator> ops::Deref for AllocString<A> {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<A: Allocator> ops::DerefMut for AllocString<A> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut str {
+        self.as_mut_str()
+    }
+}
+
+impl<A: Allocator> AsRef<str> for AllocString<A> {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl<A: Allocator> Borrow<str> for AllocString<A> {
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl<A: Allocator> BorrowMut<str> for AllocString<A> {
+    fn borrow_mut(&mut self) -> &mut str {
+        self
+    }
+}
+
+impl<A: Allocator> fmt::Debug for AllocString<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<A: Allocator> fmt::Display for AllocString<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<A: Allocator> fmt::Write for AllocString<A> {
+    fn write_char(&mut self, ch: char) -> Result<(), fmt::Error> {
+        self.push(ch);
+        Ok(())
+    }
+    fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl<A: Allocator> hash::Hash for AllocString<A> {
+    #[inline]
+    fn hash<Hs: hash::Hasher>(&self, hasher: &mut Hs) {
+        (**self).hash(hasher)
+    }
+}
+
+impl<A: Allocator> PartialOrd for AllocString<A> {
+    fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
+        Some(Ord::cmp(self, rhs))
+    }
+}
+
+impl<A: Allocator> Ord for AllocString<A> {
+    #[inline]
+    fn cmp(&self, rhs: &Self) -> Ordering {
+        Ord::cmp(&**self, &**rhs)
+    }
+}
+
+impl<A: Allocator> PartialEq for AllocString<A> {
+    #[inline]
+    fn eq(&self, rhs: &Self) -> bool {
+        PartialEq::eq(&**self, &**rhs)
+    }
+}
+
+impl<A: Allocator> Eq for AllocString<A> {}
+
+macro_rules! impl_eq {
+    ($lhs:ty, $rhs: ty) => {
+        impl<'a, A: Allocator> PartialEq<$rhs> for $lhs {
+            #[inline]
+            fn eq(&self, other: &$rhs) -> bool {
+                PartialEq::eq(&self[..], &other[..])
+            }
+        }
+
+        impl<'a, A: Allocator> PartialEq<$lhs> for $rhs {
+            #[inline]
+            fn eq(&self, other: &$lhs) -> bool {
+                PartialEq::eq(&self[..], &other[..])
+            }
+        }
+    };
+}
+
+impl_eq! { AllocString<A>, str }
+impl_eq! { AllocString<A>, String }
+impl_eq! { AllocString<A>, &'a str }
+impl_eq! { Cow<'a, str>, AllocString<A> }
+
+impl<A: Allocator> StrBuf for AllocString<A> {
+    #[inline]
+    fn as_str(&self) -> &str {
+        AllocString::as_str(self)
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        AllocString::capacity(self)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        AllocString::len(self)
+    }
+}
+
+impl<A> FallibleStringMut for AllocString<A>
+where
+    A: Allocator + Clone + Default,
+{
+    type Error = TryReserveError;
+
+    #[inline]
+    fn try_push_str(&mut self, string: &str) -> Result<(), TryReserveError> {
+        string_ext::reserve_and_push_str(self, string)
+    }
+
+    #[inline]
+    fn try_insert_str(&mut self, idx: usize, string: &str) -> Result<(), TryReserveError> {
+        string_ext::reserve_and_insert_str(self, idx, string)
+    }
+
+    #[inline]
+    fn try_replace_range<R>(&mut self, range: R, replace_with: &str) -> Result<(), TryReserveError>
+    where
+        R: RangeBounds<usize>,
+    {
+        string_ext::reserve_and_replace_range(self, range, replace_with)
+    }
+}
+
+impl<A> StringExt for AllocString<A>
+where
+    A: Allocator + Clone + Default,
+{
+    #[inline]
+    fn with_capacity(capacity: usize) -> Self {
+        AllocString::with_capacity_in(capacity, A::default())
+    }
+
+    #[inline]
+    unsafe fn from_utf8_unchecked(bytes: Vec<u8>) -> Self {
+        AllocString::from_str_in(str::from_utf8_unchecked(&bytes), A::default())
+    }
+
+    #[inline]
+    fn push_str(&mut self, string: &str) {
+        AllocString::push_str(self, string)
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        AllocString::reserve(self, additional)
+    }
+
+    #[inline]
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        AllocString::try_reserve(self, additional)
+    }
+
+    #[inline]
+    fn reserve_exact(&mut self, additional: usize) {
+        AllocString::reserve_exact(self, additional)
+    }
+
+    #[inline]
+    fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        AllocString::try_reserve_exact(self, additional)
+    }
+
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        AllocString::shrink_to_fit(self)
+    }
+
+    #[inline]
+    fn push(&mut self, ch: char) {
+        AllocString::push(self, ch)
+    }
+
+    #[inline]
+    fn truncate(&mut self, new_len: usize) {
+        AllocString::truncate(self, new_len)
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<char> {
+        AllocString::pop(self)
+    }
+
+    #[inline]
+    fn remove(&mut self, idx: usize) -> char {
+        AllocString::remove(self, idx)
+    }
+
+    #[inline]
+    fn remove_range<R>(&mut self, range: R)
+    where
+        R: RangeBounds<usize>,
+    {
+        AllocString::remove_range(self, range)
+    }
+
+    #[inline]
+    fn insert(&mut self, idx: usize, ch: char) {
+        AllocString::insert(self, idx, ch)
+    }
+
+    #[inline]
+    fn insert_str(&mut self, idx: usize, string: &str) {
+        AllocString::insert_str(self, idx, string)
+    }
+
+    #[inline]
+    unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.vec
+    }
+
+    #[inline]
+    fn split_off(&mut self, at: usize) -> Self {
+        AllocString::split_off(self, at)
+    }
+
+    #[inline]
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(char) -> bool,
+    {
+        AllocString::retain(self, f)
+    }
+
+    #[inline]
+    fn replace_range<R>(&mut self, range: R, replace_with: &str)
+    where
+        R: RangeBounds<usize>,
+    {
+        AllocString::replace_range(self, range, replace_with)
+    }
+}
+
+// The allocator may be kept in the string itself, so an `AllocString` is
+// stored as it is instead of being packed.
+unsafe impl<A> HeapStorage for AllocString<A>
+where
+    A: Allocator + Clone + Default,
+{
+    type Packed = Self;
+    type Allocator = A;
+
+    #[inline]
+    fn allocate(capacity: usize, alloc: Option<&A>) -> Self {
+        let alloc = alloc.map_or_else(A::default, A::clone);
+        AllocString::with_capacity_in(capacity, alloc)
+    }
+
+    #[inline]
+    fn try_allocate(capacity: usize, alloc: Option<&A>) -> Result<Self, TryReserveError> {
+        let mut s = AllocString::new_in(alloc.map_or_else(A::default, A::clone));
+        s.try_reserve_exact(capacity)?;
+        Ok(s)
+    }
+
+    #[inline]
+    fn clone_allocator(&self) -> Option<A> {
+        Some(self.allocator().clone())
+    }
+
+    #[inline]
+    fn pack(self) -> (Self, u8) {
+        (self, 0)
+    }
+
+    #[inline]
+    unsafe fn unpack(packed: Self, _bits: u8) -> Self {
+        packed
+    }
+}
+
+impl<A, const N: usize, P> InlinableString<N, AllocString<A>, P>
+where
+    A: Allocator + Clone + Default,
+    P: GrowthPolicy,
+{
+    /// Creates a new empty string whose contents get promoted into `alloc`
+    /// once they no longer fit inline.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate allocator_api2;
+    /// # extern crate inlinable_string;
+    /// use allocator_api2::alloc::Global;
+    /// use inlinable_string::{InlinableStringIn, StringExt};
+    ///
+    /// let mut s: InlinableStringIn<Global> = InlinableStringIn::new_in(Global);
+    /// assert!(s.is_inline());
+    /// assert!(s.allocator().is_some());
+    ///
+    /// // Allocated from the `Global` given above.
+    /// s.push_str("long enough to be promoted into the heap");
+    /// assert!(s.is_heap());
+    /// ```
+    #[inline]
+    pub fn new_in(alloc: A) -> Self {
+        let mut s = InlinableString::new();
+        s.set_heap_allocator(alloc);
+        s
+    }
+
+    /// Creates a new empty string with room for `capacity` bytes. It is
+    /// allocated from `alloc` if `capacity` does not fit inline, and its
+    /// contents get promoted into `alloc` otherwise.
+    #[inline]
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        let mut s = InlinableString::new_in(alloc);
+        s.reserve_exact(capacity);
+        s
+    }
+
+    /// Creates a copy of `string`, stored inline if it fits and allocated
+    /// from `alloc` otherwise. Its contents get promoted into `alloc` once
+    /// they no longer fit inline.
+    #[inline]
+    pub fn from_str_in(string: &str, alloc: A) -> Self {
+        let mut s = InlinableString::with_capacity_in(string.len(), alloc);
+        s.push_str(string);
+        s
+    }
+
+    /// Returns a reference to the allocator that the string's contents get
+    /// promoted into, or `None` if they get promoted into `A::default()`.
+    #[inline]
+    pub fn allocator(&self) -> Option<&A> {
+        self.heap_allocator()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AllocString, InlinableStringIn};
+    use allocator_api2::alloc::{AllocError, Allocator, Global, Layout};
+    use std::cell::Cell;
+    use std::ptr::NonNull;
+    use std::string::String;
+    use {FallibleStringMut, StringExt, StringStorage, TryReserveError, INLINABLE_STRING_CAPACITY};
+
+    const LONG_STR: &str = "this is a really long string that is much larger than
+                        INLINE_STRING_CAPACITY and so cannot be stored inline.";
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Counts the allocations made on the current thread.
+    #[derive(Clone, Copy, Default)]
+    struct Counting;
+
+    unsafe impl Allocator for Counting {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            ALLOCATIONS.with(|n| n.set(n.get() + 1));
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    fn allocations() -> usize {
+        ALLOCATIONS.with(|n| n.get())
+    }
+
+    /// Tells apart allocators by an identifier that is kept across clones.
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    struct Tagged(u32);
+
+    unsafe impl Allocator for Tagged {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    /// Fails every allocation.
+    #[derive(Clone, Copy, Default)]
+    struct Failing;
+
+    unsafe impl Allocator for Failing {
+        fn allocate(&self, _layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            Err(AllocError)
+        }
+
+        unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+    }
+
+    #[test]
+    fn test_promotion_uses_allocator() {
+        let before = allocations();
+        let mut s: InlinableStringIn<Counting> = InlinableStringIn::from("small");
+        assert_eq!(allocations(), before);
+
+        s.push_str(LONG_STR);
+        assert!(s.is_heap());
+        assert_eq!(allocations(), before + 1);
+
+        let promotions: [fn(&mut InlinableStringIn<Counting>); 4] = [
+            |s| s.reserve(100),
+            |s| s.insert_str(1, LONG_STR),
+            |s| s.replace_range(1..2, LONG_STR),
+            |s| s.push('x'),
+        ];
+        let full = "a".repeat(INLINABLE_STRING_CAPACITY);
+        for promote in &promotions {
+            let mut s: InlinableStringIn<Counting> = InlinableStringIn::from(&full[..]);
+            let before = allocations();
+            promote(&mut s);
+            assert!(s.is_heap());
+            assert_eq!(allocations(), before + 1);
+        }
+    }
+
+    #[test]
+    fn test_try_promotion_reports_alloc_error() {
+        let mut s: InlinableStringIn<Failing> = InlinableStringIn::from("small");
+        assert_eq!(s.try_push_str(LONG_STR), Err(TryReserveError::AllocError));
+        assert!(s.is_inline());
+        assert_eq!(s, "small");
+
+        let mut s = AllocString::new_in(Failing);
+        assert_eq!(s.try_reserve(1), Err(TryReserveError::AllocError));
+        assert_eq!(
+            s.try_reserve_exact(usize::MAX),
+            Err(TryReserveError::CapacityOverflow)
+        );
+    }
+
+    #[test]
+    fn test_clone_and_split_off_keep_allocator() {
+        let mut s = AllocString::from_str_in(LONG_STR, Tagged(7));
+        assert_eq!(*s.clone().allocator(), Tagged(7));
+
+        let tail = s.split_off(10);
+        assert_eq!(*tail.allocator(), Tagged(7));
+        assert_eq!(s, &LONG_STR[..10]);
+        assert_eq!(tail, &LONG_STR[10..]);
+    }
+
+    fn heap_allocator(s: InlinableStringIn<Tagged>) -> Tagged {
+        match s.into_storage() {
+            StringStorage::Heap(s) => *s.allocator(),
+            _ => panic!("not heap-allocated"),
+        }
+    }
+
+    #[test]
+    fn test_inlinable_string_keeps_allocator() {
+        let mut s: InlinableStringIn<Tagged> = InlinableStringIn::new_in(Tagged(7));
+        assert_eq!(s.allocator(), Some(&Tagged(7)));
+        s.push_str(LONG_STR);
+        assert_eq!(heap_allocator(s), Tagged(7));
+
+        let promotions: [fn(&mut InlinableStringIn<Tagged>); 5] = [
+            |s| s.push_str(LONG_STR),
+            |s| s.reserve(100),
+            |s| s.insert_str(1, LONG_STR),
+            |s| s.replace_range(1..2, LONG_STR),
+            |s| s.try_push_str(LONG_STR).unwrap(),
+        ];
+        for promote in &promotions {
+            let mut s = InlinableStringIn::from_str_in("small", Tagged(7));
+            assert!(s.is_inline());
+            promote(&mut s);
+            assert_eq!(heap_allocator(s), Tagged(7));
+        }
+
+        let s: InlinableStringIn<Tagged> = InlinableStringIn::with_capacity_in(100, Tagged(7));
+        assert!(s.is_heap());
+        assert_eq!(heap_allocator(s), Tagged(7));
+
+        // Without an allocator, contents get promoted into the default one.
+        let mut s: InlinableStringIn<Tagged> = InlinableStringIn::from("small");
+        assert_eq!(s.allocator(), None);
+        s.push_str(LONG_STR);
+        assert_eq!(heap_allocator(s), Tagged(0));
+    }
+
+    #[test]
+    fn test_inlinable_string_clone_and_split_off_keep_allocator() {
+        let mut s: InlinableStringIn<Tagged> = InlinableStringIn::new_in(Tagged(7));
+        s.push_str("small");
+        let mut clone = s.clone();
+        assert_eq!(clone.allocator(), Some(&Tagged(7)));
+        clone.push_str(LONG_STR);
+        assert_eq!(heap_allocator(clone), Tagged(7));
+
+        let mut tail = s.split_off(2);
+        assert_eq!(tail.allocator(), Some(&Tagged(7)));
+        tail.push_str(LONG_STR);
+        assert_eq!(heap_allocator(tail), Tagged(7));
+
+        // A heap-allocated string keeps its allocator after being demoted.
+        let heap = AllocString::from_str_in(LONG_STR, Tagged(7));
+        let mut s: InlinableStringIn<Tagged> = InlinableStringIn::from(StringStorage::Heap(heap));
+        s.truncate(3);
+        s.shrink_to_fit();
+        assert!(s.is_inline());
+        s.push_str(LONG_STR);
+        assert_eq!(heap_allocator(s), Tagged(7));
+    }
+
+    #[test]
+    fn test_operations_match_string() {
+        let mut s: AllocString = AllocString::from("héllo, wörld");
+        let mut expected = String::from("héllo, wörld");
+
+        s.insert(0, '¡');
+        expected.insert(0, '¡');
+        s.insert_str(3, "--");
+        expected.insert_str(3, "--");
+        assert_eq!(s, expected);
+
+        assert_eq!(s.remove(2), expected.remove(2));
+        assert_eq!(s.pop(), expected.pop());
+        s.remove_range(2..3);
+        expected.drain(2..3);
+        assert_eq!(s, expected);
+
+        s.retain(|c| c != 'l' && c != 'ö');
+        expected.retain(|c| c != 'l' && c != 'ö');
+        assert_eq!(s, expected);
+
+        s.replace_range(..2, "HE");
+        expected.replace_range(..2, "HE");
+        assert_eq!(s, expected);
+
+        s.truncate(5);
+        expected.truncate(5);
+        assert_eq!(s, expected);
+        assert_eq!(String::from(s), expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_truncate_off_char_boundary() {
+        let mut s: AllocString = AllocString::from("é");
+        s.truncate(1);
+    }
+
+    #[test]
+    fn test_retain_panic_keeps_utf8() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mut s: AllocString = AllocString::from("aébcé");
+        let mut seen = 0;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            s.retain(|c| {
+                seen += 1;
+                if seen == 4 {
+                    panic!();
+                }
+                c != 'é'
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(s, "ab");
+    }
+
+    #[test]
+    fn test_allocator_without_default() {
+        // A reference to an allocator does not implement `Default`.
+        let tagged = Tagged(7);
+        let mut s = AllocString::new_in(&tagged);
+        s.push_str(LONG_STR);
+        s.insert(0, '¡');
+        s.retain(|c| c != ' ');
+
+        let t = s.clone();
+        let u = s.split_off(6);
+        assert_eq!(s, "¡this");
+        assert_eq!(u, LONG_STR.replace(' ', "")[4..]);
+        assert_eq!(t, LONG_STR.replace(' ', "").replacen("this", "¡this", 1));
+        for alloc in [s.allocator(), t.allocator(), u.allocator()] {
+            assert!(std::ptr::eq(*alloc, &tagged));
+        }
+    }
+
+    crate::string_ext_conformance_tests!(conformance_alloc_string, AllocString<Global>);
+    crate::string_ext_conformance_tests!(conformance, InlinableStringIn<Global>);
+}
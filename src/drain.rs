@@ -0,0 +1,123 @@
+// Copyright 2015, The inlinable_string crate Developers. See the COPYRIGHT file
+// at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT
+// or http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The draining iterator shared by `InlineString::drain` and
+//! `StringExt::drain`.
+
+use core::fmt;
+use core::iter::FusedIterator;
+use core::ops::{Range, RangeBounds};
+use str_buf;
+
+/// The string operations a `Drain` needs.
+///
+/// This is public only so that it can bound `Drain`; it is not reachable from
+/// outside the crate.
+pub trait Drainable {
+    /// Returns the whole string.
+    fn as_str(&self) -> &str;
+
+    /// Removes the bytes in `range`, which lies on `char` boundaries.
+    fn remove_range(&mut self, range: Range<usize>);
+}
+
+/// A draining iterator over the characters of a string.
+///
+/// This struct is created by the `drain` method on `InlineString` and
+/// `StringExt`. The drained range is removed from the string when it is
+/// dropped.
+pub struct Drain<'a, S: Drainable + ?Sized> {
+    string: &'a mut S,
+    /// The range to remove when dropped.
+    start: usize,
+    end: usize,
+    /// The range of characters that have not been yielded yet.
+    front: usize,
+    back: usize,
+}
+
+impl<'a, S: Drainable + ?Sized> Drain<'a, S> {
+    /// Creates a `Drain` over `range`, panicking if it is out of bounds or
+    /// not on `char` boundaries.
+    #[inline]
+    pub(crate) fn new<R>(string: &'a mut S, range: R) -> Self
+    where
+        R: RangeBounds<usize>,
+    {
+        let (start, end) = str_buf::byte_range(&range, string.as_str().len());
+        // String index does all bounds checks.
+        let _ = &string.as_str()[start..end];
+        Drain {
+            string,
+            start,
+            end,
+            front: start,
+            back: end,
+        }
+    }
+
+    /// Returns the remaining (sub)string of this iterator as a slice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::InlineString;
+    ///
+    /// let mut s: InlineString = InlineString::try_from("abc").unwrap();
+    /// let mut drain = s.drain(..);
+    /// assert_eq!(drain.as_str(), "abc");
+    /// let _ = drain.next().unwrap();
+    /// assert_eq!(drain.as_str(), "bc");
+    /// ```
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.string.as_str()[self.front..self.back]
+    }
+}
+
+impl<'a, S: Drainable + ?Sized> Iterator for Drain<'a, S> {
+    type Item = char;
+
+    #[inline]
+    fn next(&mut self) -> Option<char> {
+        let ch = self.as_str().chars().next()?;
+        self.front += ch.len_utf8();
+        Some(ch)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len.div_ceil(4), Some(len))
+    }
+}
+
+impl<'a, S: Drainable + ?Sized> DoubleEndedIterator for Drain<'a, S> {
+    #[inline]
+    fn next_back(&mut self) -> Option<char> {
+        let ch = self.as_str().chars().next_back()?;
+        self.back -= ch.len_utf8();
+        Some(ch)
+    }
+}
+
+impl<'a, S: Drainable + ?Sized> FusedIterator for Drain<'a, S> {}
+
+impl<'a, S: Drainable + ?Sized> fmt::Debug for Drain<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Drain").field(&self.as_str()).finish()
+    }
+}
+
+impl<'a, S: Drainable + ?Sized> Drop for Drain<'a, S> {
+    #[inline]
+    fn drop(&mut self) {
+        self.string.remove_range(self.start..self.end);
+    }
+}
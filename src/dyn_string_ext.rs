@@ -0,0 +1,413 @@
+// Copyright 2015, The inlinable_string crate Developers. See the COPYRIGHT file
+// at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT
+// or http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! An object-safe companion to `StringExt`, for code that has to take strings
+//! as trait objects.
+//!
+//! [`StringExt`](../string_ext/trait.StringExt.html) is not object safe: it
+//! requires `Sized`, has constructors, and its `remove_range`, `retain` and
+//! `replace_range` methods are generic. `DynStringExt` offers the in-place
+//! operations of `StringExt` without any of these, so `&mut dyn DynStringExt`
+//! can be passed across plugin boundaries or stored in collections of mixed
+//! string types. Ranges are passed as a `ByteRange`, and `retain` takes a
+//! `&mut dyn FnMut(char) -> bool`.
+//!
+//! Every `StringExt` implements `DynStringExt` through a blanket impl. The two
+//! traits share method names, so only import `DynStringExt` where you work
+//! with trait objects. For the same reason it is not re-exported at the
+//! crate root, so that `use inlinable_string::*;` only brings `StringExt`
+//! into scope:
+//!
+//! ```
+//! use inlinable_string::*;
+//!
+//! let mut s: InlinableString = InlinableString::new();
+//! s.push_str("not ambiguous");
+//! s.retain(|ch| ch != ' ');
+//! assert_eq!(s, "notambiguous");
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use inlinable_string::dyn_string_ext::DynStringExt;
+//! use inlinable_string::InlinableString;
+//!
+//! fn plugin(buf: &mut dyn DynStringExt) {
+//!     buf.push_str(", world");
+//!     buf.replace_range((..5).into(), "hello");
+//!     buf.retain(&mut |ch| ch != ',');
+//! }
+//!
+//! let mut a = String::from("HELLO");
+//! let mut b: InlinableString = InlinableString::from("HELLO");
+//!
+//! let buffers: [&mut dyn DynStringExt; 2] = [&mut a, &mut b];
+//! for buf in buffers {
+//!     plugin(buf);
+//!     assert_eq!(buf.as_str(), "hello world");
+//! }
+//! ```
+
+use core::fmt::Display;
+use core::ops::{
+    Bound, Range, RangeBounds, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive,
+};
+use {FallibleStringMut, StrBuf, StringExt, TryReserveError};
+
+/// A range of byte offsets into a string, used by `DynStringExt` in place of
+/// a generic `RangeBounds<usize>` parameter.
+///
+/// Every range type of `std::ops` converts into a `ByteRange`, and so does a
+/// `(Bound<usize>, Bound<usize>)` pair.
+///
+/// # Examples
+///
+/// ```
+/// use inlinable_string::dyn_string_ext::ByteRange;
+/// use std::ops::{Bound, RangeBounds};
+///
+/// let range = ByteRange::from(1..=3);
+/// assert_eq!(range.start_bound(), Bound::Included(&1));
+/// assert_eq!(range.end_bound(), Bound::Included(&3));
+/// assert_eq!(ByteRange::from(..), ByteRange::new(Bound::Unbounded, Bound::Unbounded));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ByteRange {
+    start: Bound<usize>,
+    end: Bound<usize>,
+}
+
+impl ByteRange {
+    /// Creates a range from its start and end bounds.
+    #[inline]
+    pub fn new(start: Bound<usize>, end: Bound<usize>) -> ByteRange {
+        ByteRange { start, end }
+    }
+}
+
+impl RangeBounds<usize> for ByteRange {
+    #[inline]
+    fn start_bound(&self) -> Bound<&usize> {
+        self.start.as_ref()
+    }
+
+    #[inline]
+    fn end_bound(&self) -> Bound<&usize> {
+        self.end.as_ref()
+    }
+}
+
+macro_rules! impl_from_range {
+    ($($range:ty),*) => {
+        $(
+            impl From<$range> for ByteRange {
+                #[inline]
+                fn from(range: $range) -> ByteRange {
+                    ByteRange::new(range.start_bound().cloned(), range.end_bound().cloned())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_range! {
+    Range<usize>,
+    RangeFrom<usize>,
+    RangeFull,
+    RangeInclusive<usize>,
+    RangeTo<usize>,
+    RangeToInclusive<usize>,
+    (Bound<usize>, Bound<usize>)
+}
+
+/// The object-safe subset of `StringExt`, implemented for every `StringExt`.
+///
+/// The read-only methods come from the `StrBuf` supertrait. Each method
+/// behaves like the `StringExt` method of the same name, including when it
+/// panics.
+///
+/// See the [module level documentation](./index.html) for more.
+pub trait DynStringExt: StrBuf + Display {
+    /// Appends the given string slice onto the end of this string.
+    fn push_str(&mut self, string: &str);
+
+    /// Appends the given string slice onto the end of this string, or
+    /// returns an error, leaving the string unchanged, if the memory for it
+    /// cannot be allocated.
+    fn try_push_str(&mut self, string: &str) -> Result<(), TryReserveError>;
+
+    /// Appends the given `char` to the end of this string.
+    fn push(&mut self, ch: char);
+
+    /// Copies the specified range of bytes to the end of this string.
+    fn extend_from_within(&mut self, src: ByteRange);
+
+    /// Reserves capacity for at least `additional` more bytes.
+    fn reserve(&mut self, additional: usize);
+
+    /// Tries to reserve capacity for at least `additional` more bytes.
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError>;
+
+    /// Reserves the minimum capacity for exactly `additional` more bytes.
+    fn reserve_exact(&mut self, additional: usize);
+
+    /// Tries to reserve the minimum capacity for exactly `additional` more
+    /// bytes.
+    fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError>;
+
+    /// Shrinks the capacity of this string to match its length.
+    fn shrink_to_fit(&mut self);
+
+    /// Shrinks the capacity of this string with a lower bound.
+    fn shrink_to(&mut self, min_capacity: usize);
+
+    /// Shortens this string to the specified length.
+    fn truncate(&mut self, new_len: usize);
+
+    /// Removes the last character from this string and returns it.
+    fn pop(&mut self) -> Option<char>;
+
+    /// Removes and returns the `char` at byte position `idx`.
+    fn remove(&mut self, idx: usize) -> char;
+
+    /// Removes the specified range of bytes from this string.
+    fn remove_range(&mut self, range: ByteRange);
+
+    /// Removes all non-overlapping matches of `pat` from this string.
+    fn remove_matches(&mut self, pat: &str);
+
+    /// Inserts a character at byte position `idx`.
+    fn insert(&mut self, idx: usize, ch: char);
+
+    /// Inserts a string slice at byte position `idx`.
+    fn insert_str(&mut self, idx: usize, string: &str);
+
+    /// Inserts a string slice at byte position `idx`, or returns an error,
+    /// leaving the string unchanged, if the memory for it cannot be
+    /// allocated.
+    fn try_insert_str(&mut self, idx: usize, string: &str) -> Result<(), TryReserveError>;
+
+    /// Truncates this string, removing all contents.
+    fn clear(&mut self);
+
+    /// Retains only the characters for which `f` returns `true`.
+    fn retain(&mut self, f: &mut dyn FnMut(char) -> bool);
+
+    /// Replaces the specified range of bytes with the given string slice.
+    fn replace_range(&mut self, range: ByteRange, replace_with: &str);
+
+    /// Replaces the specified range of bytes with the given string slice, or
+    /// returns an error, leaving the string unchanged, if the memory for it
+    /// cannot be allocated.
+    fn try_replace_range(
+        &mut self,
+        range: ByteRange,
+        replace_with: &str,
+    ) -> Result<(), TryReserveError>;
+}
+
+impl<S: StringExt> DynStringExt for S {
+    #[inline]
+    fn push_str(&mut self, string: &str) {
+        StringExt::push_str(self, string)
+    }
+
+    #[inline]
+    fn try_push_str(&mut self, string: &str) -> Result<(), TryReserveError> {
+        FallibleStringMut::try_push_str(self, string)
+    }
+
+    #[inline]
+    fn push(&mut self, ch: char) {
+        StringExt::push(self, ch)
+    }
+
+    #[inline]
+    fn extend_from_within(&mut self, src: ByteRange) {
+        StringExt::extend_from_within(self, src)
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        StringExt::reserve(self, additional)
+    }
+
+    #[inline]
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        StringExt::try_reserve(self, additional)
+    }
+
+    #[inline]
+    fn reserve_exact(&mut self, additional: usize) {
+        StringExt::reserve_exact(self, additional)
+    }
+
+    #[inline]
+    fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        StringExt::try_reserve_exact(self, additional)
+    }
+
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        StringExt::shrink_to_fit(self)
+    }
+
+    #[inline]
+    fn shrink_to(&mut self, min_capacity: usize) {
+        StringExt::shrink_to(self, min_capacity)
+    }
+
+    #[inline]
+    fn truncate(&mut self, new_len: usize) {
+        StringExt::truncate(self, new_len)
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<char> {
+        StringExt::pop(self)
+    }
+
+    #[inline]
+    fn remove(&mut self, idx: usize) -> char {
+        StringExt::remove(self, idx)
+    }
+
+    #[inline]
+    fn remove_range(&mut self, range: ByteRange) {
+        StringExt::remove_range(self, range)
+    }
+
+    #[inline]
+    fn remove_matches(&mut self, pat: &str) {
+        StringExt::remove_matches(self, pat)
+    }
+
+    #[inline]
+    fn insert(&mut self, idx: usize, ch: char) {
+        StringExt::insert(self, idx, ch)
+    }
+
+    #[inline]
+    fn insert_str(&mut self, idx: usize, string: &str) {
+        StringExt::insert_str(self, idx, string)
+    }
+
+    #[inline]
+    fn try_insert_str(&mut self, idx: usize, string: &str) -> Result<(), TryReserveError> {
+        FallibleStringMut::try_insert_str(self, idx, string)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        StringExt::clear(self)
+    }
+
+    #[inline]
+    fn retain(&mut self, f: &mut dyn FnMut(char) -> bool) {
+        StringExt::retain(self, f)
+    }
+
+    #[inline]
+    fn replace_range(&mut self, range: ByteRange, replace_with: &str) {
+        StringExt::replace_range(self, range, replace_with)
+    }
+
+    #[inline]
+    fn try_replace_range(
+        &mut self,
+        range: ByteRange,
+        replace_with: &str,
+    ) -> Result<(), TryReserveError> {
+        FallibleStringMut::try_replace_range(self, range, replace_with)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ByteRange, DynStringExt};
+    use std::string::{String, ToString};
+    use std::vec::Vec;
+    use {InlinableCow, InlinableString, SharedInlinableString, TryReserveError};
+
+    const LONG_STR: &str = "this is a really long string that is much larger than
+                        INLINE_STRING_CAPACITY and so cannot be stored inline.";
+
+    fn edit(buf: &mut dyn DynStringExt) {
+        buf.push_str("abc");
+        buf.push('d');
+        buf.insert(0, '>');
+        buf.insert_str(1, "> ");
+        buf.remove_range((..1).into());
+        assert_eq!(buf.remove(0), '>');
+        buf.replace_range((1..=2).into(), "AB");
+        buf.retain(&mut |ch| ch != 'd');
+        assert_eq!(buf.pop(), Some('c'));
+        assert_eq!(buf.try_push_str("!"), Ok(()));
+        buf.extend_from_within((1..).into());
+        buf.remove_matches("AB");
+    }
+
+    #[test]
+    fn test_trait_objects() {
+        let mut string = String::new();
+        let mut inlinable: InlinableString = InlinableString::from("");
+        let mut shared: SharedInlinableString = SharedInlinableString::from("");
+        let mut cow: InlinableCow = InlinableCow::from("");
+
+        let buffers: Vec<&mut dyn DynStringExt> =
+            vec![&mut string, &mut inlinable, &mut shared, &mut cow];
+        for buf in buffers {
+            edit(buf);
+            assert_eq!(buf.as_str(), " !!");
+            assert_eq!(buf.to_string(), " !!");
+            assert_eq!(buf.len(), 3);
+
+            buf.try_insert_str(0, LONG_STR).unwrap();
+            assert!(buf.capacity() > LONG_STR.len());
+            buf.try_replace_range((..LONG_STR.len()).into(), "")
+                .unwrap();
+            buf.truncate(2);
+            buf.shrink_to(1);
+            buf.shrink_to_fit();
+            assert_eq!(buf.as_str(), " !");
+
+            assert_eq!(
+                buf.try_reserve(usize::MAX),
+                Err(TryReserveError::CapacityOverflow)
+            );
+            buf.clear();
+            assert!(buf.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_byte_range() {
+        use std::ops::Bound::*;
+
+        let ranges: [(ByteRange, ByteRange); 7] = [
+            ((1..3).into(), ByteRange::new(Included(1), Excluded(3))),
+            ((1..).into(), ByteRange::new(Included(1), Unbounded)),
+            ((..).into(), ByteRange::new(Unbounded, Unbounded)),
+            ((1..=3).into(), ByteRange::new(Included(1), Included(3))),
+            ((..3).into(), ByteRange::new(Unbounded, Excluded(3))),
+            ((..=3).into(), ByteRange::new(Unbounded, Included(3))),
+            (
+                (Excluded(0), Excluded(3)).into(),
+                ByteRange::new(Excluded(0), Excluded(3)),
+            ),
+        ];
+        for &(range, expected) in &ranges {
+            assert_eq!(range, expected);
+        }
+
+        let mut s = String::from("0123456");
+        DynStringExt::remove_range(&mut s, (Excluded(0), Included(2)).into());
+        assert_eq!(s, "03456");
+    }
+}
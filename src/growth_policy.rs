@@ -0,0 +1,115 @@
+// Copyright 2015, The inlinable_string crate Developers. See the COPYRIGHT file
+// at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT
+// or http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Policies deciding when an `InlinableString` moves between inline and
+//! heap storage.
+//!
+//! An `InlinableString` is promoted to the heap as soon as it outgrows its
+//! inline capacity. The `GrowthPolicy` chosen through its `P` type parameter
+//! decides how much room that heap allocation gets, and whether the string
+//! moves back inline after `truncate`, `pop`, `remove`, `remove_range`,
+//! `retain`, `clear`, `replace_range` or `split_off` shrank it.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[cfg(feature = "alloc")] {
+//! use inlinable_string::{DemoteWhenFits, InlinableString, NeverDemote, StringExt};
+//!
+//! const LONG: &str = "a string that is too long to be stored inline";
+//! let mut kept: InlinableString<15, String, NeverDemote> = InlinableString::from(LONG);
+//! let mut demoted: InlinableString<15, String, DemoteWhenFits> = InlinableString::from(LONG);
+//!
+//! kept.truncate(3);
+//! demoted.truncate(3);
+//!
+//! assert!(kept.is_heap());
+//! assert!(demoted.is_inline());
+//! # }
+//! ```
+
+use core::cmp;
+
+/// Decides when an `InlinableString` moves between inline and heap storage.
+///
+/// See the [module level documentation](./index.html) for more.
+pub trait GrowthPolicy {
+    /// Returns whether a heap-allocated string that shrank to `len` bytes
+    /// should move back into inline storage holding up to `inline_capacity`
+    /// bytes. It is only moved if it actually fits.
+    fn should_demote(len: usize, inline_capacity: usize) -> bool;
+
+    /// Returns the capacity to allocate when a string holding up to
+    /// `inline_capacity` bytes inline is promoted to the heap because it has
+    /// to hold `required` bytes. The result is never less than `required`.
+    ///
+    /// By default this reserves at least twice the inline capacity, so that
+    /// the next few pushes don't reallocate again.
+    #[inline]
+    fn promoted_capacity(required: usize, inline_capacity: usize) -> usize {
+        cmp::max(required, inline_capacity.saturating_mul(2))
+    }
+}
+
+/// Heap-allocated strings stay on the heap, only `shrink_to_fit` moves them
+/// back inline. This is the default policy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NeverDemote;
+
+impl GrowthPolicy for NeverDemote {
+    #[inline]
+    fn should_demote(_len: usize, _inline_capacity: usize) -> bool {
+        false
+    }
+}
+
+/// Heap-allocated strings move back inline as soon as they fit.
+///
+/// A string that keeps growing and shrinking around the inline capacity
+/// allocates every time it crosses it; see `DemoteBelow` to avoid that.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DemoteWhenFits;
+
+impl GrowthPolicy for DemoteWhenFits {
+    #[inline]
+    fn should_demote(len: usize, inline_capacity: usize) -> bool {
+        len <= inline_capacity
+    }
+}
+
+/// Heap-allocated strings move back inline once they are shorter than
+/// `THRESHOLD` bytes.
+///
+/// Picking a threshold below the inline capacity leaves room to grow again
+/// before the string has to be promoted back to the heap.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "alloc")] {
+/// use inlinable_string::{DemoteBelow, InlinableString, StringExt};
+///
+/// let mut s: InlinableString<15, String, DemoteBelow<8>> =
+///     InlinableString::from("a string that is too long to be stored inline");
+///
+/// s.truncate(12);
+/// assert!(s.is_heap());
+///
+/// s.truncate(7);
+/// assert!(s.is_inline());
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DemoteBelow<const THRESHOLD: usize>;
+
+impl<const THRESHOLD: usize> GrowthPolicy for DemoteBelow<THRESHOLD> {
+    #[inline]
+    fn should_demote(len: usize, _inline_capacity: usize) -> bool {
+        len < THRESHOLD
+    }
+}
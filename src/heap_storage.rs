@@ -0,0 +1,205 @@
+// Copyright 2015, The inlinable_string crate Developers. See the COPYRIGHT file
+// at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT
+// or http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Heap-allocated string types that an `InlinableString` can be promoted to.
+//!
+//! An `InlinableString` keeps its heap-allocated string, its inline bytes and
+//! its borrowed `'static` string in the same bytes, followed by a one byte
+//! tag telling them apart. The `HeapStorage` trait converts a heap-allocated
+//! string to and from the packed form it takes in there. A `String` packs
+//! into its pointer, its length and all but the highest byte of its capacity,
+//! which goes into the tag, so that an `InlinableString` is no bigger than a
+//! `String`.
+//!
+//! A heap-allocated string type may also come with an allocator, which an
+//! `InlinableString` keeps next to its contents so that they get promoted
+//! back into it after being moved inline.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::mem::size_of;
+//! use inlinable_string::InlinableString;
+//!
+//! assert_eq!(size_of::<InlinableString>(), size_of::<String>());
+//! assert_eq!(size_of::<Option<InlinableString>>(), size_of::<String>());
+//! ```
+
+use alloc::string::String;
+use core::convert::Infallible;
+use core::mem::{self, ManuallyDrop};
+use core::ops;
+use {StringExt, TryReserveError};
+
+/// A heap-allocated string type that an `InlinableString` can be promoted to.
+///
+/// # Safety
+///
+/// `pack` must return `bits` less than `0x80`, and `unpack` must rebuild the
+/// string that was packed.
+///
+/// The string data a value dereferences to must not be stored inside the
+/// value itself, so that it stays where it is when the value is moved,
+/// packed or unpacked. `InlinableString` also reads a packed string by
+/// unpacking a bitwise copy of it, which it only uses through shared
+/// references and then forgets without dropping it.
+pub unsafe trait HeapStorage: StringExt + ops::DerefMut<Target = str> {
+    /// The form the string takes inside an `InlinableString`.
+    type Packed;
+
+    /// The allocator the string can be created with. Types that always use
+    /// the global allocator set this to an uninhabited type such as
+    /// `Infallible`, which takes up no room in an `InlinableString`.
+    type Allocator: Clone;
+
+    /// Creates an empty string with room for at least `capacity` bytes,
+    /// allocated from `alloc`, or from the default allocator if it is `None`.
+    fn allocate(capacity: usize, alloc: Option<&Self::Allocator>) -> Self;
+
+    /// Like `allocate`, but returns an error instead of panicking if the
+    /// allocation fails.
+    fn try_allocate(
+        capacity: usize,
+        alloc: Option<&Self::Allocator>,
+    ) -> Result<Self, TryReserveError>;
+
+    /// Returns a clone of the allocator the string was created with, if it
+    /// has one.
+    fn clone_allocator(&self) -> Option<Self::Allocator>;
+
+    /// Converts the string into its packed form, plus seven more bits of
+    /// data that get stored in the tag byte.
+    fn pack(self) -> (Self::Packed, u8);
+
+    /// Converts a packed string back.
+    ///
+    /// # Safety
+    ///
+    /// `packed` and `bits` have to be the result of a call to `pack`.
+    unsafe fn unpack(packed: Self::Packed, bits: u8) -> Self;
+}
+
+/// The packed form of a `String`: its pointer, its length and the low bytes
+/// of its capacity.
+///
+/// A `String`'s capacity never exceeds `isize::MAX`, so its high byte fits in
+/// the seven bits that `pack` returns alongside.
+#[repr(C, packed)]
+pub struct PackedString {
+    ptr: *mut u8,
+    len: usize,
+    capacity: [u8; mem::size_of::<usize>() - 1],
+}
+
+// SAFETY: a `PackedString` owns its buffer, just like the `String` it was
+// packed from.
+unsafe impl Send for PackedString {}
+unsafe impl Sync for PackedString {}
+
+impl PackedString {
+    /// Packs the raw parts of a string, returning the high byte of
+    /// `capacity` separately.
+    #[inline]
+    pub(crate) fn new(ptr: *mut u8, len: usize, capacity: usize) -> (PackedString, u8) {
+        let bytes = capacity.to_le_bytes();
+        let (low, high) = bytes.split_at(bytes.len() - 1);
+        let mut packed = PackedString {
+            ptr,
+            len,
+            capacity: [0; mem::size_of::<usize>() - 1],
+        };
+        packed.capacity.copy_from_slice(low);
+        (packed, high[0])
+    }
+
+    #[inline]
+    pub(crate) fn ptr(&self) -> *mut u8 {
+        self.ptr
+    }
+
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the capacity, given the high byte that `new` returned.
+    #[inline]
+    pub(crate) fn capacity(&self, high: u8) -> usize {
+        let mut bytes = [high; mem::size_of::<usize>()];
+        bytes[..mem::size_of::<usize>() - 1].copy_from_slice(&self.capacity);
+        usize::from_le_bytes(bytes)
+    }
+}
+
+unsafe impl HeapStorage for String {
+    type Packed = PackedString;
+    type Allocator = Infallible;
+
+    #[inline]
+    fn allocate(capacity: usize, _: Option<&Infallible>) -> String {
+        String::with_capacity(capacity)
+    }
+
+    #[inline]
+    fn try_allocate(capacity: usize, _: Option<&Infallible>) -> Result<String, TryReserveError> {
+        StringExt::try_with_capacity(capacity)
+    }
+
+    #[inline]
+    fn clone_allocator(&self) -> Option<Infallible> {
+        None
+    }
+
+    #[inline]
+    fn pack(self) -> (PackedString, u8) {
+        let mut s = ManuallyDrop::new(self);
+        // Take the pointer from the `Vec`, as going through a `&mut str`
+        // would invalidate the references handed out to the contents.
+        let vec = unsafe { s.as_mut_vec() };
+        PackedString::new(vec.as_mut_ptr(), vec.len(), vec.capacity())
+    }
+
+    #[inline]
+    unsafe fn unpack(packed: PackedString, bits: u8) -> String {
+        String::from_raw_parts(packed.ptr(), packed.len(), packed.capacity(bits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeapStorage, PackedString};
+    use std::mem::size_of;
+    use std::string::String;
+
+    #[test]
+    fn test_size() {
+        assert_eq!(size_of::<PackedString>(), size_of::<String>() - 1);
+    }
+
+    #[test]
+    fn test_pack_string() {
+        let mut s = String::with_capacity(1000);
+        s.push_str("packed");
+        let ptr = s.as_ptr();
+
+        let (packed, bits) = s.pack();
+        assert!(bits < 0x80);
+        let s = unsafe { String::unpack(packed, bits) };
+        assert_eq!(s, "packed");
+        assert_eq!(s.as_ptr(), ptr);
+        assert_eq!(s.capacity(), 1000);
+    }
+
+    #[test]
+    fn test_capacity_high_byte() {
+        let max = isize::MAX as usize;
+        let (packed, high) = PackedString::new(std::ptr::null_mut(), 0, max);
+        assert_eq!(high, 0x7F);
+        assert_eq!(packed.capacity(high), max);
+    }
+}
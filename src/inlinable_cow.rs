@@ -0,0 +1,698 @@
+// Copyright 2015, The inlinable_string crate Developers. See the COPYRIGHT file
+// at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT
+// or http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A clone-on-write string that either borrows a `&'a str` or owns its
+//! contents the way an `InlinableString` does.
+//!
+//! `InlinableCow` is to `InlinableString` what `std::borrow::Cow<'a, str>` is
+//! to `String`: borrowed strings are kept borrowed until they are first
+//! mutated, at which point they are copied into inline storage if they fit,
+//! or onto the heap otherwise.
+//!
+//! # Examples
+//!
+//! ```
+//! use inlinable_string::{InlinableCow, StringExt};
+//!
+//! let text = String::from("some text owned by somebody else");
+//! let mut s: InlinableCow = InlinableCow::from(&text[..4]);
+//! assert!(matches!(s, InlinableCow::Borrowed(_)));
+//!
+//! // The first mutation copies the borrowed string, inline if it fits.
+//! s.push_str("thing");
+//! assert!(matches!(s, InlinableCow::Inline(_)));
+//! assert_eq!(s, "something");
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::{FromUtf16Error, FromUtf8Error, String};
+use alloc::vec::Vec;
+use core::borrow::{Borrow, BorrowMut};
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash;
+use core::mem;
+use core::ops::{self, RangeBounds};
+use string_ext;
+use {
+    FallibleStringMut, GrowthPolicy, HeapStorage, InlinableString, InlineString, StrBuf, StringExt,
+    StringStorage, TryReserveError, INLINABLE_STRING_CAPACITY,
+};
+
+/// A string that is either borrowed, or owned and stored inline or on the
+/// heap.
+///
+/// `N` and `H` are the inline capacity and the heap-allocated string type,
+/// as for `InlinableString<N, H>`.
+///
+/// See the [module level documentation](./index.html) for more.
+#[derive(Clone)]
+pub enum InlinableCow<'a, const N: usize = INLINABLE_STRING_CAPACITY, H = String> {
+    /// A borrowed string. It gets copied into `Inline` or `Heap` storage on
+    /// first mutation, except by `truncate` and `split_off`, which only
+    /// re-slice it.
+    Borrowed(&'a str),
+    /// A small owned string stored inline.
+    Inline(InlineString<N>),
+    /// A heap-allocated owned string.
+    Heap(H),
+}
+
+/// Writes the owned string back into the `InlinableCow` it was taken from,
+/// even if the operation performed on it panics.
+struct WriteBack<'s, 'a, const N: usize, H>
+where
+    H: HeapStorage,
+{
+    cow: &'s mut InlinableCow<'a, N, H>,
+    owned: InlinableString<N, H>,
+}
+
+impl<'s, 'a, const N: usize, H> Drop for WriteBack<'s, 'a, N, H>
+where
+    H: HeapStorage,
+{
+    fn drop(&mut self) {
+        *self.cow = InlinableCow::from(mem::take(&mut self.owned));
+    }
+}
+
+impl<'a, const N: usize, H> InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    /// Converts into an owned `InlinableString`, copying the string if it is
+    /// borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableCow, InlinableString};
+    ///
+    /// let s: InlinableCow = InlinableCow::from("borrowed");
+    /// let owned: InlinableString = s.into_owned();
+    /// assert!(owned.is_inline());
+    /// assert_eq!(owned, "borrowed");
+    /// ```
+    #[inline]
+    pub fn into_owned(self) -> InlinableString<N, H> {
+        match self {
+            InlinableCow::Borrowed(s) => InlinableString::<N, H>::from(s),
+            InlinableCow::Inline(s) => InlinableString::from(StringStorage::Inline(s)),
+            InlinableCow::Heap(s) => InlinableString::from(StringStorage::Heap(s)),
+        }
+    }
+
+    /// Returns `true` if the string is still borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableCow, StringExt};
+    ///
+    /// let mut s: InlinableCow = InlinableCow::from("borrowed");
+    /// assert!(s.is_borrowed());
+    ///
+    /// s.push('!');
+    /// assert!(!s.is_borrowed());
+    /// ```
+    #[inline]
+    pub fn is_borrowed(&self) -> bool {
+        matches!(*self, InlinableCow::Borrowed(_))
+    }
+
+    /// Runs `f` on the owned version of this string, first copying it out of
+    /// the borrowed state with room for `additional` more bytes if needed.
+    #[inline]
+    fn with_owned<R, F>(&mut self, additional: usize, f: F) -> R
+    where
+        F: FnOnce(&mut InlinableString<N, H>) -> R,
+    {
+        let owned = match mem::replace(self, InlinableCow::Borrowed("")) {
+            InlinableCow::Borrowed(s) => {
+                let mut owned = InlinableString::<N, H>::with_capacity(s.len() + additional);
+                owned.push_str(s);
+                owned
+            }
+            InlinableCow::Inline(s) => InlinableString::from(StringStorage::Inline(s)),
+            InlinableCow::Heap(s) => InlinableString::from(StringStorage::Heap(s)),
+        };
+        let mut guard = WriteBack { cow: self, owned };
+        f(&mut guard.owned)
+    }
+}
+
+impl<'a, const N: usize, H> From<&'a str> for InlinableCow<'a, N, H> {
+    #[inline]
+    fn from(string: &'a str) -> Self {
+        InlinableCow::Borrowed(string)
+    }
+}
+
+impl<'a, const N: usize, H> From<String> for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    #[inline]
+    fn from(string: String) -> Self {
+        InlinableCow::from(InlinableString::<N, H>::from(string))
+    }
+}
+
+impl<'a, const N: usize, H> From<Cow<'a, str>> for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    #[inline]
+    fn from(string: Cow<'a, str>) -> Self {
+        match string {
+            Cow::Borrowed(s) => InlinableCow::Borrowed(s),
+            Cow::Owned(s) => InlinableCow::from(s),
+        }
+    }
+}
+
+impl<'a, const N: usize, H, P> From<InlinableString<N, H, P>> for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn from(string: InlinableString<N, H, P>) -> Self {
+        match string.into_storage() {
+            StringStorage::Heap(s) => InlinableCow::Heap(s),
+            StringStorage::Inline(s) => InlinableCow::Inline(s),
+            StringStorage::Static(s) => InlinableCow::Borrowed(s),
+        }
+    }
+}
+
+impl<'a, const N: usize, H> From<InlinableCow<'a, N, H>> for Cow<'a, str>
+where
+    H: Into<String>,
+{
+    #[inline]
+    fn from(string: InlinableCow<'a, N, H>) -> Self {
+        match string {
+            InlinableCow::Borrowed(s) => Cow::Borrowed(s),
+            InlinableCow::Inline(s) => Cow::Owned(String::from(s)),
+            InlinableCow::Heap(s) => Cow::Owned(s.into()),
+        }
+    }
+}
+
+impl<'a, const N: usize, H> From<InlinableCow<'a, N, H>> for String
+where
+    H: Into<String>,
+{
+    #[inline]
+    fn from(string: InlinableCow<'a, N, H>) -> Self {
+        match string {
+            InlinableCow::Borrowed(s) => String::from(s),
+            InlinableCow::Inline(s) => String::from(s),
+            InlinableCow::Heap(s) => s.into(),
+        }
+    }
+}
+
+impl<'a, const N: usize, H> Default for InlinableCow<'a, N, H> {
+    #[inline]
+    fn default() -> Self {
+        InlinableCow::Borrowed("")
+    }
+}
+
+impl<'a, const N: usize, H> ops::Deref for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        match *self {
+            InlinableCow::Borrowed(s) => s,
+            InlinableCow::Inline(ref s) => s,
+            InlinableCow::Heap(ref s) => s,
+        }
+    }
+}
+
+impl<'a, const N: usize, H> ops::DerefMut for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut str {
+        if let InlinableCow::Borrowed(s) = *self {
+            *self = InlinableCow::from(InlinableString::<N, H>::from(s));
+        }
+        match *self {
+            InlinableCow::Inline(ref mut s) => s,
+            InlinableCow::Heap(ref mut s) => s,
+            InlinableCow::Borrowed(_) => unreachable!(),
+        }
+    }
+}
+
+impl<'a, const N: usize, H> AsRef<str> for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl<'a, const N: usize, H> Borrow<str> for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl<'a, const N: usize, H> BorrowMut<str> for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    fn borrow_mut(&mut self) -> &mut str {
+        self
+    }
+}
+
+impl<'a, const N: usize, H> fmt::Debug for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, const N: usize, H> fmt::Display for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<'a, const N: usize, H> fmt::Write for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    fn write_char(&mut self, ch: char) -> Result<(), fmt::Error> {
+        self.push(ch);
+        Ok(())
+    }
+    fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl<'a, const N: usize, H> hash::Hash for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    #[inline]
+    fn hash<Hs: hash::Hasher>(&self, hasher: &mut Hs) {
+        (**self).hash(hasher)
+    }
+}
+
+impl<'a, const N: usize, H> PartialOrd for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
+        Some(Ord::cmp(self, rhs))
+    }
+}
+
+impl<'a, const N: usize, H> Ord for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    #[inline]
+    fn cmp(&self, rhs: &Self) -> Ordering {
+        Ord::cmp(&**self, &**rhs)
+    }
+}
+
+impl<'a, const N: usize, H> PartialEq for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    #[inline]
+    fn eq(&self, rhs: &Self) -> bool {
+        PartialEq::eq(&**self, &**rhs)
+    }
+}
+
+impl<'a, const N: usize, H> Eq for InlinableCow<'a, N, H> where H: HeapStorage {}
+
+macro_rules! impl_eq {
+    ($lhs:ty, $rhs: ty) => {
+        impl<'a, 'b, const N: usize, H> PartialEq<$rhs> for $lhs
+        where
+            H: HeapStorage,
+        {
+            #[inline]
+            fn eq(&self, other: &$rhs) -> bool {
+                PartialEq::eq(&self[..], &other[..])
+            }
+        }
+
+        impl<'a, 'b, const N: usize, H> PartialEq<$lhs> for $rhs
+        where
+            H: HeapStorage,
+        {
+            #[inline]
+            fn eq(&self, other: &$lhs) -> bool {
+                PartialEq::eq(&self[..], &other[..])
+            }
+        }
+    };
+}
+
+impl_eq! { InlinableCow<'a, N, H>, str }
+impl_eq! { InlinableCow<'a, N, H>, String }
+impl_eq! { InlinableCow<'a, N, H>, &'b str }
+impl_eq! { InlinableCow<'a, N, H>, InlinableString<N, H> }
+impl_eq! { Cow<'b, str>, InlinableCow<'a, N, H> }
+
+impl<'a, const N: usize, H> StrBuf for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    #[inline]
+    fn as_str(&self) -> &str {
+        self
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        match *self {
+            InlinableCow::Borrowed(s) => s.len(),
+            InlinableCow::Inline(_) => N,
+            InlinableCow::Heap(ref s) => s.capacity(),
+        }
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        str::len(self)
+    }
+
+    #[inline]
+    fn as_bytes(&self) -> &[u8] {
+        str::as_bytes(self)
+    }
+}
+
+impl<'a, const N: usize, H> FallibleStringMut for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    type Error = TryReserveError;
+
+    #[inline]
+    fn try_push_str(&mut self, string: &str) -> Result<(), TryReserveError> {
+        string_ext::reserve_and_push_str(self, string)
+    }
+
+    #[inline]
+    fn try_insert_str(&mut self, idx: usize, string: &str) -> Result<(), TryReserveError> {
+        string_ext::reserve_and_insert_str(self, idx, string)
+    }
+
+    #[inline]
+    fn try_replace_range<R>(&mut self, range: R, replace_with: &str) -> Result<(), TryReserveError>
+    where
+        R: RangeBounds<usize>,
+    {
+        string_ext::reserve_and_replace_range(self, range, replace_with)
+    }
+}
+
+impl<'a, const N: usize, H> StringExt for InlinableCow<'a, N, H>
+where
+    H: HeapStorage,
+{
+    #[inline]
+    fn new() -> Self {
+        InlinableCow::Borrowed("")
+    }
+
+    #[inline]
+    fn with_capacity(capacity: usize) -> Self {
+        InlinableCow::from(InlinableString::<N, H>::with_capacity(capacity))
+    }
+
+    #[inline]
+    fn from_utf8(vec: Vec<u8>) -> Result<Self, FromUtf8Error> {
+        InlinableString::<N, H>::from_utf8(vec).map(InlinableCow::from)
+    }
+
+    #[inline]
+    fn from_utf16(v: &[u16]) -> Result<Self, FromUtf16Error> {
+        InlinableString::<N, H>::from_utf16(v).map(InlinableCow::from)
+    }
+
+    #[inline]
+    fn from_utf16_lossy(v: &[u16]) -> Self {
+        InlinableCow::from(InlinableString::<N, H>::from_utf16_lossy(v))
+    }
+
+    #[inline]
+    unsafe fn from_utf8_unchecked(bytes: Vec<u8>) -> Self {
+        InlinableCow::from(InlinableString::<N, H>::from_utf8_unchecked(bytes))
+    }
+
+    #[inline]
+    fn push_str(&mut self, string: &str) {
+        self.with_owned(string.len(), |s| s.push_str(string))
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        self.with_owned(additional, |s| s.reserve(additional))
+    }
+
+    #[inline]
+    fn reserve_exact(&mut self, additional: usize) {
+        self.with_owned(additional, |s| s.reserve_exact(additional))
+    }
+
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        if !self.is_borrowed() {
+            self.with_owned(0, |s| s.shrink_to_fit())
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, ch: char) {
+        self.with_owned(ch.len_utf8(), |s| s.push(ch))
+    }
+
+    #[inline]
+    fn truncate(&mut self, new_len: usize) {
+        if let InlinableCow::Borrowed(s) = *self {
+            if new_len < s.len() {
+                // Keep borrowing the part that is kept.
+                *self = InlinableCow::Borrowed(&s[..new_len]);
+            }
+            return;
+        }
+        self.with_owned(0, |s| s.truncate(new_len))
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<char> {
+        self.with_owned(0, |s| s.pop())
+    }
+
+    #[inline]
+    fn remove(&mut self, idx: usize) -> char {
+        self.with_owned(0, |s| s.remove(idx))
+    }
+
+    #[inline]
+    fn remove_range<R>(&mut self, range: R)
+    where
+        R: RangeBounds<usize>,
+    {
+        self.with_owned(0, |s| s.remove_range(range))
+    }
+
+    #[inline]
+    fn insert(&mut self, idx: usize, ch: char) {
+        self.with_owned(ch.len_utf8(), |s| s.insert(idx, ch))
+    }
+
+    #[inline]
+    fn insert_str(&mut self, idx: usize, string: &str) {
+        self.with_owned(string.len(), |s| s.insert_str(idx, string))
+    }
+
+    #[inline]
+    unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
+        str::as_bytes_mut(self)
+    }
+
+    #[inline]
+    fn split_off(&mut self, at: usize) -> Self {
+        if let InlinableCow::Borrowed(s) = *self {
+            let (head, tail) = s.split_at(at);
+            *self = InlinableCow::Borrowed(head);
+            return InlinableCow::Borrowed(tail);
+        }
+        InlinableCow::from(self.with_owned(0, |s| s.split_off(at)))
+    }
+
+    #[inline]
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(char) -> bool,
+    {
+        self.with_owned(0, |s| s.retain(f))
+    }
+
+    #[inline]
+    fn replace_range<R>(&mut self, range: R, replace_with: &str)
+    where
+        R: RangeBounds<usize>,
+    {
+        self.with_owned(0, |s| s.replace_range(range, replace_with))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InlinableCow;
+    use std::borrow::Cow;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::string::String;
+    use {InlinableString, StrBuf, StringExt};
+
+    const LONG_STR: &str = "this is a really long string that is much larger than
+                        INLINE_STRING_CAPACITY and so cannot be stored inline.";
+
+    #[test]
+    fn test_size() {
+        use std::mem::size_of;
+        assert!(size_of::<InlinableCow>() <= size_of::<String>() + size_of::<usize>());
+    }
+
+    #[test]
+    fn test_borrowed_until_mutated() {
+        let owner = String::from(LONG_STR);
+        let mut s: InlinableCow = InlinableCow::from(&owner[..]);
+        assert!(s.is_borrowed());
+        assert_eq!(s.len(), LONG_STR.len());
+        assert_eq!(s.capacity(), LONG_STR.len());
+        s.shrink_to_fit();
+        assert!(s.is_borrowed());
+
+        s.push('!');
+        assert!(matches!(s, InlinableCow::Heap(_)));
+        assert_eq!(s, String::from(LONG_STR) + "!");
+        assert_eq!(owner, LONG_STR);
+    }
+
+    #[test]
+    fn test_promotes_to_inline() {
+        let owner = String::from(LONG_STR);
+        let mut s: InlinableCow = InlinableCow::from(&owner[..4]);
+        s.insert_str(0, "is ");
+        assert!(matches!(s, InlinableCow::Inline(_)));
+        assert_eq!(s, "is this");
+    }
+
+    #[test]
+    fn test_shortening_keeps_borrowing() {
+        let owner = String::from(LONG_STR);
+        let mut s: InlinableCow = InlinableCow::from(&owner[..]);
+        s.truncate(owner.len() + 1);
+        assert!(s.is_borrowed());
+        s.truncate(40);
+        assert!(s.is_borrowed());
+        assert_eq!(s, &LONG_STR[..40]);
+
+        let tail = s.split_off(4);
+        assert!(s.is_borrowed());
+        assert!(tail.is_borrowed());
+        assert_eq!(s, "this");
+        assert_eq!(tail, &LONG_STR[4..40]);
+    }
+
+    #[test]
+    fn test_mutations_match_string() {
+        let mut s: InlinableCow = InlinableCow::from("hello, world");
+        let mut expected = String::from("hello, world");
+
+        s.retain(|c| c != 'l');
+        expected.retain(|c| c != 'l');
+        assert_eq!(s, expected);
+
+        s.replace_range(..3, "HE");
+        expected.replace_range(..3, "HE");
+        assert_eq!(s, expected);
+
+        assert_eq!(s.pop(), expected.pop());
+        assert_eq!(s.remove(1), expected.remove(1));
+        assert_eq!(s.split_off(2), expected.split_off(2));
+        assert_eq!(s, expected);
+
+        s.make_ascii_lowercase();
+        expected.make_ascii_lowercase();
+        assert_eq!(s, expected);
+    }
+
+    #[test]
+    fn test_into_owned() {
+        let s: InlinableCow = InlinableCow::from(LONG_STR);
+        let owned: InlinableString = s.into_owned();
+        assert!(owned.is_heap());
+        assert_eq!(owned, LONG_STR);
+
+        let s: InlinableCow = InlinableCow::from(String::from("small"));
+        assert!(matches!(s, InlinableCow::Inline(_)));
+        assert!(s.into_owned().is_inline());
+    }
+
+    #[test]
+    fn test_cow_interop() {
+        let s: InlinableCow = InlinableCow::from(Cow::Borrowed("borrowed"));
+        assert!(s.is_borrowed());
+        assert_eq!(s, Cow::Borrowed("borrowed"));
+        assert_eq!(Cow::Borrowed("borrowed"), s);
+
+        let s: InlinableCow = InlinableCow::from(Cow::Owned(String::from("owned")));
+        assert!(!s.is_borrowed());
+        assert_eq!(s, Cow::<str>::Owned(String::from("owned")));
+
+        let borrowed: InlinableCow = InlinableCow::from("x");
+        assert!(matches!(Cow::from(borrowed), Cow::Borrowed("x")));
+    }
+
+    #[test]
+    fn test_panic_keeps_contents() {
+        let mut s: InlinableCow = InlinableCow::from("héllo");
+        let result = panic::catch_unwind(AssertUnwindSafe(|| s.insert(2, '!')));
+        assert!(result.is_err());
+        assert_eq!(s, "héllo");
+    }
+
+    crate::string_ext_conformance_tests!(conformance, InlinableCow<'static>);
+}
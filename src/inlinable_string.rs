@@ -0,0 +1,2736 @@
+// Copyright 2015, The inlinable_string crate Developers. See the COPYRIGHT file
+// at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT
+// or http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::{FromUtf16Error, FromUtf8Error, String};
+use alloc::vec::Vec;
+use core::borrow::{Borrow, BorrowMut};
+use core::cmp::{self, Ordering};
+use core::convert::TryFrom;
+use core::fmt;
+use core::hash;
+use core::iter;
+use core::marker::PhantomData;
+use core::mem::{self, ManuallyDrop};
+use core::num::NonZeroU8;
+use core::ops::{self, RangeBounds};
+use core::ptr;
+use core::str;
+use str_buf;
+use string_ext;
+use {
+    FallibleStringMut, GrowthPolicy, HeapStorage, InlineString, NeverDemote, StrBuf, StringExt,
+    TryReserveError,
+};
+
+/// The default capacity (in bytes) of an `InlinableString`'s inline storage.
+///
+/// The inline bytes share their space with the heap-allocated string and are
+/// followed by a one byte tag, so that an `InlinableString` is exactly as big
+/// as a `String`.
+pub const INLINABLE_STRING_CAPACITY: usize = mem::size_of::<String>() - 1;
+
+/// The tag of a `Static` string. The tag of an `Inline` string is its length
+/// plus one, and that of a `Heap` string has `HEAP_TAG` set.
+const STATIC_TAG: u8 = 0x7F;
+
+/// The bit set in the tag of a `Heap` string. The other seven bits hold the
+/// bits returned by `HeapStorage::pack`.
+const HEAP_TAG: u8 = 0x80;
+
+#[inline]
+const fn nonzero_tag(tag: u8) -> NonZeroU8 {
+    match NonZeroU8::new(tag) {
+        Some(tag) => tag,
+        None => unreachable!(),
+    }
+}
+
+/// An owned, grow-able UTF-8 string that allocates short strings inline on the
+/// stack.
+///
+/// `N` is the capacity of the inline storage (see `InlineString<N>`), and `H`
+/// is the heap-allocated string type the inline string gets promoted to once
+/// it outgrows `N` bytes. `H` may be any
+/// [`HeapStorage`](./heap_storage/trait.HeapStorage.html) implementation. The
+/// length of an inline string is kept in the tag byte, so `N` may not exceed
+/// 125.
+///
+/// `P` is the [`GrowthPolicy`](./growth_policy/trait.GrowthPolicy.html)
+/// deciding how much room a string gets when it is promoted to the heap, and
+/// whether it moves back inline once it shrinks. It defaults to
+/// `NeverDemote`.
+///
+/// [`is_inline`](#method.is_inline), [`is_heap`](#method.is_heap) and
+/// [`is_static`](#method.is_static) tell where a string's contents are kept,
+/// and [`into_storage`](#method.into_storage) takes them out as a
+/// [`StringStorage`](./enum.StringStorage.html).
+///
+/// # Memory layout
+///
+/// The inline bytes, the packed heap-allocated string and the borrowed
+/// `'static` string all share the same bytes, which are followed by a one
+/// byte tag:
+///
+/// * an inline string's tag is its length plus one;
+/// * a static string's tag is `0x7F`;
+/// * a heap-allocated string's tag has its high bit set, and keeps the high
+///   byte of a `String`'s capacity (which never exceeds `isize::MAX`) in the
+///   other seven bits.
+///
+/// The tag is never zero, so with the default parameters an
+/// `InlinableString` and an `Option<InlinableString>` are both exactly the
+/// size of a `String`, and hold up to
+/// [`INLINABLE_STRING_CAPACITY`](./constant.INLINABLE_STRING_CAPACITY.html)
+/// (23 on 64-bit targets) bytes inline.
+///
+/// ```
+/// use std::mem::size_of;
+/// use inlinable_string::InlinableString;
+///
+/// assert_eq!(size_of::<InlinableString>(), size_of::<String>());
+/// assert_eq!(size_of::<Option<InlinableString>>(), size_of::<String>());
+/// ```
+///
+/// Bigger inline capacities make the string bigger too.
+///
+/// ```
+/// use inlinable_string::{InlinableString, StringExt};
+///
+/// let mut key: InlinableString<62> = InlinableString::from("user:4f1c2e9a:profile");
+/// assert!(key.is_inline());
+///
+/// key.push_str("/avatars/large/original-upload-2026-10-16.png");
+/// assert!(key.is_heap());
+/// ```
+///
+/// A capacity whose length does not fit the tag is rejected at compile time:
+///
+/// ```compile_fail
+/// use inlinable_string::InlinableString;
+///
+/// let s = InlinableString::<126>::from_static("too big");
+/// ```
+///
+/// See the [module level documentation](./index.html) for more.
+pub struct InlinableString<
+    const N: usize = INLINABLE_STRING_CAPACITY,
+    H: HeapStorage = String,
+    P = NeverDemote,
+> {
+    body: Body<N, H>,
+    tag: NonZeroU8,
+    // The allocator that inline contents get promoted into, `H`'s default if
+    // `None`. It is kept while the contents are inline, so it takes up no
+    // room for heap types without an allocator.
+    alloc: Option<H::Allocator>,
+    // Aligns the body for `H`, and makes the string `Send` and `Sync` only if
+    // `H` is.
+    _align: [H; 0],
+    policy: PhantomData<P>,
+}
+
+/// The bytes of an `InlinableString`, told apart by the tag.
+#[repr(C)]
+union Body<const N: usize, H: HeapStorage> {
+    inline: [u8; N],
+    heap: ManuallyDrop<H::Packed>,
+    static_str: StaticStr,
+}
+
+/// A `&'static str` without alignment, so that the body only gets aligned
+/// for `H`.
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+struct StaticStr(&'static str);
+
+/// Where an `InlinableString` keeps its contents.
+///
+/// `InlinableString::into_storage` takes a string apart into its storage, and
+/// `From<StringStorage>` puts one back together.
+///
+/// # Examples
+///
+/// ```
+/// use inlinable_string::{InlinableString, StringStorage};
+///
+/// let s: InlinableString = InlinableString::from("small");
+/// match s.into_storage() {
+///     StringStorage::Inline(inline) => assert_eq!(inline, "small"),
+///     _ => unreachable!(),
+/// }
+///
+/// let s: InlinableString = InlinableString::from(StringStorage::Heap(String::from("heap")));
+/// assert!(s.is_heap());
+/// ```
+#[derive(Clone, Debug)]
+pub enum StringStorage<const N: usize = INLINABLE_STRING_CAPACITY, H = String> {
+    /// A heap-allocated string.
+    Heap(H),
+    /// A small string stored inline.
+    Inline(InlineString<N>),
+    /// A string with `'static` lifetime that is borrowed rather than copied.
+    /// It gets copied into `Inline` or `Heap` storage on first mutation, except
+    /// by `truncate` and `split_off`, which only re-slice it.
+    Static(&'static str),
+}
+
+impl<const N: usize, H: HeapStorage, P> InlinableString<N, H, P> {
+    /// Compile-time check that the length of every inline string fits into
+    /// the tag.
+    const CAPACITY_FITS: () = assert!(
+        N < STATIC_TAG as usize - 1,
+        "inlinable_string: `InlinableString` capacity may not exceed 125 bytes"
+    );
+
+    #[inline]
+    const fn from_parts(body: Body<N, H>, tag: u8) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CAPACITY_FITS;
+
+        InlinableString {
+            body,
+            tag: nonzero_tag(tag),
+            alloc: None,
+            _align: [],
+            policy: PhantomData,
+        }
+    }
+
+    #[inline]
+    const fn inline(s: InlineString<N>) -> Self {
+        let (bytes, len) = s.into_raw_parts();
+        InlinableString::from_parts(Body { inline: bytes }, len as u8 + 1)
+    }
+
+    #[inline]
+    const fn borrowed(s: &'static str) -> Self {
+        InlinableString::from_parts(
+            Body {
+                static_str: StaticStr(s),
+            },
+            STATIC_TAG,
+        )
+    }
+
+    #[inline]
+    fn heap(s: H) -> Self {
+        InlinableString::from_storage(StringStorage::Heap(s))
+    }
+
+    /// Packs `storage` into a body and a tag.
+    #[inline]
+    fn pack_storage(storage: StringStorage<N, H>) -> (Body<N, H>, u8) {
+        match storage {
+            StringStorage::Heap(s) => {
+                let (packed, bits) = s.pack();
+                debug_assert!(
+                    bits & HEAP_TAG == 0,
+                    "inlinable_string: internal error: packed bits overlap the heap tag"
+                );
+                let body = Body {
+                    heap: ManuallyDrop::new(packed),
+                };
+                (body, HEAP_TAG | bits)
+            }
+            StringStorage::Inline(s) => {
+                let (bytes, len) = s.into_raw_parts();
+                (Body { inline: bytes }, len as u8 + 1)
+            }
+            StringStorage::Static(s) => (
+                Body {
+                    static_str: StaticStr(s),
+                },
+                STATIC_TAG,
+            ),
+        }
+    }
+
+    /// Creates a string holding `storage`, which keeps the allocator of a
+    /// heap-allocated string.
+    #[inline]
+    fn from_storage(storage: StringStorage<N, H>) -> Self {
+        let alloc = match storage {
+            StringStorage::Heap(ref s) => s.clone_allocator(),
+            _ => None,
+        };
+        let (body, tag) = Self::pack_storage(storage);
+        let mut string = InlinableString::from_parts(body, tag);
+        string.alloc = alloc;
+        string
+    }
+
+    /// Creates a string holding `storage`, with the same allocator as
+    /// `self`.
+    #[inline]
+    fn with_storage(&self, storage: StringStorage<N, H>) -> Self {
+        let (body, tag) = Self::pack_storage(storage);
+        let mut string = InlinableString::from_parts(body, tag);
+        string.alloc = self.alloc.clone();
+        string
+    }
+
+    /// Replaces the storage of the string, keeping its allocator.
+    #[inline]
+    fn set_storage(&mut self, storage: StringStorage<N, H>) {
+        let (body, tag) = Self::pack_storage(storage);
+        let old_body = mem::replace(&mut self.body, body);
+        let old_tag = mem::replace(&mut self.tag, nonzero_tag(tag)).get();
+        if old_tag & HEAP_TAG != 0 {
+            // SAFETY: the tag says the old body held a packed heap string,
+            // which the string no longer owns.
+            let packed = ManuallyDrop::into_inner(unsafe { old_body.heap });
+            drop(unsafe { H::unpack(packed, old_tag & !HEAP_TAG) });
+        }
+    }
+
+    /// Creates an empty heap-allocated string with room for `capacity`
+    /// bytes, from the string's allocator.
+    #[inline]
+    fn allocate(&self, capacity: usize) -> H {
+        H::allocate(capacity, self.alloc.as_ref())
+    }
+
+    /// Like `allocate`, but returns an error if the allocation fails.
+    #[inline]
+    fn try_allocate(&self, capacity: usize) -> Result<H, TryReserveError> {
+        H::try_allocate(capacity, self.alloc.as_ref())
+    }
+
+    /// Reads the storage out of the string, without taking it away.
+    ///
+    /// Safety
+    ///
+    /// A heap-allocated string is then owned twice: the result may only be
+    /// used through shared references and forgotten, or the string itself
+    /// has to be forgotten or overwritten without being dropped.
+    #[inline]
+    unsafe fn read_storage(&self) -> StringStorage<N, H> {
+        let tag = self.tag.get();
+        if tag & HEAP_TAG != 0 {
+            let packed = ManuallyDrop::into_inner(ptr::read(&self.body.heap));
+            StringStorage::Heap(H::unpack(packed, tag & !HEAP_TAG))
+        } else if tag == STATIC_TAG {
+            StringStorage::Static(self.body.static_str.0)
+        } else {
+            StringStorage::Inline(InlineString::from_raw_parts(
+                self.body.inline,
+                tag as usize - 1,
+            ))
+        }
+    }
+
+    /// Returns a view of the storage.
+    #[inline]
+    fn storage(&self) -> StorageRef<'_, N, H> {
+        StorageRef {
+            // SAFETY: the copy is only handed out by shared reference, and
+            // never dropped.
+            storage: ManuallyDrop::new(unsafe { self.read_storage() }),
+            marker: PhantomData,
+        }
+    }
+
+    /// Takes the storage out of the string to be mutated, and puts it back
+    /// when the returned guard is dropped. The string is left empty in the
+    /// meantime.
+    #[inline]
+    fn storage_mut(&mut self) -> StorageMut<'_, N, H, P> {
+        // SAFETY: the string forgets about the storage by becoming an empty
+        // inline string, so it is owned by the guard alone.
+        let storage = unsafe { self.read_storage() };
+        self.tag = NonZeroU8::MIN;
+        StorageMut {
+            string: self,
+            storage: ManuallyDrop::new(storage),
+        }
+    }
+
+    /// Returns the `'static` string that a `Static` string borrows.
+    #[inline]
+    fn as_static(&self) -> Option<&'static str> {
+        if self.tag.get() == STATIC_TAG {
+            // SAFETY: the tag says the body holds a static string.
+            Some(unsafe { self.body.static_str.0 })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn contents(&self) -> &str {
+        let tag = self.tag.get();
+        if tag & HEAP_TAG != 0 {
+            let s: *const str = match *self.storage() {
+                StringStorage::Heap(ref s) => &**s,
+                _ => unreachable!(),
+            };
+            // SAFETY: `HeapStorage` implementations keep their contents
+            // outside of themselves, so they live as long as the string.
+            unsafe { &*s }
+        } else if tag == STATIC_TAG {
+            // SAFETY: the tag says the body holds a static string.
+            unsafe { self.body.static_str.0 }
+        } else {
+            // SAFETY: the tag says the body holds that many bytes of an
+            // inline string.
+            unsafe { str::from_utf8_unchecked(&self.body.inline[..tag as usize - 1]) }
+        }
+    }
+}
+
+impl<const N: usize, H: HeapStorage, P> Drop for InlinableString<N, H, P> {
+    #[inline]
+    fn drop(&mut self) {
+        if self.tag.get() & HEAP_TAG != 0 {
+            // SAFETY: the string is never used again.
+            drop(unsafe { self.read_storage() });
+        }
+    }
+}
+
+/// A view of an `InlinableString`'s storage, see `InlinableString::storage`.
+struct StorageRef<'a, const N: usize, H> {
+    storage: ManuallyDrop<StringStorage<N, H>>,
+    marker: PhantomData<&'a H>,
+}
+
+impl<'a, const N: usize, H> ops::Deref for StorageRef<'a, N, H> {
+    type Target = StringStorage<N, H>;
+
+    #[inline]
+    fn deref(&self) -> &StringStorage<N, H> {
+        &self.storage
+    }
+}
+
+/// The storage taken out of an `InlinableString`, see
+/// `InlinableString::storage_mut`.
+struct StorageMut<'a, const N: usize, H: HeapStorage, P> {
+    string: &'a mut InlinableString<N, H, P>,
+    storage: ManuallyDrop<StringStorage<N, H>>,
+}
+
+impl<'a, const N: usize, H: HeapStorage, P> ops::Deref for StorageMut<'a, N, H, P> {
+    type Target = StringStorage<N, H>;
+
+    #[inline]
+    fn deref(&self) -> &StringStorage<N, H> {
+        &self.storage
+    }
+}
+
+impl<'a, const N: usize, H: HeapStorage, P> ops::DerefMut for StorageMut<'a, N, H, P> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut StringStorage<N, H> {
+        &mut self.storage
+    }
+}
+
+impl<'a, const N: usize, H: HeapStorage, P> Drop for StorageMut<'a, N, H, P> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: the storage is never used again.
+        let storage = unsafe { ManuallyDrop::take(&mut self.storage) };
+        self.string.set_storage(storage);
+    }
+}
+
+impl<const N: usize, H, P> InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    /// Creates a string that borrows `string` instead of copying it. Nothing
+    /// is copied or allocated until the string is first mutated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StringExt};
+    ///
+    /// static GREETING: InlinableString =
+    ///     InlinableString::from_static("a long greeting that does not fit inline");
+    ///
+    /// let mut s = GREETING.clone();
+    /// assert!(s.is_static());
+    ///
+    /// s.push_str("!");
+    /// assert!(s.is_heap());
+    /// assert_eq!(s, "a long greeting that does not fit inline!");
+    /// ```
+    #[inline]
+    pub const fn from_static(string: &'static str) -> Self {
+        InlinableString::borrowed(string)
+    }
+
+    /// Creates a string holding a copy of `s` in `Inline` storage, in a
+    /// `const` context.
+    ///
+    /// This is the `const fn` counterpart of `From<&str>` for strings that
+    /// are known to fit inline; see also the
+    /// [`inline_str!`](./macro.inline_str.html) macro.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s.len()` is greater than `N`. In a `const` or `static`
+    /// initializer, that panic is a compile-time error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::InlinableString;
+    ///
+    /// static DEFAULT_NAME: InlinableString = InlinableString::from_str_const("anonymous");
+    ///
+    /// let name = DEFAULT_NAME.clone();
+    /// assert!(name.is_inline());
+    /// assert_eq!(name, "anonymous");
+    /// ```
+    #[inline]
+    pub const fn from_str_const(s: &str) -> Self {
+        InlinableString::inline(InlineString::from_str_const(s))
+    }
+
+    /// Returns `true` if the string is stored inline.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StringExt};
+    ///
+    /// let mut s: InlinableString = InlinableString::from("small");
+    /// assert!(s.is_inline());
+    ///
+    /// s.push_str(" and now big enough to need the heap");
+    /// assert!(!s.is_inline());
+    /// ```
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        self.tag.get() & HEAP_TAG == 0 && self.tag.get() != STATIC_TAG
+    }
+
+    /// Returns `true` if the string is heap-allocated.
+    #[inline]
+    pub fn is_heap(&self) -> bool {
+        self.tag.get() & HEAP_TAG != 0
+    }
+
+    /// Returns `true` if the string borrows a `'static` string, as created by
+    /// [`from_static`](#method.from_static).
+    #[inline]
+    pub fn is_static(&self) -> bool {
+        self.tag.get() == STATIC_TAG
+    }
+
+    /// Takes the string apart into its storage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StringStorage};
+    ///
+    /// let s: InlinableString = InlinableString::from_static("static");
+    /// assert!(matches!(s.into_storage(), StringStorage::Static("static")));
+    /// ```
+    #[inline]
+    pub fn into_storage(self) -> StringStorage<N, H> {
+        let mut string = ManuallyDrop::new(self);
+        // SAFETY: the string is forgotten, after dropping its allocator.
+        unsafe {
+            ptr::drop_in_place(&mut string.alloc);
+            string.read_storage()
+        }
+    }
+
+    /// Returns the allocator that inline contents get promoted into, if the
+    /// string has one.
+    #[cfg(feature = "allocator-api2")]
+    #[inline]
+    pub(crate) fn heap_allocator(&self) -> Option<&H::Allocator> {
+        self.alloc.as_ref()
+    }
+
+    /// Makes the string promote its inline contents into `alloc`.
+    #[cfg(feature = "allocator-api2")]
+    #[inline]
+    pub(crate) fn set_heap_allocator(&mut self, alloc: H::Allocator) {
+        self.alloc = Some(alloc);
+    }
+
+    /// Runs `f` on the heap-allocated string, if the string is on the heap.
+    #[inline]
+    pub(crate) fn with_heap<R, F>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&H) -> R,
+    {
+        match *self.storage() {
+            StringStorage::Heap(ref s) => Some(f(s)),
+            _ => None,
+        }
+    }
+
+    /// Formats `args` into a new string.
+    ///
+    /// This is what [`format_inline!`](./macro.format_inline.html) expands
+    /// to. Output that fits is stored inline. When the arguments have nothing
+    /// to format, the literal is used as is: copied inline if it fits, or
+    /// borrowed like [`from_static`](#method.from_static) if it doesn't, so
+    /// nothing is allocated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a formatting trait implementation returns an error, like
+    /// `format!` does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::InlinableString;
+    ///
+    /// let s: InlinableString = InlinableString::from_fmt(format_args!("{}-{}", 4, 2));
+    /// assert_eq!(s, "4-2");
+    /// assert!(s.is_inline());
+    /// ```
+    pub fn from_fmt(args: fmt::Arguments) -> Self {
+        match args.as_str() {
+            Some(s) if s.len() > N => InlinableString::from_static(s),
+            Some(s) => InlinableString::from(s),
+            None => {
+                let mut string = Self::new();
+                fmt::Write::write_fmt(&mut string, args).expect(
+                    "a formatting trait implementation returned an error when the \
+                     underlying stream did not",
+                );
+                string
+            }
+        }
+    }
+
+    /// Converts a slice of bytes to a string, replacing any invalid UTF-8
+    /// sequences with U+FFFD REPLACEMENT CHARACTER.
+    ///
+    /// Unlike `StringExt::from_utf8_lossy`, which returns a `Cow<str>`, this
+    /// builds an `InlinableString` directly, and does not allocate if the
+    /// result fits inline.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::InlinableString;
+    ///
+    /// let s: InlinableString = InlinableString::from_utf8_lossy(b"Hello \xF0\x90\x80World");
+    /// assert_eq!(s, "Hello \u{FFFD}World");
+    /// assert!(s.is_inline());
+    /// ```
+    #[inline]
+    pub fn from_utf8_lossy(v: &[u8]) -> Self {
+        // Every invalid sequence is replaced by at least as many bytes, so
+        // the result is never shorter than `v`.
+        let mut s = Self::with_capacity(v.len());
+        for chunk in v.utf8_chunks() {
+            s.push_str(chunk.valid());
+            if !chunk.invalid().is_empty() {
+                s.push(char::REPLACEMENT_CHARACTER);
+            }
+        }
+        s
+    }
+
+    /// Copies a `Static` string into `Inline` or `Heap` storage with room for
+    /// `additional` more bytes, so that it can be mutated in place.
+    #[inline]
+    fn promote_static(&mut self, additional: usize) {
+        if let Some(s) = self.as_static() {
+            let required = s.len() + additional;
+            let promoted = match InlineString::try_from(s) {
+                Ok(inlined) if required <= N => StringStorage::Inline(inlined),
+                _ => {
+                    let mut promoted = self.allocate(P::promoted_capacity(required, N));
+                    promoted.push_str(s);
+                    StringStorage::Heap(promoted)
+                }
+            };
+            self.set_storage(promoted);
+        }
+    }
+
+    /// Like `promote_static`, but returns an error instead of aborting if the
+    /// heap-allocated string cannot be allocated.
+    #[inline]
+    fn try_promote_static(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        if let Some(s) = self.as_static() {
+            let required = string_ext::required_capacity(s.len(), additional)?;
+            let promoted = match InlineString::try_from(s) {
+                Ok(inlined) if required <= N => StringStorage::Inline(inlined),
+                _ => {
+                    let mut promoted = self.try_allocate(P::promoted_capacity(required, N))?;
+                    promoted.push_str(s);
+                    StringStorage::Heap(promoted)
+                }
+            };
+            self.set_storage(promoted);
+        }
+        Ok(())
+    }
+
+    /// Moves a heap-allocated string back into inline storage if it fits and
+    /// the growth policy asks for it.
+    #[inline]
+    fn demote_if_needed(&mut self) {
+        let inlined = match *self.storage() {
+            StringStorage::Heap(ref s) if P::should_demote(s.len(), N) => {
+                InlineString::try_from(&s[..]).ok()
+            }
+            _ => None,
+        };
+        if let Some(inlined) = inlined {
+            self.set_storage(StringStorage::Inline(inlined));
+        }
+    }
+
+    #[inline]
+    fn contents_mut(&mut self) -> &mut str {
+        self.promote_static(0);
+        if self.is_heap() {
+            let s: *mut str = match *self.storage_mut() {
+                StringStorage::Heap(ref mut s) => &mut **s,
+                _ => unreachable!(),
+            };
+            // SAFETY: `HeapStorage` implementations keep their contents
+            // outside of themselves, so they stay put when the guard packs
+            // the string back.
+            unsafe { &mut *s }
+        } else {
+            let len = self.len();
+            // SAFETY: the string is inline, and holds `len` bytes of UTF-8.
+            unsafe { str::from_utf8_unchecked_mut(&mut self.body.inline[..len]) }
+        }
+    }
+}
+
+/// Decodes UTF-16 into inline storage, or returns `None` if the result does
+/// not fit, or if `v` is invalid and `lossy` is false.
+#[inline]
+fn decode_utf16_inline<const N: usize>(v: &[u16], lossy: bool) -> Option<InlineString<N>> {
+    // Every code unit takes at least one byte in UTF-8.
+    if v.len() > N {
+        return None;
+    }
+    let mut s = InlineString::new();
+    for ch in char::decode_utf16(v.iter().cloned()) {
+        let ch = match ch {
+            Ok(ch) => ch,
+            Err(_) if lossy => char::REPLACEMENT_CHARACTER,
+            Err(_) => return None,
+        };
+        s.push(ch).ok()?;
+    }
+    Some(s)
+}
+
+impl<const N: usize, H, P> Clone for InlinableString<N, H, P>
+where
+    H: HeapStorage + Clone,
+{
+    fn clone(&self) -> Self {
+        self.with_storage((*self.storage()).clone())
+    }
+}
+
+impl<const N: usize, H, P> From<StringStorage<N, H>> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn from(storage: StringStorage<N, H>) -> Self {
+        InlinableString::from_storage(storage)
+    }
+}
+
+/// Prints the quoted string, like `str` does. The alternate form, `{:#?}`,
+/// also shows whether the string is stored inline, on the heap or borrowed
+/// from a `'static` string, as well as its length and capacity:
+///
+/// ```
+/// use inlinable_string::{InlinableString, StringExt};
+///
+/// let mut s: InlinableString<8> = InlinableString::from("hello");
+/// assert_eq!(format!("{:?}", s), r#""hello""#);
+/// assert_eq!(
+///     format!("{:#?}", s),
+///     r#"InlinableString {
+///     storage: Inline,
+///     value: "hello",
+///     len: 5,
+///     capacity: 8,
+/// }"#
+/// );
+///
+/// s.push_str(", world");
+/// assert!(format!("{:#?}", s).contains("storage: Heap"));
+/// ```
+impl<const N: usize, H, P> fmt::Debug for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !f.alternate() {
+            return fmt::Debug::fmt(&**self, f);
+        }
+        let storage = match *self.storage() {
+            StringStorage::Heap(_) => "Heap",
+            StringStorage::Inline(_) => "Inline",
+            StringStorage::Static(_) => "Static",
+        };
+        f.debug_struct("InlinableString")
+            .field("storage", &format_args!("{}", storage))
+            .field("value", &&**self)
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+impl<const N: usize, H, P> iter::FromIterator<char> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        let mut buf = Self::new();
+        buf.extend(iter);
+        buf
+    }
+}
+
+impl<'a, const N: usize, H, P> iter::FromIterator<&'a str> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        let mut buf = Self::new();
+        buf.extend(iter);
+        buf
+    }
+}
+
+impl<const N: usize, H, P> Extend<char> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn extend<I: IntoIterator<Item = char>>(&mut self, iterable: I) {
+        let iterator = iterable.into_iter();
+        let (lower_bound, _) = iterator.size_hint();
+        self.reserve(lower_bound);
+        for ch in iterator {
+            self.push(ch);
+        }
+    }
+}
+
+impl<'a, const N: usize, H, P> Extend<&'a char> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn extend<I: IntoIterator<Item = &'a char>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned());
+    }
+}
+
+impl<'a, const N: usize, H, P> Extend<&'a str> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, iterable: I) {
+        let iterator = iterable.into_iter();
+        let (lower_bound, _) = iterator.size_hint();
+        self.reserve(lower_bound);
+        for s in iterator {
+            self.push_str(s);
+        }
+    }
+}
+
+impl<const N: usize, H, P> ops::Add<&str> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    type Output = Self;
+
+    #[inline]
+    fn add(mut self, other: &str) -> Self {
+        self.push_str(other);
+        self
+    }
+}
+
+impl<const N: usize, H, P> PartialOrd for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
+        Some(Ord::cmp(self, rhs))
+    }
+}
+
+impl<const N: usize, H, P> Ord for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn cmp(&self, rhs: &Self) -> Ordering {
+        Ord::cmp(&self[..], &rhs[..])
+    }
+}
+
+impl<const N: usize, H, P> hash::Hash for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn hash<Hs: hash::Hasher>(&self, hasher: &mut Hs) {
+        (**self).hash(hasher)
+    }
+}
+
+impl<const N: usize, H, P> Borrow<str> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl<const N: usize, H, P> BorrowMut<str> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn borrow_mut(&mut self) -> &mut str {
+        self
+    }
+}
+
+impl<const N: usize, H, P> AsRef<str> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl<const N: usize, H, P> AsMut<str> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn as_mut(&mut self) -> &mut str {
+        self
+    }
+}
+
+impl<const N: usize, H, P> From<&str> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn from(string: &str) -> Self {
+        match InlineString::try_from(string) {
+            Ok(s) => InlinableString::inline(s),
+            Err(_) => InlinableString::heap(string_ext::from_string(String::from(string))),
+        }
+    }
+}
+
+impl<const N: usize, H, P> From<String> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn from(string: String) -> Self {
+        match InlineString::try_from(string.as_str()) {
+            Ok(s) => InlinableString::inline(s),
+            Err(_) => InlinableString::heap(string_ext::from_string(string)),
+        }
+    }
+}
+
+impl<const N: usize, H, P> Default for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn default() -> Self {
+        InlinableString::new()
+    }
+}
+
+impl<const N: usize, H, P> fmt::Display for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<const N: usize, H, P> fmt::Write for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    fn write_char(&mut self, ch: char) -> Result<(), fmt::Error> {
+        self.push(ch);
+        Ok(())
+    }
+    fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl<const N: usize, H, P> ops::Index<ops::Range<usize>> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    type Output = str;
+
+    #[inline]
+    fn index(&self, index: ops::Range<usize>) -> &str {
+        self.contents().index(index)
+    }
+}
+
+impl<const N: usize, H, P> ops::Index<ops::RangeTo<usize>> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    type Output = str;
+
+    #[inline]
+    fn index(&self, index: ops::RangeTo<usize>) -> &str {
+        self.contents().index(index)
+    }
+}
+
+impl<const N: usize, H, P> ops::Index<ops::RangeFrom<usize>> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    type Output = str;
+
+    #[inline]
+    fn index(&self, index: ops::RangeFrom<usize>) -> &str {
+        self.contents().index(index)
+    }
+}
+
+impl<const N: usize, H, P> ops::Index<ops::RangeFull> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    type Output = str;
+
+    #[inline]
+    fn index(&self, index: ops::RangeFull) -> &str {
+        self.contents().index(index)
+    }
+}
+
+impl<const N: usize, H, P> ops::IndexMut<ops::Range<usize>> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn index_mut(&mut self, index: ops::Range<usize>) -> &mut str {
+        ops::DerefMut::deref_mut(self).index_mut(index)
+    }
+}
+
+impl<const N: usize, H, P> ops::IndexMut<ops::RangeTo<usize>> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn index_mut(&mut self, index: ops::RangeTo<usize>) -> &mut str {
+        ops::DerefMut::deref_mut(self).index_mut(index)
+    }
+}
+
+impl<const N: usize, H, P> ops::IndexMut<ops::RangeFrom<usize>> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn index_mut(&mut self, index: ops::RangeFrom<usize>) -> &mut str {
+        ops::DerefMut::deref_mut(self).index_mut(index)
+    }
+}
+
+impl<const N: usize, H, P> ops::IndexMut<ops::RangeFull> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn index_mut(&mut self, index: ops::RangeFull) -> &mut str {
+        ops::DerefMut::deref_mut(self).index_mut(index)
+    }
+}
+
+impl<const N: usize, H, P> ops::Deref for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.contents()
+    }
+}
+
+impl<const N: usize, H, P> ops::DerefMut for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut str {
+        self.contents_mut()
+    }
+}
+
+impl<const N: usize, H, P> PartialEq for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn eq(&self, rhs: &Self) -> bool {
+        PartialEq::eq(&self[..], &rhs[..])
+    }
+}
+
+impl<const N: usize, H, P> Eq for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+}
+
+macro_rules! impl_eq {
+    ($lhs:ty, $rhs: ty) => {
+        impl<'a, const N: usize, H, P> PartialEq<$rhs> for $lhs
+        where
+            H: HeapStorage,
+            P: GrowthPolicy,
+        {
+            #[inline]
+            fn eq(&self, other: &$rhs) -> bool {
+                PartialEq::eq(&self[..], &other[..])
+            }
+        }
+
+        impl<'a, const N: usize, H, P> PartialEq<$lhs> for $rhs
+        where
+            H: HeapStorage,
+            P: GrowthPolicy,
+        {
+            #[inline]
+            fn eq(&self, other: &$lhs) -> bool {
+                PartialEq::eq(&self[..], &other[..])
+            }
+        }
+    };
+}
+
+impl_eq! { InlinableString<N, H, P>, str }
+impl_eq! { InlinableString<N, H, P>, String }
+impl_eq! { InlinableString<N, H, P>, &'a str }
+impl_eq! { InlinableString<N, H, P>, InlineString<N> }
+impl_eq! { Cow<'a, str>, InlinableString<N, H, P> }
+
+impl<const N: usize, H, P> StrBuf for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn as_str(&self) -> &str {
+        self
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        match *self.storage() {
+            StringStorage::Heap(ref s) => s.capacity(),
+            StringStorage::Inline(_) => N,
+            StringStorage::Static(s) => s.len(),
+        }
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.contents().len()
+    }
+
+    #[inline]
+    fn as_bytes(&self) -> &[u8] {
+        self.contents().as_bytes()
+    }
+}
+
+impl<const N: usize, H, P> FallibleStringMut for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    type Error = TryReserveError;
+
+    #[inline]
+    fn try_push_str(&mut self, string: &str) -> Result<(), TryReserveError> {
+        self.try_promote_static(string.len())?;
+        let mut guard = self.storage_mut();
+        let promoted = match *guard.storage {
+            StringStorage::Inline(ref mut s) => {
+                if s.push_str(string).is_ok() {
+                    return Ok(());
+                }
+                let required = string_ext::required_capacity(s.len(), string.len())?;
+                let mut promoted = guard
+                    .string
+                    .try_allocate(P::promoted_capacity(required, N))?;
+                promoted.push_str(&*s);
+                promoted.push_str(string);
+                promoted
+            }
+            StringStorage::Heap(ref mut s) => return s.try_push_str(string),
+            _ => unreachable!(),
+        };
+        *guard.storage = StringStorage::Heap(promoted);
+        Ok(())
+    }
+
+    #[inline]
+    fn try_insert_str(&mut self, idx: usize, string: &str) -> Result<(), TryReserveError> {
+        self.try_promote_static(string.len())?;
+        let mut guard = self.storage_mut();
+        let promoted = match *guard.storage {
+            StringStorage::Heap(ref mut s) => return s.try_insert_str(idx, string),
+            StringStorage::Inline(ref mut s) => {
+                if s.insert_str(idx, string).is_ok() {
+                    return Ok(());
+                }
+
+                let required = string_ext::required_capacity(s.len(), string.len())?;
+                let mut promoted = guard
+                    .string
+                    .try_allocate(P::promoted_capacity(required, N))?;
+                promoted.push_str(&s[..idx]);
+                promoted.push_str(string);
+                promoted.push_str(&s[idx..]);
+                promoted
+            }
+            _ => unreachable!(),
+        };
+
+        *guard.storage = StringStorage::Heap(promoted);
+        Ok(())
+    }
+
+    #[inline]
+    fn try_replace_range<R>(&mut self, range: R, replace_with: &str) -> Result<(), TryReserveError>
+    where
+        R: RangeBounds<usize>,
+    {
+        let len = self.len();
+        let (start, end) = str_buf::byte_range(&range, len);
+
+        // String index does all bounds checks.
+        let range_len = self[start..end].len();
+        let new_len = string_ext::required_capacity(len - range_len, replace_with.len())?;
+
+        self.try_promote_static(new_len.saturating_sub(len))?;
+        let mut guard = self.storage_mut();
+        let promoted = match *guard.storage {
+            StringStorage::Heap(ref mut s) => {
+                s.try_replace_range(start..end, replace_with)?;
+                drop(guard);
+                self.demote_if_needed();
+                return Ok(());
+            }
+            StringStorage::Inline(ref mut s) => {
+                if s.replace_range(start..end, replace_with).is_ok() {
+                    return Ok(());
+                }
+
+                let mut promoted = guard
+                    .string
+                    .try_allocate(P::promoted_capacity(new_len, N))?;
+                promoted.push_str(&s[..start]);
+                promoted.push_str(replace_with);
+                promoted.push_str(&s[end..]);
+                promoted
+            }
+            _ => unreachable!(),
+        };
+
+        *guard.storage = StringStorage::Heap(promoted);
+        Ok(())
+    }
+}
+
+impl<const N: usize, H, P> StringExt for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn new() -> Self {
+        InlinableString::inline(InlineString::new())
+    }
+
+    #[inline]
+    fn with_capacity(capacity: usize) -> Self {
+        if capacity <= N {
+            InlinableString::inline(InlineString::new())
+        } else {
+            InlinableString::heap(H::with_capacity(capacity))
+        }
+    }
+
+    #[inline]
+    fn from_utf8(vec: Vec<u8>) -> Result<Self, FromUtf8Error> {
+        if vec.len() <= N {
+            if let Ok(s) = str::from_utf8(&vec) {
+                return Ok(InlinableString::inline(InlineString::try_from(s).unwrap()));
+            }
+        }
+        // Too long to inline, or invalid: `H` builds the error.
+        H::from_utf8(vec).map(InlinableString::heap)
+    }
+
+    #[inline]
+    fn from_utf16(v: &[u16]) -> Result<Self, FromUtf16Error> {
+        match decode_utf16_inline(v, false) {
+            Some(s) => Ok(InlinableString::inline(s)),
+            // Too long to inline, or invalid: `H` builds the error.
+            None => H::from_utf16(v).map(InlinableString::heap),
+        }
+    }
+
+    #[inline]
+    fn from_utf16_lossy(v: &[u16]) -> Self {
+        match decode_utf16_inline(v, true) {
+            Some(s) => InlinableString::inline(s),
+            None => InlinableString::heap(H::from_utf16_lossy(v)),
+        }
+    }
+
+    #[inline]
+    unsafe fn from_raw_parts(buf: *mut u8, length: usize, capacity: usize) -> Self {
+        InlinableString::heap(H::from_raw_parts(buf, length, capacity))
+    }
+
+    #[inline]
+    unsafe fn from_utf8_unchecked(bytes: Vec<u8>) -> Self {
+        if bytes.len() <= N {
+            let s = str::from_utf8_unchecked(&bytes);
+            return InlinableString::inline(InlineString::try_from(s).unwrap());
+        }
+        InlinableString::heap(H::from_utf8_unchecked(bytes))
+    }
+
+    #[inline]
+    fn push_str(&mut self, string: &str) {
+        self.promote_static(string.len());
+        let mut guard = self.storage_mut();
+        let promoted = match *guard.storage {
+            StringStorage::Inline(ref mut s) => {
+                if s.push_str(string).is_ok() {
+                    return;
+                }
+                let mut promoted = guard
+                    .string
+                    .allocate(P::promoted_capacity(string.len() + s.len(), N));
+                promoted.push_str(&*s);
+                promoted.push_str(string);
+                promoted
+            }
+            StringStorage::Heap(ref mut s) => {
+                s.push_str(string);
+                return;
+            }
+            _ => unreachable!(),
+        };
+        *guard.storage = StringStorage::Heap(promoted);
+    }
+
+    #[inline]
+    fn extend_from_within<R>(&mut self, src: R)
+    where
+        R: RangeBounds<usize>,
+    {
+        let (start, end) = str_buf::byte_range(&src, self.len());
+        // String index does all bounds checks.
+        let _ = &self[start..end];
+
+        self.promote_static(end - start);
+        let mut guard = self.storage_mut();
+        let promoted = match *guard.storage {
+            StringStorage::Inline(ref mut s) => {
+                if s.extend_from_within(start..end).is_ok() {
+                    return;
+                }
+                let mut promoted = guard
+                    .string
+                    .allocate(P::promoted_capacity(s.len() + (end - start), N));
+                promoted.push_str(s);
+                promoted.push_str(&s[start..end]);
+                promoted
+            }
+            StringStorage::Heap(ref mut s) => {
+                s.extend_from_within(start..end);
+                return;
+            }
+            _ => unreachable!(),
+        };
+        *guard.storage = StringStorage::Heap(promoted);
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        self.promote_static(additional);
+        let mut guard = self.storage_mut();
+        let promoted = match *guard.storage {
+            StringStorage::Inline(ref s) => {
+                let new_capacity = s.len() + additional;
+                if new_capacity <= N {
+                    return;
+                }
+                let mut promoted = guard.string.allocate(new_capacity);
+                promoted.push_str(s);
+                promoted
+            }
+            StringStorage::Heap(ref mut s) => {
+                s.reserve(additional);
+                return;
+            }
+            _ => unreachable!(),
+        };
+        *guard.storage = StringStorage::Heap(promoted);
+    }
+
+    #[inline]
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.try_promote_static(additional)?;
+        let mut guard = self.storage_mut();
+        let promoted = match *guard.storage {
+            StringStorage::Inline(ref s) => {
+                let new_capacity = string_ext::required_capacity(s.len(), additional)?;
+                if new_capacity <= N {
+                    return Ok(());
+                }
+                let mut promoted = guard.string.try_allocate(new_capacity)?;
+                promoted.push_str(s);
+                promoted
+            }
+            StringStorage::Heap(ref mut s) => return s.try_reserve(additional),
+            _ => unreachable!(),
+        };
+        *guard.storage = StringStorage::Heap(promoted);
+        Ok(())
+    }
+
+    #[inline]
+    fn reserve_exact(&mut self, additional: usize) {
+        self.promote_static(additional);
+        let mut guard = self.storage_mut();
+        let promoted = match *guard.storage {
+            StringStorage::Inline(ref s) => {
+                let new_capacity = s.len() + additional;
+                if new_capacity <= N {
+                    return;
+                }
+                let mut promoted = guard.string.allocate(new_capacity);
+                promoted.push_str(s);
+                promoted
+            }
+            StringStorage::Heap(ref mut s) => {
+                s.reserve_exact(additional);
+                return;
+            }
+            _ => unreachable!(),
+        };
+        *guard.storage = StringStorage::Heap(promoted);
+    }
+
+    #[inline]
+    fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.try_promote_static(additional)?;
+        let mut guard = self.storage_mut();
+        let promoted = match *guard.storage {
+            StringStorage::Inline(ref s) => {
+                let new_capacity = string_ext::required_capacity(s.len(), additional)?;
+                if new_capacity <= N {
+                    return Ok(());
+                }
+                let mut promoted = guard.string.try_allocate(new_capacity)?;
+                promoted.push_str(s);
+                promoted
+            }
+            StringStorage::Heap(ref mut s) => return s.try_reserve_exact(additional),
+            _ => unreachable!(),
+        };
+        *guard.storage = StringStorage::Heap(promoted);
+        Ok(())
+    }
+
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        let inlined = match *self.storage_mut() {
+            StringStorage::Heap(ref mut s) => match InlineString::try_from(&s[..]) {
+                Ok(inlined) => Some(inlined),
+                Err(_) => {
+                    s.shrink_to_fit();
+                    None
+                }
+            },
+            // If already inlined, capacity can't be reduced.
+            _ => None,
+        };
+
+        if let Some(inl) = inlined {
+            self.set_storage(StringStorage::Inline(inl));
+        }
+    }
+
+    #[inline]
+    fn shrink_to(&mut self, min_capacity: usize) {
+        let inlined = match *self.storage_mut() {
+            StringStorage::Heap(ref mut s) if s.capacity() > min_capacity => {
+                if cmp::max(s.len(), min_capacity) <= N {
+                    InlineString::try_from(&s[..]).ok()
+                } else {
+                    s.shrink_to(min_capacity);
+                    None
+                }
+            }
+            // If already inlined, capacity can't be reduced.
+            _ => None,
+        };
+
+        if let Some(inl) = inlined {
+            self.set_storage(StringStorage::Inline(inl));
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, ch: char) {
+        self.promote_static(ch.len_utf8());
+        let mut guard = self.storage_mut();
+        let promoted = match *guard.storage {
+            StringStorage::Inline(ref mut s) => {
+                if s.push(ch).is_ok() {
+                    return;
+                }
+
+                let mut promoted = guard
+                    .string
+                    .allocate(P::promoted_capacity(s.len() + ch.len_utf8(), N));
+                promoted.push_str(s);
+                promoted.push(ch);
+                promoted
+            }
+            StringStorage::Heap(ref mut s) => {
+                s.push(ch);
+                return;
+            }
+            _ => unreachable!(),
+        };
+
+        *guard.storage = StringStorage::Heap(promoted);
+    }
+
+    #[inline]
+    fn truncate(&mut self, new_len: usize) {
+        if let Some(s) = self.as_static() {
+            if new_len < s.len() {
+                // Keep borrowing the part that is kept.
+                self.set_storage(StringStorage::Static(&s[..new_len]));
+            }
+            return;
+        }
+        self.promote_static(0);
+        match *self.storage_mut() {
+            StringStorage::Heap(ref mut s) => s.truncate(new_len),
+            StringStorage::Inline(ref mut s) => s.truncate(new_len),
+            _ => unreachable!(),
+        };
+        self.demote_if_needed();
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<char> {
+        self.promote_static(0);
+        let ch = match *self.storage_mut() {
+            StringStorage::Heap(ref mut s) => s.pop(),
+            StringStorage::Inline(ref mut s) => s.pop(),
+            _ => unreachable!(),
+        };
+        self.demote_if_needed();
+        ch
+    }
+
+    #[inline]
+    fn remove(&mut self, idx: usize) -> char {
+        self.promote_static(0);
+        let ch = match *self.storage_mut() {
+            StringStorage::Heap(ref mut s) => s.remove(idx),
+            StringStorage::Inline(ref mut s) => s.remove(idx),
+            _ => unreachable!(),
+        };
+        self.demote_if_needed();
+        ch
+    }
+
+    #[inline]
+    fn remove_range<R>(&mut self, range: R)
+    where
+        R: RangeBounds<usize>,
+    {
+        self.promote_static(0);
+        match &mut *self.storage_mut() {
+            StringStorage::Heap(s) => s.remove_range(range),
+            StringStorage::Inline(s) => s.remove_range(range),
+            _ => unreachable!(),
+        }
+        self.demote_if_needed();
+    }
+
+    #[inline]
+    fn remove_matches(&mut self, pat: &str) {
+        if let Some(s) = self.as_static() {
+            if pat.is_empty() || !s.contains(pat) {
+                // Nothing to remove, so keep borrowing.
+                return;
+            }
+        }
+        self.promote_static(0);
+        match &mut *self.storage_mut() {
+            StringStorage::Heap(s) => s.remove_matches(pat),
+            StringStorage::Inline(s) => s.remove_matches(pat),
+            _ => unreachable!(),
+        }
+        self.demote_if_needed();
+    }
+
+    #[inline]
+    fn insert(&mut self, idx: usize, ch: char) {
+        self.promote_static(ch.len_utf8());
+        let mut guard = self.storage_mut();
+        let promoted = match *guard.storage {
+            StringStorage::Heap(ref mut s) => {
+                s.insert(idx, ch);
+                return;
+            }
+            StringStorage::Inline(ref mut s) => {
+                if s.insert(idx, ch).is_ok() {
+                    return;
+                }
+
+                let mut promoted = guard
+                    .string
+                    .allocate(P::promoted_capacity(s.len() + ch.len_utf8(), N));
+                promoted.push_str(&s[..idx]);
+                promoted.push(ch);
+                promoted.push_str(&s[idx..]);
+                promoted
+            }
+            _ => unreachable!(),
+        };
+
+        *guard.storage = StringStorage::Heap(promoted);
+    }
+
+    #[inline]
+    fn insert_str(&mut self, idx: usize, string: &str) {
+        self.promote_static(string.len());
+        let mut guard = self.storage_mut();
+        let promoted = match *guard.storage {
+            StringStorage::Heap(ref mut s) => {
+                s.insert_str(idx, string);
+                return;
+            }
+            StringStorage::Inline(ref mut s) => {
+                if s.insert_str(idx, string).is_ok() {
+                    return;
+                }
+
+                let mut promoted = guard
+                    .string
+                    .allocate(P::promoted_capacity(s.len() + string.len(), N));
+                promoted.push_str(&s[..idx]);
+                promoted.push_str(string);
+                promoted.push_str(&s[idx..]);
+                promoted
+            }
+            _ => unreachable!(),
+        };
+
+        *guard.storage = StringStorage::Heap(promoted);
+    }
+
+    #[inline]
+    unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
+        str::as_bytes_mut(self.contents_mut())
+    }
+
+    #[inline]
+    fn into_boxed_str(self) -> Box<str>
+    where
+        Self: Into<String>,
+    {
+        if self.is_heap() {
+            return <String>::into_boxed_str(self.into());
+        }
+        Box::from(&*self)
+    }
+
+    #[inline]
+    fn split_off(&mut self, at: usize) -> Self {
+        if let Some(s) = self.as_static() {
+            let (head, tail) = s.split_at(at);
+            self.set_storage(StringStorage::Static(head));
+            return self.with_storage(StringStorage::Static(tail));
+        }
+        let tail = match &mut *self.storage_mut() {
+            StringStorage::Inline(s) => StringStorage::Inline(s.split_off(at)),
+            StringStorage::Heap(s) => match InlineString::try_from(&s[at..]) {
+                Ok(inlined) => {
+                    s.truncate(at);
+                    StringStorage::Inline(inlined)
+                }
+                Err(_) => StringStorage::Heap(s.split_off(at)),
+            },
+            _ => unreachable!(),
+        };
+        self.demote_if_needed();
+        self.with_storage(tail)
+    }
+
+    #[inline]
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(char) -> bool,
+    {
+        self.promote_static(0);
+        match &mut *self.storage_mut() {
+            StringStorage::Inline(s) => s.retain(f),
+            StringStorage::Heap(s) => s.retain(f),
+            _ => unreachable!(),
+        }
+        self.demote_if_needed();
+    }
+
+    #[inline]
+    fn replace_range<R>(&mut self, range: R, replace_with: &str)
+    where
+        R: RangeBounds<usize>,
+    {
+        let len = self.len();
+        let (start, end) = str_buf::byte_range(&range, len);
+
+        // String index does all bounds checks.
+        let range_len = self[start..end].len();
+        let new_len = len - range_len + replace_with.len();
+
+        self.promote_static(new_len.saturating_sub(len));
+        let mut guard = self.storage_mut();
+        let promoted = match *guard.storage {
+            StringStorage::Heap(ref mut s) => {
+                s.replace_range(start..end, replace_with);
+                drop(guard);
+                self.demote_if_needed();
+                return;
+            }
+            StringStorage::Inline(ref mut s) => {
+                if s.replace_range(start..end, replace_with).is_ok() {
+                    return;
+                }
+
+                let mut promoted = guard.string.allocate(P::promoted_capacity(new_len, N));
+                promoted.push_str(&s[..start]);
+                promoted.push_str(replace_with);
+                promoted.push_str(&s[end..]);
+                promoted
+            }
+            _ => unreachable!(),
+        };
+
+        *guard.storage = StringStorage::Heap(promoted);
+    }
+}
+
+/// Formats into an `InlinableString`.
+///
+/// Takes the same arguments as `format!`, but the result is stored inline
+/// when it fits instead of always being allocated on the heap. To build
+/// another `InlinableString` type, give it before the format string:
+/// `format_inline!(T, "...", args...)`.
+///
+/// See [`InlinableString::from_fmt`](./struct.InlinableString.html#method.from_fmt).
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate inlinable_string;
+///
+/// use inlinable_string::InlinableString;
+///
+/// # fn main() {
+/// let id = 7;
+/// let key = format_inline!("user:{}", id);
+/// assert_eq!(key, "user:7");
+/// assert!(key.is_inline());
+///
+/// let user = "user";
+/// let path = format_inline!(InlinableString<8>, "/home/{}/.config", user);
+/// assert!(path.is_heap());
+/// # }
+/// ```
+#[macro_export]
+macro_rules! format_inline {
+    ($fmt:literal $($args:tt)*) => {
+        <$crate::InlinableString>::from_fmt(format_args!($fmt $($args)*))
+    };
+    ($ty:ty, $fmt:literal $($args:tt)*) => {
+        <$ty>::from_fmt(format_args!($fmt $($args)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::iter::FromIterator;
+    use std::string::{String, ToString};
+    use std::vec::Vec;
+    use {
+        DemoteBelow, DemoteWhenFits, FallibleStringMut, GrowthPolicy, InlinableString, NeverDemote,
+        StrBuf, StringExt, TryReserveError, INLINABLE_STRING_CAPACITY,
+    };
+
+    const LONG_STR: &str = "this is a really long string that is much larger than
+                        INLINABLE_STRING_CAPACITY and so cannot be stored inline.";
+
+    #[test]
+    fn test_long_string() {
+        // If this fails, increase the size of the long string.
+        assert!(LONG_STR.len() > INLINABLE_STRING_CAPACITY);
+    }
+
+    #[test]
+    fn test_size() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<InlinableString>(), size_of::<String>());
+        assert_eq!(size_of::<Option<InlinableString>>(), size_of::<String>());
+        assert_eq!(INLINABLE_STRING_CAPACITY, size_of::<String>() - 1);
+    }
+
+    // First, specifically test operations that overflow InlineString's capacity
+    // and require promoting the string to heap allocation.
+
+    #[test]
+    fn test_push_str() {
+        let mut s: InlinableString = InlinableString::new();
+        s.push_str("small");
+        assert_eq!(s, "small");
+
+        s.push_str(LONG_STR);
+        assert_eq!(s, String::from("small") + LONG_STR);
+    }
+
+    #[test]
+    fn test_write() {
+        use std::fmt::Write;
+        let mut s: InlinableString = InlinableString::new();
+        write!(&mut s, "small").expect("!write");
+        assert_eq!(s, "small");
+
+        write!(&mut s, "{}", LONG_STR).expect("!write");
+        assert_eq!(s, String::from("small") + LONG_STR);
+    }
+
+    #[test]
+    fn test_push() {
+        let mut s: InlinableString = InlinableString::new();
+
+        for _ in 0..INLINABLE_STRING_CAPACITY {
+            s.push('a');
+        }
+        s.push('a');
+
+        assert_eq!(
+            s,
+            String::from_iter((0..INLINABLE_STRING_CAPACITY + 1).map(|_| 'a'))
+        );
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut s: InlinableString = InlinableString::new();
+
+        for _ in 0..INLINABLE_STRING_CAPACITY {
+            s.insert(0, 'a');
+        }
+        s.insert(0, 'a');
+
+        assert_eq!(
+            s,
+            String::from_iter((0..INLINABLE_STRING_CAPACITY + 1).map(|_| 'a'))
+        );
+    }
+
+    #[test]
+    fn test_insert_str() {
+        let mut s: InlinableString = InlinableString::new();
+
+        for _ in 0..(INLINABLE_STRING_CAPACITY / 3) {
+            s.insert_str(0, "foo");
+        }
+        s.insert_str(0, "foo");
+
+        assert_eq!(
+            s,
+            String::from_iter((0..(INLINABLE_STRING_CAPACITY / 3) + 1).map(|_| "foo"))
+        );
+    }
+
+    #[test]
+    fn test_replace_range() {
+        let mut s: InlinableString = InlinableString::from("smol str");
+        assert!(s.is_inline());
+
+        s.replace_range(1..7, LONG_STR);
+        assert_eq!(s, ["s", LONG_STR, "r"].concat());
+
+        let mut s: InlinableString = InlinableString::from("smol str");
+        s.replace_range(1..4, "ee");
+        assert!(s.is_inline());
+        assert_eq!(s, "see str");
+    }
+
+    #[test]
+    fn test_from_str_const() {
+        static TABLE: [InlinableString<8>; 2] = [
+            InlinableString::from_str_const("small"),
+            InlinableString::from_str_const("8 bytes!"),
+        ];
+        for (s, expected) in TABLE.iter().zip(["small", "8 bytes!"]) {
+            assert!(s.is_inline());
+            assert_eq!(*s, expected);
+        }
+
+        let mut s = TABLE[0].clone();
+        s.push_str(" and then some");
+        assert!(s.is_heap());
+        assert_eq!(s, "small and then some");
+    }
+
+    #[test]
+    fn test_from_fmt() {
+        let s: InlinableString = InlinableString::from_fmt(format_args!("{}", 42));
+        assert!(s.is_inline());
+        assert_eq!(s, "42");
+
+        let s: InlinableString = InlinableString::from_fmt(format_args!("short"));
+        assert!(s.is_inline());
+        assert_eq!(s, "short");
+
+        let s: InlinableString = InlinableString::from_fmt(format_args!("{}", LONG_STR));
+        assert!(s.is_heap());
+        assert_eq!(s, LONG_STR);
+    }
+
+    #[test]
+    fn test_format_inline() {
+        let s = format_inline!("{}-{}", "a", 1);
+        assert!(s.is_inline());
+        assert_eq!(s, "a-1");
+
+        let s = format_inline!("a literal that is too long to be stored inline");
+        assert!(s.is_static());
+        assert_eq!(s, "a literal that is too long to be stored inline");
+
+        let s = format_inline!(InlinableString<62>, "{:>40}", "right");
+        assert!(s.is_inline());
+        assert_eq!(s.len(), 40);
+    }
+
+    #[test]
+    fn test_from_static() {
+        const S: InlinableString = InlinableString::from_static(LONG_STR);
+        let s = S;
+        assert!(s.is_static());
+        assert_eq!(s, LONG_STR);
+        assert_eq!(s.len(), LONG_STR.len());
+        assert_eq!(s.capacity(), LONG_STR.len());
+        assert_eq!(&s[..4], "this");
+        assert_eq!(s.clone(), LONG_STR);
+        assert_eq!(String::from(s), LONG_STR);
+    }
+
+    #[test]
+    fn test_static_promotes_on_mutation() {
+        type Op<S> = fn(&mut S);
+        let ops: Vec<(Op<InlinableString>, Op<String>)> = vec![
+            (|s| s.push_str("!?"), |s| s.push_str("!?")),
+            (|s| s.push('!'), |s| s.push('!')),
+            (|s| s.insert(1, '!'), |s| s.insert(1, '!')),
+            (|s| s.insert_str(1, "!!"), |s| s.insert_str(1, "!!")),
+            (
+                |s| {
+                    s.pop();
+                },
+                |s| {
+                    s.pop();
+                },
+            ),
+            (
+                |s| {
+                    s.remove(0);
+                },
+                |s| {
+                    s.remove(0);
+                },
+            ),
+            (|s| s.remove_range(1..3), |s| drop(s.drain(1..3))),
+            (|s| s.retain(|c| c != 'i'), |s| s.retain(|c| c != 'i')),
+            (
+                |s| s.replace_range(..4, "THAT"),
+                |s| s.replace_range(..4, "THAT"),
+            ),
+            (|s| s.make_ascii_uppercase(), |s| s.make_ascii_uppercase()),
+        ];
+
+        for original in &["this is short", LONG_STR] {
+            for &(inlinable_op, string_op) in &ops {
+                let mut s: InlinableString = InlinableString::from_static(original);
+                let mut expected = String::from(*original);
+                inlinable_op(&mut s);
+                string_op(&mut expected);
+
+                assert_eq!(s, expected);
+                if expected.len() <= INLINABLE_STRING_CAPACITY {
+                    assert!(s.is_inline(), "{:?}", s);
+                } else {
+                    assert!(s.is_heap(), "{:?}", s);
+                }
+            }
+        }
+
+        let mut s: InlinableString = InlinableString::from_static("short");
+        s.reserve(100);
+        assert!(s.is_heap());
+        assert!(s.capacity() >= 105);
+    }
+
+    #[test]
+    fn test_static_truncate_and_split_off() {
+        let mut s: InlinableString = InlinableString::from_static(LONG_STR);
+        s.truncate(LONG_STR.len() + 1);
+        assert!(s.is_static());
+        s.truncate(40);
+        assert!(s.is_static());
+        assert_eq!(s, &LONG_STR[..40]);
+
+        let tail = s.split_off(4);
+        assert!(s.is_static());
+        assert!(tail.is_static());
+        assert_eq!(s, "this");
+        assert_eq!(tail, &LONG_STR[4..40]);
+
+        s.clear();
+        assert!(s.is_static());
+        assert_eq!(s, "");
+    }
+
+    #[test]
+    fn test_custom_capacity_promotion() {
+        let mut label: InlinableString<14> = InlinableString::from("OK");
+        assert_eq!(label.capacity(), 14);
+
+        label.push_str(" and Cancel");
+        assert!(label.is_inline());
+        label.insert(0, '[');
+        label.push(']');
+        assert!(label.is_heap());
+        assert_eq!(label, "[OK and Cancel]");
+
+        let mut key: InlinableString<62> = InlinableString::new();
+        key.reserve(62);
+        assert!(key.is_inline());
+        key.reserve(63);
+        assert!(key.is_heap());
+
+        let mut key: InlinableString<62> = InlinableString::from("smol str");
+        key.insert_str(4, LONG_STR);
+        assert_eq!(key, ["smol", LONG_STR, " str"].concat());
+
+        let mut key: InlinableString<62> = InlinableString::from("smol str");
+        key.replace_range(..4, &LONG_STR[..60]);
+        assert!(key.is_heap());
+        assert_eq!(key, [&LONG_STR[..60], " str"].concat());
+    }
+
+    #[test]
+    fn test_promotion_headroom() {
+        let mut s: InlinableString = InlinableString::from("0123456789");
+        s.push_str("01234567890123456789");
+        assert!(s.is_heap());
+        assert!(s.capacity() >= 2 * INLINABLE_STRING_CAPACITY);
+
+        let full = "a".repeat(INLINABLE_STRING_CAPACITY);
+        let mut s: InlinableString = InlinableString::from(&full[..]);
+        s.push('f');
+        assert!(s.capacity() >= 2 * INLINABLE_STRING_CAPACITY);
+
+        // Explicit reservations are honoured as they are.
+        let mut s: InlinableString = InlinableString::new();
+        s.reserve_exact(INLINABLE_STRING_CAPACITY + 1);
+        assert!(s.capacity() < 2 * INLINABLE_STRING_CAPACITY);
+    }
+
+    #[test]
+    fn test_demotion_policies() {
+        fn shrink<P: GrowthPolicy>(op: &str) -> InlinableString<15, String, P> {
+            let mut s: InlinableString<15, String, P> = InlinableString::from(LONG_STR);
+            match op {
+                "truncate" => s.truncate(12),
+                "pop" => {
+                    s.truncate(13);
+                    s.pop();
+                }
+                "remove" => {
+                    s.truncate(13);
+                    s.remove(0);
+                }
+                "remove_range" => s.remove_range(12..),
+                "retain" => s.retain(|c| c == 'i' || c == 's'),
+                "clear" => s.clear(),
+                "replace_range" => s.replace_range(4.., "!"),
+                "split_off" => drop(s.split_off(12)),
+                _ => unreachable!(),
+            }
+            assert!(s.len() <= 12, "{}: {:?}", op, s);
+            s
+        }
+
+        for op in &[
+            "truncate",
+            "pop",
+            "remove",
+            "remove_range",
+            "retain",
+            "clear",
+            "replace_range",
+            "split_off",
+        ] {
+            let s = shrink::<NeverDemote>(op);
+            assert!(s.is_heap(), "{}: {:?}", op, s);
+
+            let s = shrink::<DemoteWhenFits>(op);
+            assert!(s.is_inline(), "{}: {:?}", op, s);
+
+            let s = shrink::<DemoteBelow<4>>(op);
+            if s.len() < 4 {
+                assert!(s.is_inline(), "{}: {:?}", op, s);
+            } else {
+                assert!(s.is_heap(), "{}: {:?}", op, s);
+            }
+        }
+    }
+
+    // Next, some general sanity tests.
+
+    #[test]
+    fn test_split_off() {
+        // This test checks `Heap -> (Heap, Inline)` case of the function;
+        // `Heap -> (Heap, Heap)` is tested by `String` itself,
+        // `Inline -> (Inline, Inline)` is tested by `InlineString`.
+
+        let mut inlinable: InlinableString = LONG_STR.into();
+        let len = LONG_STR.len();
+        assert!(len > INLINABLE_STRING_CAPACITY);
+
+        let at = len - 7;
+        let right_part = inlinable.split_off(at);
+        assert_eq!(&LONG_STR[..at], inlinable);
+        assert_eq!(&LONG_STR[at..], right_part);
+        assert!(inlinable.is_heap());
+        assert!(right_part.is_inline());
+    }
+
+    #[test]
+    fn test_new() {
+        let s = <InlinableString as StringExt>::new();
+        assert!(StrBuf::is_empty(&s));
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let s = <InlinableString as StringExt>::with_capacity(10);
+        assert!(StrBuf::capacity(&s) >= 10);
+    }
+
+    #[test]
+    fn test_from_utf8() {
+        let s = <InlinableString as StringExt>::from_utf8(vec![104, 101, 108, 108, 111]);
+        assert_eq!(s.unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_from_utf16() {
+        let v = &mut [0xD834, 0xDD1E, 0x006d, 0x0075, 0x0073, 0x0069, 0x0063];
+        let s = <InlinableString as StringExt>::from_utf16(v);
+        assert_eq!(s.unwrap(), "𝄞music");
+    }
+
+    #[test]
+    fn test_from_utf16_lossy() {
+        let input = b"Hello \xF0\x90\x80World";
+        let output = <InlinableString as StringExt>::from_utf8_lossy(input);
+        assert_eq!(output, "Hello \u{FFFD}World");
+    }
+
+    #[test]
+    fn test_into_bytes() {
+        let s: InlinableString = InlinableString::from("hello");
+        let bytes = StringExt::into_bytes(s);
+        assert_eq!(bytes, [104, 101, 108, 108, 111]);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let s = <InlinableString as StringExt>::with_capacity(100);
+        assert!(StrBuf::capacity(&s) >= 100);
+    }
+
+    #[test]
+    fn test_reserve() {
+        let mut s = <InlinableString as StringExt>::new();
+        StringExt::reserve(&mut s, 100);
+        assert!(StrBuf::capacity(&s) >= 100);
+    }
+
+    #[test]
+    fn test_reserve_exact() {
+        let mut s = <InlinableString as StringExt>::new();
+        StringExt::reserve_exact(&mut s, 100);
+        assert!(StrBuf::capacity(&s) >= 100);
+    }
+
+    #[test]
+    fn test_try_reserve() {
+        let mut s: InlinableString = InlinableString::from("small");
+        assert_eq!(
+            s.try_reserve(usize::MAX),
+            Err(TryReserveError::CapacityOverflow)
+        );
+        assert_eq!(
+            s.try_reserve(isize::MAX as usize / 2),
+            Err(TryReserveError::AllocError)
+        );
+        assert!(s.is_inline());
+        assert_eq!(s, "small");
+
+        assert_eq!(s.try_reserve_exact(100), Ok(()));
+        assert!(s.is_heap());
+        assert!(s.capacity() >= 105);
+        assert_eq!(
+            s.try_reserve_exact(isize::MAX as usize / 2),
+            Err(TryReserveError::AllocError)
+        );
+        assert_eq!(s, "small");
+
+        let mut s: InlinableString = InlinableString::from_static(LONG_STR);
+        assert_eq!(
+            s.try_reserve(isize::MAX as usize / 2),
+            Err(TryReserveError::AllocError)
+        );
+        assert!(s.is_static());
+    }
+
+    #[test]
+    fn test_try_push_str() {
+        let mut s: InlinableString = InlinableString::new();
+        assert_eq!(s.try_push_str("small"), Ok(()));
+        assert_eq!(s.try_push('!'), Ok(()));
+        assert!(s.is_inline());
+
+        assert_eq!(s.try_push_str(LONG_STR), Ok(()));
+        assert!(s.is_heap());
+        assert_eq!(s, String::from("small!") + LONG_STR);
+
+        let mut s: InlinableString<15, String, NeverDemote> = InlinableString::from("abc");
+        assert_eq!(s.try_insert_str(1, "0123456789abcdef"), Ok(()));
+        assert!(s.capacity() >= 30);
+        assert_eq!(s, "a0123456789abcdefbc");
+
+        let mut s: InlinableString = InlinableString::from_static("tiny");
+        assert_eq!(s.try_insert_str(0, "a "), Ok(()));
+        assert!(s.is_inline());
+        assert_eq!(s, "a tiny");
+
+        assert_eq!(s.try_replace_range(..1, "A"), Ok(()));
+        assert!(s.is_inline());
+        assert_eq!(s.try_replace_range(1..2, LONG_STR), Ok(()));
+        assert!(s.is_heap());
+        assert_eq!(s, String::from("A") + LONG_STR + "tiny");
+    }
+
+    #[test]
+    fn test_try_with_capacity() {
+        let s = <InlinableString as StringExt>::try_with_capacity(100).unwrap();
+        assert!(s.is_heap());
+        assert!(s.capacity() >= 100);
+        assert_eq!(
+            <InlinableString as StringExt>::try_with_capacity(isize::MAX as usize),
+            Err(TryReserveError::AllocError)
+        );
+    }
+
+    #[test]
+    fn test_shrink_to_fit() {
+        let mut s = <InlinableString as StringExt>::with_capacity(100);
+        StringExt::push_str(&mut s, "foo");
+        StringExt::shrink_to_fit(&mut s);
+        assert_eq!(StrBuf::capacity(&s), INLINABLE_STRING_CAPACITY);
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut s: InlinableString = InlinableString::from("foo");
+        StringExt::truncate(&mut s, 1);
+        assert_eq!(s, "f");
+    }
+
+    #[test]
+    fn test_pop() {
+        let mut s: InlinableString = InlinableString::from("foo");
+        assert_eq!(StringExt::pop(&mut s), Some('o'));
+        assert_eq!(StringExt::pop(&mut s), Some('o'));
+        assert_eq!(StringExt::pop(&mut s), Some('f'));
+        assert_eq!(StringExt::pop(&mut s), None);
+    }
+
+    #[test]
+    fn test_ord() {
+        let s1: InlinableString = InlinableString::from("foo");
+        let s2: InlinableString = InlinableString::from("bar");
+        assert_eq!(Ord::cmp(&s1, &s2), Ordering::Greater);
+        assert_eq!(Ord::cmp(&s1, &s1), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_display() {
+        let short: InlinableString = InlinableString::from("he");
+        let long: InlinableString = InlinableString::from("hello world");
+        assert_eq!(format!("{}", short), "he".to_string());
+        assert_eq!(format!("{}", long), "hello world".to_string());
+    }
+
+    #[test]
+    fn test_debug() {
+        let short: InlinableString = InlinableString::from("he");
+        let long: InlinableString = InlinableString::from("hello world hello world hello world");
+        assert_eq!(format!("{:?}", short), "\"he\"");
+        assert_eq!(
+            format!("{:?}", long),
+            "\"hello world hello world hello world\""
+        );
+    }
+
+    #[test]
+    fn test_extract_if() {
+        let mut s: InlinableString = InlinableString::from("a1b2c3");
+        assert_eq!(s.extract_if(|ch| ch.is_ascii_digit()), "123");
+        assert_eq!(s, "abc");
+        assert!(s.is_inline());
+
+        let mut s: InlinableString<15, String, DemoteWhenFits> = InlinableString::from(LONG_STR);
+        let extracted = s.extract_if(|ch| ch != 'a');
+        assert!(extracted.is_heap());
+        assert_eq!(s, "a".repeat(LONG_STR.matches('a').count()));
+        assert!(s.is_inline());
+        assert_eq!(extracted, LONG_STR.replace('a', ""));
+
+        let mut s: InlinableString = InlinableString::from_static("static");
+        assert_eq!(s.extract_if(|ch| ch == 't'), "tt");
+        assert_eq!(s, "saic");
+    }
+
+    #[test]
+    fn test_from_utf8_storage() {
+        let s: InlinableString = StringExt::from_utf8(b"abc".to_vec()).unwrap();
+        assert_eq!(s, "abc");
+        assert!(s.is_inline());
+
+        let s: InlinableString = StringExt::from_utf8(LONG_STR.as_bytes().to_vec()).unwrap();
+        assert_eq!(s, LONG_STR);
+        assert!(s.is_heap());
+
+        let err = <InlinableString as StringExt>::from_utf8(b"a\xFF".to_vec()).unwrap_err();
+        assert_eq!(err.into_bytes(), b"a\xFF");
+
+        let s: InlinableString = unsafe { StringExt::from_utf8_unchecked(b"abc".to_vec()) };
+        assert!(s.is_inline());
+        let s: InlinableString =
+            unsafe { StringExt::from_utf8_unchecked(LONG_STR.as_bytes().to_vec()) };
+        assert!(s.is_heap());
+    }
+
+    #[test]
+    fn test_from_utf8_lossy() {
+        let s: InlinableString = InlinableString::from_utf8_lossy(b"a\xF0\x90\x80b\xFF");
+        assert_eq!(s, "a\u{FFFD}b\u{FFFD}");
+        assert!(s.is_inline());
+
+        let s: InlinableString = InlinableString::from_utf8_lossy(LONG_STR.as_bytes());
+        assert_eq!(s, LONG_STR);
+        assert!(s.is_heap());
+
+        let s: InlinableString<4> = InlinableString::from_utf8_lossy(b"\xFF\xFF");
+        assert_eq!(s, "\u{FFFD}\u{FFFD}");
+        assert!(s.is_heap());
+    }
+
+    #[test]
+    fn test_from_utf16_storage() {
+        let utf16: Vec<u16> = "ab🦀".encode_utf16().collect();
+        let s: InlinableString = StringExt::from_utf16(&utf16).unwrap();
+        assert_eq!(s, "ab🦀");
+        assert!(s.is_inline());
+        let s: InlinableString<4> = StringExt::from_utf16(&utf16).unwrap();
+        assert_eq!(s, "ab🦀");
+        assert!(s.is_heap());
+
+        let long: Vec<u16> = LONG_STR.encode_utf16().collect();
+        let s: InlinableString = StringExt::from_utf16(&long).unwrap();
+        assert_eq!(s, LONG_STR);
+        assert!(s.is_heap());
+
+        let invalid = [0x61, 0xD83E, 0x62];
+        assert!(<InlinableString as StringExt>::from_utf16(&invalid).is_err());
+        let s: InlinableString = StringExt::from_utf16_lossy(&invalid);
+        assert_eq!(s, "a\u{FFFD}b");
+        assert!(s.is_inline());
+        let s: InlinableString<4> = StringExt::from_utf16_lossy(&invalid);
+        assert_eq!(s, "a\u{FFFD}b");
+        assert!(s.is_heap());
+    }
+
+    #[test]
+    fn test_alternate_debug() {
+        let inline: InlinableString = InlinableString::from("inline");
+        let heap: InlinableString = InlinableString::from(LONG_STR);
+        let stat: InlinableString = InlinableString::from_static("static");
+        assert_eq!(format!("{:?}", inline), r#""inline""#);
+        assert_eq!(format!("{:?}", heap), format!("{:?}", LONG_STR));
+        assert_eq!(format!("{:?}", stat), r#""static""#);
+
+        assert!(format!("{:#?}", inline).starts_with("InlinableString {\n    storage: Inline,"));
+        let expected = format!(
+            "InlinableString {{ storage: Heap, value: {:?}, len: {}, capacity: {} }}",
+            LONG_STR,
+            LONG_STR.len(),
+            heap.capacity()
+        );
+        assert_eq!(
+            format!("{:#?}", heap)
+                .replace("\n   ", "")
+                .replace(",\n}", " }"),
+            expected
+        );
+        assert_eq!(
+            format!("{:#?}", stat),
+            "InlinableString {\n    storage: Static,\n    value: \"static\",\n    len: 6,\n    capacity: 6,\n}"
+        );
+    }
+
+    #[test]
+    fn test_from_utf16_bytes() {
+        let mut le = Vec::new();
+        let mut be = Vec::new();
+        InlinableString::<15>::from("a€🦀").encode_utf16le_into(&mut le);
+        InlinableString::<15>::from("a€🦀").encode_utf16be_into(&mut be);
+        for s in [
+            InlinableString::<15>::from_utf16le(&le).unwrap(),
+            InlinableString::from_utf16be(&be).unwrap(),
+            InlinableString::from_utf16le_lossy(&le),
+            InlinableString::from_utf16be_lossy(&be),
+        ] {
+            assert_eq!(s, "a€🦀");
+            assert!(s.is_inline());
+        }
+
+        let mut long = Vec::new();
+        InlinableString::<15>::from(LONG_STR).encode_utf16le_into(&mut long);
+        let s = InlinableString::<15>::from_utf16le(&long).unwrap();
+        assert_eq!(s, LONG_STR);
+        assert!(s.is_heap());
+
+        assert!(InlinableString::<15>::from_utf16le(&le[1..]).is_err());
+        let s = InlinableString::<15>::from_utf16le_lossy(&le[..le.len() - 1]);
+        assert_eq!(s, "a€\u{FFFD}\u{FFFD}");
+    }
+
+    #[test]
+    fn test_extend_from_within() {
+        let mut s: InlinableString = InlinableString::from("abc");
+        s.extend_from_within(..);
+        assert_eq!(s, "abcabc");
+        assert!(s.is_inline());
+
+        let mut s: InlinableString = InlinableString::from("abc");
+        while s.len() <= INLINABLE_STRING_CAPACITY {
+            s.extend_from_within(..);
+        }
+        assert!(s.is_heap());
+        assert_eq!(s, "abc".repeat(s.len() / 3));
+
+        let mut s: InlinableString = InlinableString::from_static("static");
+        s.extend_from_within(..3);
+        assert_eq!(s, "staticsta");
+        assert!(s.is_inline());
+    }
+
+    #[test]
+    fn test_shrink_to() {
+        let mut s: InlinableString = InlinableString::from(LONG_STR);
+        s.truncate(5);
+        s.shrink_to(100);
+        assert!(s.is_heap());
+        assert!(s.capacity() >= 100);
+        s.shrink_to(INLINABLE_STRING_CAPACITY);
+        assert!(s.is_inline());
+        assert_eq!(s, &LONG_STR[..5]);
+
+        let mut s: InlinableString = InlinableString::from(LONG_STR);
+        s.shrink_to(0);
+        assert!(s.is_heap());
+        assert_eq!(s, LONG_STR);
+    }
+
+    #[test]
+    fn test_remove_matches() {
+        let mut s: InlinableString = InlinableString::from_static("static");
+        s.remove_matches("x");
+        assert!(s.is_static());
+        s.remove_matches("t");
+        assert_eq!(s, "saic");
+        assert!(s.is_inline());
+
+        let mut s: InlinableString<15, String, DemoteWhenFits> = InlinableString::from(LONG_STR);
+        s.remove_matches(" ");
+        assert_eq!(s, LONG_STR.replace(' ', ""));
+        let kept = LONG_STR.replace(' ', "");
+        s.remove_matches(&kept[3..]);
+        assert_eq!(s, &kept[..3]);
+        assert!(s.is_inline());
+    }
+
+    #[test]
+    fn test_into_boxed_str() {
+        let inline: InlinableString = InlinableString::from("inline");
+        assert_eq!(&*inline.into_boxed_str(), "inline");
+        let heap: InlinableString = InlinableString::from(LONG_STR);
+        assert_eq!(&*heap.into_boxed_str(), LONG_STR);
+        let stat: InlinableString = InlinableString::from_static("static");
+        assert_eq!(&*stat.leak(), "static");
+    }
+
+    #[test]
+    fn test_drain() {
+        let mut s: InlinableString = InlinableString::from("hello world");
+        assert_eq!(s.drain(5..).collect::<String>(), " world");
+        assert_eq!(s, "hello");
+        assert!(s.is_inline());
+
+        let mut s: InlinableString<15, String, DemoteWhenFits> = InlinableString::from(LONG_STR);
+        assert!(s.is_heap());
+        let drained: String = s.drain(5..).rev().collect();
+        assert_eq!(drained, LONG_STR[5..].chars().rev().collect::<String>());
+        assert_eq!(s, &LONG_STR[..5]);
+        assert!(s.is_inline());
+
+        // Dropping the iterator without consuming it still removes the range.
+        let mut s: InlinableString = InlinableString::from(LONG_STR);
+        s.drain(..LONG_STR.len() - 1);
+        assert_eq!(s, &LONG_STR[LONG_STR.len() - 1..]);
+    }
+
+    crate::string_ext_conformance_tests!(conformance, InlinableString);
+    crate::string_ext_conformance_tests!(
+        conformance_demote_when_fits,
+        InlinableString<15, String, DemoteWhenFits>
+    );
+    crate::string_ext_conformance_tests!(conformance_small_inline, InlinableString<4>);
+}
+
+#[cfg(test)]
+#[cfg(feature = "nightly")]
+mod benches {
+    use super::{InlinableString, StringExt};
+    use test::{black_box, Bencher};
+
+    const SMALL_STR: &'static str = "foobar";
+
+    const LARGE_STR: &'static str =
+        "abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz
+         abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz
+         abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz
+         abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz
+         abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz
+         abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz
+         abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz
+         abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz";
+
+    #[bench]
+    fn bench_std_string_push_str_small_onto_empty(b: &mut Bencher) {
+        b.iter(|| {
+            let mut s = String::new();
+            s.push_str(SMALL_STR);
+            black_box(s);
+        });
+    }
+
+    #[bench]
+    fn bench_inlinable_string_push_str_small_onto_empty(b: &mut Bencher) {
+        b.iter(|| {
+            let mut s: InlinableString = InlinableString::new();
+            s.push_str(SMALL_STR);
+            black_box(s);
+        });
+    }
+
+    #[bench]
+    fn bench_std_string_push_str_large_onto_empty(b: &mut Bencher) {
+        b.iter(|| {
+            let mut s = String::new();
+            s.push_str(LARGE_STR);
+            black_box(s);
+        });
+    }
+
+    #[bench]
+    fn bench_inlinable_string_push_str_large_onto_empty(b: &mut Bencher) {
+        b.iter(|| {
+            let mut s: InlinableString = InlinableString::new();
+            s.push_str(LARGE_STR);
+            black_box(s);
+        });
+    }
+
+    #[bench]
+    fn bench_std_string_push_str_small_onto_small(b: &mut Bencher) {
+        b.iter(|| {
+            let mut s = String::from(SMALL_STR);
+            s.push_str(SMALL_STR);
+            black_box(s);
+        });
+    }
+
+    #[bench]
+    fn bench_inlinable_string_push_str_small_onto_small(b: &mut Bencher) {
+        b.iter(|| {
+            let mut s: InlinableString = InlinableString::from(SMALL_STR);
+            s.push_str(SMALL_STR);
+            black_box(s);
+        });
+    }
+
+    #[bench]
+    fn bench_std_string_push_str_large_onto_large(b: &mut Bencher) {
+        b.iter(|| {
+            let mut s = String::from(LARGE_STR);
+            s.push_str(LARGE_STR);
+            black_box(s);
+        });
+    }
+
+    #[bench]
+    fn bench_inlinable_string_push_str_large_onto_large(b: &mut Bencher) {
+        b.iter(|| {
+            let mut s: InlinableString = InlinableString::from(LARGE_STR);
+            s.push_str(LARGE_STR);
+            black_box(s);
+        });
+    }
+
+    #[bench]
+    fn bench_std_string_from_small(b: &mut Bencher) {
+        b.iter(|| {
+            let s = String::from(SMALL_STR);
+            black_box(s);
+        });
+    }
+
+    #[bench]
+    fn bench_inlinable_string_from_small(b: &mut Bencher) {
+        b.iter(|| {
+            let s: InlinableString = InlinableString::from(SMALL_STR);
+            black_box(s);
+        });
+    }
+
+    #[bench]
+    fn bench_std_string_from_large(b: &mut Bencher) {
+        b.iter(|| {
+            let s = String::from(LARGE_STR);
+            black_box(s);
+        });
+    }
+
+    #[bench]
+    fn bench_inlinable_string_from_large(b: &mut Bencher) {
+        b.iter(|| {
+            let s: InlinableString = InlinableString::from(LARGE_STR);
+            black_box(s);
+        });
+    }
+}
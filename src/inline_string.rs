@@ -7,13 +7,18 @@
 // copied, modified, or distributed except according to those terms.
 
 //! A short UTF-8 string that uses inline storage and does no heap
-//! allocation. It may be no longer than `INLINE_STRING_CAPACITY` bytes long.
+//! allocation. It may be no longer than `N` bytes long, where `N` is the
+//! capacity chosen through the const generic parameter and defaults to
+//! `INLINE_STRING_CAPACITY`.
 //!
 //! The capacity restriction makes many operations that would otherwise be
 //! infallible on `std::string::String` fallible. Additionally, many trait
 //! interfaces don't allow returning an error when a string runs out of space,
 //! and so the trait implementation simply panics. As such, `InlineString` does
-//! not implement `StringExt` and is ***not*** a drop-in replacement for
+//! not implement `StringExt`, only the
+//! [`StrBuf`](../str_buf/trait.StrBuf.html) and
+//! [`FallibleStringMut`](../str_buf/trait.FallibleStringMut.html) layers
+//! below it, and is ***not*** a drop-in replacement for
 //! `std::string::String` in the way that `inlinable_string::InlinableString`
 //! aims to be, and is generally difficult to work with. It is not recommended
 //! to use this type directly unless you really, really want to avoid heap
@@ -26,58 +31,271 @@
 //! ```
 //! use inlinable_string::InlineString;
 //!
-//! let mut s = InlineString::new();
+//! let mut s: InlineString = InlineString::new();
 //! assert!(s.push_str("hi world").is_ok());
 //! assert_eq!(s, "hi world");
 //!
 //! assert!(s.push_str("a really long string that is much bigger than `INLINE_STRING_CAPACITY`").is_err());
 //! assert_eq!(s, "hi world");
 //! ```
-
-use std::borrow;
-use std::convert::{Infallible, TryFrom};
-use std::fmt::{self, Display};
-use std::hash;
-use std::io::Write;
-use std::mem;
-use std::ops::{self, RangeBounds};
-use std::ptr;
-use std::str;
-
-/// The capacity (in bytes) of inline storage for small strings.
-/// `InlineString::len()` may never be larger than this.
-///
-/// Sometime in the future, when Rust's generics support specializing with
-/// compile-time static integers, this number should become configurable.
+//!
+//! The capacity may be picked per use site:
+//!
+//! ```
+//! use inlinable_string::InlineString;
+//!
+//! let mut tag = InlineString::<8>::new();
+//! assert!(tag.push_str("utf-8").is_ok());
+//! assert!(tag.push_str("-encoded").is_err());
+//! assert_eq!(tag.capacity(), 8);
+//! ```
+//!
+//! Note that, like any other default generic parameter, the default capacity
+//! is only applied where a type is written out. A bare `InlineString::new()`
+//! expression needs the capacity to be known from its surroundings or an
+//! annotation such as `let s: InlineString = InlineString::new();`.
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+use core::borrow::{Borrow, BorrowMut};
+use core::cmp::{self, Ordering};
+use core::convert::{Infallible, TryFrom};
+use core::fmt::{self, Display};
+use core::hash;
+use core::iter;
+use core::mem;
+use core::ops::{self, RangeBounds};
+use core::ptr;
+use core::str;
+use drain;
+#[cfg(feature = "alloc")]
+use str_buf::Rejected;
+use str_buf::{self, FallibleStringMut, StrBuf};
+
+/// The default capacity (in bytes) of inline storage for small strings.
+/// `InlineString::len()` may never be larger than this, unless a different
+/// capacity is given as the `N` parameter of `InlineString<N>`.
 pub const INLINE_STRING_CAPACITY: usize = {
-    use mem::size_of;
-    size_of::<String>() + size_of::<usize>() - 2
+    use core::mem::size_of;
+    // `size_of::<String>() + size_of::<usize>() - 2`, spelled out because
+    // `String` needs the `alloc` feature: a `String` is three words.
+    4 * size_of::<usize>() - 2
 };
 
+/// An unsigned integer type used to store the length of an `InlineString`.
+///
+/// The width of the length field bounds the capacity: `u8` allows up to 255
+/// bytes and `u16` up to 65535 bytes. This trait is sealed and only
+/// implemented for those two types.
+pub trait LengthType: Copy + fmt::Debug + sealed::Length {
+    /// The largest capacity whose every length this type can represent.
+    const MAX: usize;
+
+    /// The zero length.
+    const ZERO: Self;
+
+    #[doc(hidden)]
+    fn from_usize(n: usize) -> Self;
+
+    #[doc(hidden)]
+    fn to_usize(self) -> usize;
+}
+
+mod sealed {
+    /// Seals `LengthType`.
+    pub trait Length {}
+
+    impl Length for u8 {}
+    impl Length for u16 {}
+
+    /// Seals `Appendable`.
+    pub trait Appendable {}
+
+    impl Appendable for char {}
+    impl Appendable for &char {}
+    impl Appendable for &str {}
+}
+
+/// A character or string slice that
+/// [`InlineString::try_extend`](./struct.InlineString.html#method.try_extend)
+/// can append.
+///
+/// This trait is sealed and only implemented for `char`, `&char` and `&str`.
+pub trait Appendable: sealed::Appendable {
+    #[doc(hidden)]
+    fn append_to<const N: usize, L: LengthType>(
+        self,
+        s: &mut InlineString<N, L>,
+    ) -> Result<(), NotEnoughCapacity>;
+}
+
+impl Appendable for char {
+    #[inline]
+    fn append_to<const N: usize, L: LengthType>(
+        self,
+        s: &mut InlineString<N, L>,
+    ) -> Result<(), NotEnoughCapacity> {
+        s.push(self)
+    }
+}
+
+impl Appendable for &char {
+    #[inline]
+    fn append_to<const N: usize, L: LengthType>(
+        self,
+        s: &mut InlineString<N, L>,
+    ) -> Result<(), NotEnoughCapacity> {
+        s.push(*self)
+    }
+}
+
+impl Appendable for &str {
+    #[inline]
+    fn append_to<const N: usize, L: LengthType>(
+        self,
+        s: &mut InlineString<N, L>,
+    ) -> Result<(), NotEnoughCapacity> {
+        s.push_str(self)
+    }
+}
+
+macro_rules! impl_length_type {
+    ($ty:ty) => {
+        impl LengthType for $ty {
+            const MAX: usize = <$ty>::MAX as usize;
+            const ZERO: Self = 0;
+
+            #[inline]
+            fn from_usize(n: usize) -> Self {
+                debug_assert!(
+                    n <= <Self as LengthType>::MAX,
+                    "inlinable_string: internal error: length does not fit its type"
+                );
+                n as $ty
+            }
+
+            #[inline]
+            fn to_usize(self) -> usize {
+                self as usize
+            }
+        }
+    };
+}
+
+impl_length_type!(u8);
+impl_length_type!(u16);
+
+/// Converts a length to `L` in a `const` context, where `LengthType`'s
+/// methods can't be called.
+///
+/// `n` has to fit in `L`.
+const fn length_from_usize<L: LengthType>(n: usize) -> L {
+    // `L` is either `u8` or `u16`, so its value can be read from the start of
+    // its native-endian bytes.
+    #[repr(C)]
+    union Repr<L: Copy> {
+        bytes: [u8; 2],
+        length: L,
+    }
+
+    let bytes = if mem::size_of::<L>() == 1 {
+        [n as u8, 0]
+    } else {
+        (n as u16).to_ne_bytes()
+    };
+    unsafe { Repr { bytes }.length }
+}
+
 /// A short UTF-8 string that uses inline storage and does no heap allocation.
 ///
+/// `N` is the capacity in bytes and `L` is the integer type of the length
+/// field, which has to be wide enough to count up to `N`: the default `u8`
+/// covers capacities up to 255 bytes, and `u16` is needed beyond that. This
+/// keeps the length a single byte for small strings while still allowing
+/// bigger buffers on the stack:
+///
+/// ```
+/// use inlinable_string::InlineString;
+///
+/// let mut line = InlineString::<1024, u16>::new();
+/// for _ in 0..64 {
+///     line.push_str("0123456789abcdef").unwrap();
+/// }
+/// assert_eq!(line.len(), 1024);
+/// assert!(line.push('!').is_err());
+/// ```
+///
+/// A capacity that does not fit the length type is rejected at compile time:
+///
+/// ```compile_fail
+/// use inlinable_string::InlineString;
+///
+/// let s = InlineString::<256>::new();
+/// ```
+///
+/// # Overflow
+///
+/// `InlineString` implements the same standard traits as `InlinableString`.
+/// The conversions among them (`FromStr`, `TryFrom<&str>`, `TryFrom<String>`
+/// and `TryFrom<char>`) return a `NotEnoughCapacity` error when the result
+/// does not fit, but the following ones **panic** instead, because their
+/// signatures can't report an error:
+///
+/// * `Extend<char>`, `Extend<&char>` and `Extend<&str>`: use
+///   [`try_extend`](#method.try_extend) instead;
+/// * `FromIterator<char>` and `FromIterator<&str>`: use
+///   [`try_from_iter`](#method.try_from_iter) instead;
+/// * `Add<&str>` and `AddAssign<&str>`: use [`push_str`](#method.push_str)
+///   instead.
+///
+/// A string is left unchanged when one of them panics. The `fmt::Write` impl
+/// returns `fmt::Error` rather than panicking, and keeps whatever pieces of
+/// the output were written before the overflow. Write through
+/// [`transactional_writer`](#method.transactional_writer) to undo them
+/// instead, or through [`truncating_writer`](#method.truncating_writer) to
+/// keep as much of the output as fits.
+///
+/// ```
+/// use std::collections::{BTreeMap, HashMap};
+/// use inlinable_string::InlineString;
+///
+/// let key: InlineString = "key".parse().unwrap();
+///
+/// let mut sorted = BTreeMap::new();
+/// sorted.insert(key.clone(), 1);
+/// let mut hashed = HashMap::new();
+/// hashed.insert(key, 1);
+///
+/// assert_eq!(sorted.get("key"), Some(&1));
+/// assert_eq!(hashed.get("key"), Some(&1));
+/// ```
+///
 /// See the [module level documentation](./index.html) for more.
-#[derive(Clone, Debug, Eq)]
-pub struct InlineString {
-    length: u8,
-    bytes: [u8; INLINE_STRING_CAPACITY],
+#[derive(Clone)]
+pub struct InlineString<const N: usize = INLINE_STRING_CAPACITY, L: LengthType = u8> {
+    length: L,
+    bytes: [u8; N],
 }
 
-impl AsRef<str> for InlineString {
+impl<const N: usize, L: LengthType> AsRef<str> for InlineString<N, L> {
     fn as_ref(&self) -> &str {
         self.assert_sanity();
         unsafe { str::from_utf8_unchecked(&self.bytes[..self.len()]) }
     }
 }
 
-impl AsRef<[u8]> for InlineString {
+impl<const N: usize, L: LengthType> AsRef<[u8]> for InlineString<N, L> {
     #[inline]
     fn as_ref(&self) -> &[u8] {
         self.as_bytes()
     }
 }
 
-impl AsMut<str> for InlineString {
+impl<const N: usize, L: LengthType> AsMut<str> for InlineString<N, L> {
     fn as_mut(&mut self) -> &mut str {
         self.assert_sanity();
         let length = self.len();
@@ -85,15 +303,72 @@ impl AsMut<str> for InlineString {
     }
 }
 
-/// An error type for `InlineString`.
+/// The error type for `InlineString`: the result of an operation would not
+/// fit into its capacity. The string is left unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use inlinable_string::InlineString;
+///
+/// let mut s: InlineString<8> = InlineString::try_from("hello").unwrap();
+/// let err = s.push_str(", world").unwrap_err();
+/// assert_eq!(err.required(), 12);
+/// assert_eq!(err.capacity(), 8);
+/// assert_eq!(err.excess(), 4);
+/// assert_eq!(
+///     err.to_string(),
+///     "the result needs 12 bytes, but the `InlineString` can only hold 8"
+/// );
+/// ```
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub struct NotEnoughCapacity;
+pub struct NotEnoughCapacity {
+    required: usize,
+    capacity: usize,
+}
+
+impl NotEnoughCapacity {
+    /// Creates an error for a result of `required` bytes that does not fit
+    /// into `capacity` bytes.
+    #[inline]
+    pub const fn new(required: usize, capacity: usize) -> Self {
+        NotEnoughCapacity { required, capacity }
+    }
+
+    /// Returns the length, in bytes, that the result would have had.
+    #[inline]
+    pub const fn required(&self) -> usize {
+        self.required
+    }
+
+    /// Returns the capacity, in bytes, of the string.
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns how many bytes over the capacity the result would have been.
+    #[inline]
+    pub const fn excess(&self) -> usize {
+        self.required.saturating_sub(self.capacity)
+    }
+}
+
 impl Display for NotEnoughCapacity {
     #[inline]
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        "the length of the result string is bigger than maximum capacity of `InlineString`".fmt(fmt)
+        write!(
+            fmt,
+            "the result needs {} bytes, but the `InlineString` can only hold {}",
+            self.required, self.capacity
+        )
     }
 }
+
+#[cfg(feature = "std")]
+impl std::error::Error for NotEnoughCapacity {}
+
 impl From<Infallible> for NotEnoughCapacity {
     #[inline]
     fn from(x: Infallible) -> NotEnoughCapacity {
@@ -101,29 +376,166 @@ impl From<Infallible> for NotEnoughCapacity {
     }
 }
 
-impl TryFrom<&str> for InlineString {
+/// Panics with `error`, for the trait impls that can't return it.
+#[cold]
+#[inline(never)]
+fn capacity_overflow(error: NotEnoughCapacity) -> ! {
+    panic!("{}", error)
+}
+
+impl<const N: usize, L: LengthType> TryFrom<&str> for InlineString<N, L> {
     type Error = NotEnoughCapacity;
 
     fn try_from(string: &str) -> Result<Self, NotEnoughCapacity> {
         let string_len = string.len();
-        if string_len <= INLINE_STRING_CAPACITY {
+        if string_len <= N {
             // SAFETY:
             // `string_len` is not bigger than capacity.
             unsafe { Ok(Self::from_str_unchecked(string)) }
         } else {
-            Err(NotEnoughCapacity)
+            Err(NotEnoughCapacity::new(string_len, N))
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<const N: usize, L: LengthType> TryFrom<String> for InlineString<N, L> {
+    type Error = Rejected<String, NotEnoughCapacity>;
+
+    /// Copies `string` into inline storage, or hands it back if it does not
+    /// fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::InlineString;
+    ///
+    /// let s = InlineString::<8>::try_from(String::from("short")).unwrap();
+    /// assert_eq!(s, "short");
+    ///
+    /// let rejected = InlineString::<8>::try_from(String::from("much too long")).unwrap_err();
+    /// assert_eq!(rejected.error().excess(), 5);
+    /// assert_eq!(rejected.into_inner(), "much too long");
+    /// ```
+    #[inline]
+    fn try_from(string: String) -> Result<Self, Self::Error> {
+        match Self::try_from(string.as_str()) {
+            Ok(s) => Ok(s),
+            Err(error) => Err(Rejected::new(string, error)),
         }
     }
 }
 
-impl fmt::Display for InlineString {
+impl<const N: usize, L: LengthType> TryFrom<char> for InlineString<N, L> {
+    type Error = NotEnoughCapacity;
+
+    #[inline]
+    fn try_from(ch: char) -> Result<Self, NotEnoughCapacity> {
+        let mut s = Self::new();
+        s.push(ch)?;
+        Ok(s)
+    }
+}
+
+impl<const N: usize, L: LengthType> str::FromStr for InlineString<N, L> {
+    type Err = NotEnoughCapacity;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, NotEnoughCapacity> {
+        Self::try_from(s)
+    }
+}
+
+impl<const N: usize, L: LengthType> StrBuf for InlineString<N, L> {
+    #[inline]
+    fn as_str(&self) -> &str {
+        self
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        N
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        InlineString::len(self)
+    }
+}
+
+impl<const N: usize, L: LengthType> FallibleStringMut for InlineString<N, L> {
+    type Error = NotEnoughCapacity;
+
+    #[inline]
+    fn try_push_str(&mut self, string: &str) -> Result<(), NotEnoughCapacity> {
+        InlineString::push_str(self, string)
+    }
+
+    #[inline]
+    fn try_push(&mut self, ch: char) -> Result<(), NotEnoughCapacity> {
+        InlineString::push(self, ch)
+    }
+
+    #[inline]
+    fn try_insert_str(&mut self, idx: usize, string: &str) -> Result<(), NotEnoughCapacity> {
+        InlineString::insert_str(self, idx, string)
+    }
+
+    #[inline]
+    fn try_replace_range<R>(
+        &mut self,
+        range: R,
+        replace_with: &str,
+    ) -> Result<(), NotEnoughCapacity>
+    where
+        R: RangeBounds<usize>,
+    {
+        InlineString::replace_range(self, range, replace_with)
+    }
+}
+
+impl<const N: usize, L: LengthType> fmt::Display for InlineString<N, L> {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         self.assert_sanity();
         write!(f, "{}", self as &str)
     }
 }
 
-impl fmt::Write for InlineString {
+/// Prints the quoted string, like `str` does. The alternate form, `{:#?}`,
+/// also shows the length and capacity:
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use inlinable_string::InlineString;
+///
+/// let s: InlineString<8> = InlineString::try_from("hello").unwrap();
+/// assert_eq!(format!("{:?}", s), r#""hello""#);
+/// assert_eq!(
+///     format!("{:#?}", s),
+///     r#"InlineString {
+///     value: "hello",
+///     len: 5,
+///     capacity: 8,
+/// }"#
+/// );
+/// ```
+impl<const N: usize, L: LengthType> fmt::Debug for InlineString<N, L> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.assert_sanity();
+        if f.alternate() {
+            f.debug_struct("InlineString")
+                .field("value", &&**self)
+                .field("len", &self.len())
+                .field("capacity", &N)
+                .finish()
+        } else {
+            fmt::Debug::fmt(&**self, f)
+        }
+    }
+}
+
+impl<const N: usize, L: LengthType> fmt::Write for InlineString<N, L> {
     fn write_char(&mut self, ch: char) -> Result<(), fmt::Error> {
         self.push(ch).map_err(|_| fmt::Error)
     }
@@ -132,14 +544,122 @@ impl fmt::Write for InlineString {
     }
 }
 
-impl hash::Hash for InlineString {
+impl<const N: usize, L: LengthType> hash::Hash for InlineString<N, L> {
     #[inline]
     fn hash<H: hash::Hasher>(&self, hasher: &mut H) {
         (**self).hash(hasher)
     }
 }
 
-impl ops::Index<ops::Range<usize>> for InlineString {
+impl<const N: usize, L: LengthType> PartialOrd for InlineString<N, L> {
+    #[inline]
+    fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
+        Some(self.cmp(rhs))
+    }
+}
+
+impl<const N: usize, L: LengthType> Ord for InlineString<N, L> {
+    #[inline]
+    fn cmp(&self, rhs: &Self) -> Ordering {
+        (**self).cmp(&**rhs)
+    }
+}
+
+impl<const N: usize, L: LengthType> Borrow<str> for InlineString<N, L> {
+    #[inline]
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl<const N: usize, L: LengthType> BorrowMut<str> for InlineString<N, L> {
+    #[inline]
+    fn borrow_mut(&mut self) -> &mut str {
+        self
+    }
+}
+
+impl<const N: usize, L: LengthType> iter::FromIterator<char> for InlineString<N, L> {
+    /// # Panics
+    ///
+    /// Panics if the characters do not fit; see `try_from_iter`.
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        Self::try_from_iter(iter).unwrap_or_else(|error| capacity_overflow(error))
+    }
+}
+
+impl<'a, const N: usize, L: LengthType> iter::FromIterator<&'a str> for InlineString<N, L> {
+    /// # Panics
+    ///
+    /// Panics if the strings do not fit; see `try_from_iter`.
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        Self::try_from_iter(iter).unwrap_or_else(|error| capacity_overflow(error))
+    }
+}
+
+impl<const N: usize, L: LengthType> Extend<char> for InlineString<N, L> {
+    /// # Panics
+    ///
+    /// Panics, leaving the string unchanged, if the characters do not fit;
+    /// see `try_extend`.
+    fn extend<I: IntoIterator<Item = char>>(&mut self, iter: I) {
+        if let Err(error) = self.try_extend(iter) {
+            capacity_overflow(error)
+        }
+    }
+}
+
+impl<'a, const N: usize, L: LengthType> Extend<&'a char> for InlineString<N, L> {
+    /// # Panics
+    ///
+    /// Panics, leaving the string unchanged, if the characters do not fit;
+    /// see `try_extend`.
+    fn extend<I: IntoIterator<Item = &'a char>>(&mut self, iter: I) {
+        if let Err(error) = self.try_extend(iter) {
+            capacity_overflow(error)
+        }
+    }
+}
+
+impl<'a, const N: usize, L: LengthType> Extend<&'a str> for InlineString<N, L> {
+    /// # Panics
+    ///
+    /// Panics, leaving the string unchanged, if the strings do not fit; see
+    /// `try_extend`.
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, iter: I) {
+        if let Err(error) = self.try_extend(iter) {
+            capacity_overflow(error)
+        }
+    }
+}
+
+impl<const N: usize, L: LengthType> ops::Add<&str> for InlineString<N, L> {
+    type Output = Self;
+
+    /// # Panics
+    ///
+    /// Panics if the result does not fit; see `push_str`.
+    #[inline]
+    fn add(mut self, other: &str) -> Self {
+        self += other;
+        self
+    }
+}
+
+impl<const N: usize, L: LengthType> ops::AddAssign<&str> for InlineString<N, L> {
+    /// # Panics
+    ///
+    /// Panics, leaving the string unchanged, if the result does not fit; see
+    /// `push_str`.
+    #[inline]
+    fn add_assign(&mut self, other: &str) {
+        if let Err(error) = self.push_str(other) {
+            capacity_overflow(error)
+        }
+    }
+}
+
+impl<const N: usize, L: LengthType> ops::Index<ops::Range<usize>> for InlineString<N, L> {
     type Output = str;
 
     #[inline]
@@ -149,7 +669,7 @@ impl ops::Index<ops::Range<usize>> for InlineString {
     }
 }
 
-impl ops::Index<ops::RangeTo<usize>> for InlineString {
+impl<const N: usize, L: LengthType> ops::Index<ops::RangeTo<usize>> for InlineString<N, L> {
     type Output = str;
 
     #[inline]
@@ -159,7 +679,7 @@ impl ops::Index<ops::RangeTo<usize>> for InlineString {
     }
 }
 
-impl ops::Index<ops::RangeFrom<usize>> for InlineString {
+impl<const N: usize, L: LengthType> ops::Index<ops::RangeFrom<usize>> for InlineString<N, L> {
     type Output = str;
 
     #[inline]
@@ -169,7 +689,7 @@ impl ops::Index<ops::RangeFrom<usize>> for InlineString {
     }
 }
 
-impl ops::Index<ops::RangeFull> for InlineString {
+impl<const N: usize, L: LengthType> ops::Index<ops::RangeFull> for InlineString<N, L> {
     type Output = str;
 
     #[inline]
@@ -179,7 +699,7 @@ impl ops::Index<ops::RangeFull> for InlineString {
     }
 }
 
-impl ops::IndexMut<ops::Range<usize>> for InlineString {
+impl<const N: usize, L: LengthType> ops::IndexMut<ops::Range<usize>> for InlineString<N, L> {
     #[inline]
     fn index_mut(&mut self, index: ops::Range<usize>) -> &mut str {
         self.assert_sanity();
@@ -187,7 +707,7 @@ impl ops::IndexMut<ops::Range<usize>> for InlineString {
     }
 }
 
-impl ops::IndexMut<ops::RangeTo<usize>> for InlineString {
+impl<const N: usize, L: LengthType> ops::IndexMut<ops::RangeTo<usize>> for InlineString<N, L> {
     #[inline]
     fn index_mut(&mut self, index: ops::RangeTo<usize>) -> &mut str {
         self.assert_sanity();
@@ -195,7 +715,7 @@ impl ops::IndexMut<ops::RangeTo<usize>> for InlineString {
     }
 }
 
-impl ops::IndexMut<ops::RangeFrom<usize>> for InlineString {
+impl<const N: usize, L: LengthType> ops::IndexMut<ops::RangeFrom<usize>> for InlineString<N, L> {
     #[inline]
     fn index_mut(&mut self, index: ops::RangeFrom<usize>) -> &mut str {
         self.assert_sanity();
@@ -203,7 +723,7 @@ impl ops::IndexMut<ops::RangeFrom<usize>> for InlineString {
     }
 }
 
-impl ops::IndexMut<ops::RangeFull> for InlineString {
+impl<const N: usize, L: LengthType> ops::IndexMut<ops::RangeFull> for InlineString<N, L> {
     #[inline]
     fn index_mut(&mut self, _index: ops::RangeFull) -> &mut str {
         self.assert_sanity();
@@ -212,7 +732,7 @@ impl ops::IndexMut<ops::RangeFull> for InlineString {
     }
 }
 
-impl ops::Deref for InlineString {
+impl<const N: usize, L: LengthType> ops::Deref for InlineString<N, L> {
     type Target = str;
 
     #[inline]
@@ -222,7 +742,7 @@ impl ops::Deref for InlineString {
     }
 }
 
-impl ops::DerefMut for InlineString {
+impl<const N: usize, L: LengthType> ops::DerefMut for InlineString<N, L> {
     #[inline]
     fn deref_mut(&mut self) -> &mut str {
         self.assert_sanity();
@@ -231,32 +751,38 @@ impl ops::DerefMut for InlineString {
     }
 }
 
-impl Default for InlineString {
+impl<const N: usize, L: LengthType> Default for InlineString<N, L> {
     #[inline]
-    fn default() -> InlineString {
-        InlineString::new()
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-impl PartialEq<InlineString> for InlineString {
+impl<const N: usize, L, const M: usize, K> PartialEq<InlineString<M, K>> for InlineString<N, L>
+where
+    L: LengthType,
+    K: LengthType,
+{
     #[inline]
-    fn eq(&self, rhs: &InlineString) -> bool {
+    fn eq(&self, rhs: &InlineString<M, K>) -> bool {
         self.assert_sanity();
         rhs.assert_sanity();
         PartialEq::eq(&self[..], &rhs[..])
     }
 }
 
+impl<const N: usize, L: LengthType> Eq for InlineString<N, L> {}
+
 macro_rules! impl_eq {
     ($lhs:ty, $rhs: ty) => {
-        impl<'a> PartialEq<$rhs> for $lhs {
+        impl<'a, const N: usize, L: LengthType> PartialEq<$rhs> for $lhs {
             #[inline]
             fn eq(&self, other: &$rhs) -> bool {
                 PartialEq::eq(&self[..], &other[..])
             }
         }
 
-        impl<'a> PartialEq<$lhs> for $rhs {
+        impl<'a, const N: usize, L: LengthType> PartialEq<$lhs> for $rhs {
             #[inline]
             fn eq(&self, other: &$lhs) -> bool {
                 PartialEq::eq(&self[..], &other[..])
@@ -265,20 +791,53 @@ macro_rules! impl_eq {
     };
 }
 
-impl_eq! { InlineString, str }
-impl_eq! { InlineString, &'a str }
-impl_eq! { borrow::Cow<'a, str>, InlineString }
+impl_eq! { InlineString<N, L>, str }
+impl_eq! { InlineString<N, L>, &'a str }
+#[cfg(feature = "alloc")]
+impl_eq! { InlineString<N, L>, String }
+#[cfg(feature = "alloc")]
+impl_eq! { Cow<'a, str>, InlineString<N, L> }
+
+#[cfg(feature = "alloc")]
+impl<const N: usize> InlineString<N> {
+    /// Takes the string apart into its buffer and its length.
+    #[inline]
+    pub(crate) const fn into_raw_parts(self) -> ([u8; N], usize) {
+        (self.bytes, self.length as usize)
+    }
+
+    /// Puts a string taken apart by `into_raw_parts` back together.
+    ///
+    /// Safety
+    ///
+    /// `len` may not be bigger than `N`, and `bytes[..len]` has to be valid
+    /// UTF-8.
+    #[inline]
+    pub(crate) const unsafe fn from_raw_parts(bytes: [u8; N], len: usize) -> Self {
+        InlineString {
+            length: len as u8,
+            bytes,
+        }
+    }
+}
+
+impl<const N: usize, L: LengthType> InlineString<N, L> {
+    /// Compile-time check that every length up to `N` fits into `L`.
+    const CAPACITY_FITS: () = assert!(
+        N <= L::MAX,
+        "inlinable_string: `InlineString` capacity does not fit its length type; \
+         use `u16` as the length type for capacities over 255 bytes"
+    );
 
-impl InlineString {
     #[cfg_attr(feature = "nightly", allow(inline_always))]
     #[inline(always)]
     fn assert_sanity(&self) {
         debug_assert!(
-            self.length as usize <= INLINE_STRING_CAPACITY,
+            self.length.to_usize() <= N,
             "inlinable_string: internal error: length greater than capacity"
         );
         debug_assert!(
-            str::from_utf8(&self.bytes[0..self.length as usize]).is_ok(),
+            str::from_utf8(&self.bytes[0..self.length.to_usize()]).is_ok(),
             "inlinable_string: internal error: contents are not valid UTF-8!"
         );
     }
@@ -287,49 +846,37 @@ impl InlineString {
     ///
     /// # Safety:
     ///
-    /// It is instant UB if the length of `s` is bigger than `INLINE_STRING_CAPACITY`.
+    /// It is instant UB if the length of `s` is bigger than `N`.
     unsafe fn from_str_unchecked(s: &str) -> Self {
         let string_len = s.len();
         debug_assert!(
-            string_len <= INLINE_STRING_CAPACITY as usize,
+            string_len <= N,
             "inlinable_string: internal error: length greater than capacity"
         );
 
-        let mut ss = InlineString::new();
+        let mut ss = Self::new();
         unsafe {
             ptr::copy_nonoverlapping(s.as_ptr(), ss.bytes.as_mut_ptr(), string_len);
         }
-        ss.length = string_len as u8;
+        ss.length = L::from_usize(string_len);
 
         ss.assert_sanity();
 
         ss
     }
 
-    /// Returns a mutable reference to the inner buffer.
-    ///
-    /// Safety
-    ///
-    /// The same as [`str::as_bytes_mut()`].
-    ///
-    ///[`str::as_bytes_mut()`]: https://doc.rust-lang.org/std/primitive.str.html#method.as_bytes_mut
-    #[inline]
-    pub(crate) unsafe fn as_bytes_mut(&mut self) -> &mut [u8; INLINE_STRING_CAPACITY] {
-        &mut self.bytes
-    }
-
     /// Insanely unsafe function to set length.
     ///
     /// Safety
     ///
     /// It's UB if `new_len`
     ///
-    /// * is bigger than `INLINE_STRING_CAPACITY`;
+    /// * is bigger than `N`;
     /// * doesn't lie at the start and/or end of a UTF-8 code point sequence;
     /// * grabs some uninitialized memory.
     #[inline]
     pub(crate) unsafe fn set_len(&mut self, new_len: usize) {
-        self.length = new_len as u8
+        self.length = L::from_usize(new_len)
     }
 
     /// Creates a new string buffer initialized with the empty string.
@@ -339,49 +886,175 @@ impl InlineString {
     /// ```
     /// use inlinable_string::InlineString;
     ///
-    /// let s = InlineString::new();
+    /// let s: InlineString = InlineString::new();
     /// ```
-    #[inline]
-    pub fn new() -> InlineString {
-        InlineString {
-            length: 0,
-            bytes: [0; INLINE_STRING_CAPACITY],
-        }
-    }
-
-    /// Returns the underlying byte buffer, encoded as UTF-8. Trailing bytes are
-    /// zeroed.
     ///
-    /// # Examples
+    /// Being a `const fn`, it can initialize constants and statics:
     ///
     /// ```
-    /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let s = InlineString::try_from("hello").unwrap();
-    /// let bytes = s.into_bytes();
-    /// assert_eq!(&bytes[0..5], [104, 101, 108, 108, 111]);
+    /// static EMPTY: InlineString<16> = InlineString::new();
+    /// assert!(EMPTY.is_empty());
     /// ```
     #[inline]
-    pub fn into_bytes(mut self) -> [u8; INLINE_STRING_CAPACITY] {
-        self.assert_sanity();
-        for i in self.len()..INLINE_STRING_CAPACITY {
-            self.bytes[i] = 0;
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CAPACITY_FITS;
+
+        InlineString {
+            length: L::ZERO,
+            bytes: [0; N],
         }
-        self.bytes
     }
 
-    /// Pushes the given string onto this string buffer.
+    /// Creates an `InlineString` holding a copy of `s` in a `const` context.
+    ///
+    /// This is the `const fn` counterpart of `TryFrom<&str>`, so tables of
+    /// inline strings can be built at compile time. A `const fn` has no way to
+    /// report an error, so it panics if `s` does not fit; in a `const` or
+    /// `static` initializer, that panic is a compile-time error. The
+    /// [`inline_str!`](../macro.inline_str.html) macro always evaluates it at
+    /// compile time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s.len()` is greater than `N`.
     ///
     /// # Examples
     ///
     /// ```
-    /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let mut s = InlineString::try_from("foo").unwrap();
-    /// s.push_str("bar");
-    /// assert_eq!(s, "foobar");
+    /// static UNITS: [InlineString<2>; 3] = [
+    ///     InlineString::from_str_const("B"),
+    ///     InlineString::from_str_const("kB"),
+    ///     InlineString::from_str_const("MB"),
+    /// ];
+    /// assert_eq!(UNITS[1], "kB");
+    /// ```
+    ///
+    /// A string that does not fit fails to compile:
+    ///
+    /// ```compile_fail
+    /// use inlinable_string::InlineString;
+    ///
+    /// const TOO_LONG: InlineString<2> = InlineString::from_str_const("GiB");
+    /// ```
+    #[inline]
+    pub const fn from_str_const(s: &str) -> Self {
+        let mut string = Self::new();
+        let bytes = s.as_bytes();
+        assert!(
+            bytes.len() <= N,
+            "inlinable_string: string does not fit the `InlineString` capacity"
+        );
+
+        let mut i = 0;
+        while i < bytes.len() {
+            string.bytes[i] = bytes[i];
+            i += 1;
+        }
+        // `new` has checked that every length up to `N` fits in `L`.
+        string.length = length_from_usize(bytes.len());
+        string
+    }
+
+    /// Formats `args` into a new `InlineString`, failing if the output does
+    /// not fit.
+    ///
+    /// This is what [`try_format_inline!`](../macro.try_format_inline.html)
+    /// expands to. Unlike writing through `fmt::Write`, which stops with a bare
+    /// `fmt::Error` in the middle of the output, the error tells how many
+    /// bytes the whole output needs, and no partially formatted string is
+    /// returned. Arguments without anything to format are copied directly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a formatting trait implementation returns an error on its
+    /// own, like `format!` does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::InlineString;
+    ///
+    /// let s = InlineString::<8>::try_from_fmt(format_args!("{}-{}", 4, 2)).unwrap();
+    /// assert_eq!(s, "4-2");
+    ///
+    /// let err = InlineString::<8>::try_from_fmt(format_args!("{:>10}", 42)).unwrap_err();
+    /// assert_eq!(err.required(), 10);
+    /// assert_eq!(err.capacity(), 8);
+    /// ```
+    pub fn try_from_fmt(args: fmt::Arguments) -> Result<Self, NotEnoughCapacity> {
+        if let Some(s) = args.as_str() {
+            return Self::try_from(s);
+        }
+
+        let mut string = Self::new();
+        if fmt::write(&mut string, args).is_ok() {
+            return Ok(string);
+        }
+
+        // Measure the whole output to tell apart an overflow from an error
+        // raised by a formatting trait implementation.
+        let mut counter = LengthCounter(0);
+        match fmt::write(&mut counter, args) {
+            Ok(()) if counter.0 > N => Err(NotEnoughCapacity::new(counter.0, N)),
+            _ => panic!(
+                "a formatting trait implementation returned an error when the \
+                 underlying stream did not"
+            ),
+        }
+    }
+
+    /// Returns the number of bytes this string can hold, which is always `N`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::InlineString;
+    ///
+    /// let s = InlineString::<64>::new();
+    /// assert_eq!(s.capacity(), 64);
+    /// ```
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the underlying byte buffer, encoded as UTF-8. Trailing bytes are
+    /// zeroed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::InlineString;
+    ///
+    /// let s: InlineString = InlineString::try_from("hello").unwrap();
+    /// let bytes = s.into_bytes();
+    /// assert_eq!(&bytes[0..5], [104, 101, 108, 108, 111]);
+    /// ```
+    #[inline]
+    pub fn into_bytes(mut self) -> [u8; N] {
+        self.assert_sanity();
+        let len = self.len();
+        self.bytes[len..].fill(0);
+        self.bytes
+    }
+
+    /// Pushes the given string onto this string buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::InlineString;
+    ///
+    /// let mut s: InlineString = InlineString::try_from("foo").unwrap();
+    /// s.push_str("bar");
+    /// assert_eq!(s, "foobar");
     /// ```
     #[inline]
     pub fn push_str(&mut self, string: &str) -> Result<(), NotEnoughCapacity> {
@@ -390,18 +1063,64 @@ impl InlineString {
         let string_len = string.len();
         let new_length = self.len() + string_len;
 
-        if new_length > INLINE_STRING_CAPACITY {
-            return Err(NotEnoughCapacity);
+        if new_length > N {
+            return Err(NotEnoughCapacity::new(new_length, N));
         }
 
         unsafe {
             ptr::copy_nonoverlapping(
                 string.as_ptr(),
-                self.bytes.as_mut_ptr().offset(self.length as isize),
+                self.bytes.as_mut_ptr().add(self.len()),
                 string_len,
             );
         }
-        self.length = new_length as u8;
+        self.length = L::from_usize(new_length);
+
+        self.assert_sanity();
+        Ok(())
+    }
+
+    /// Copies the bytes in the `src` range to the end of the string, or
+    /// returns `NotEnoughCapacity`, leaving the string unchanged, if they
+    /// don't fit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range has `start_bound > end_bound`, if the range is
+    /// bounded on either end and does not lie on a [`char`] boundary, or if
+    /// it's out of bounds.
+    ///
+    /// [`char`]: https://doc.rust-lang.org/std/primitive.char.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::InlineString;
+    ///
+    /// let mut s: InlineString<8> = InlineString::try_from("abcde").unwrap();
+    /// assert!(s.extend_from_within(2..4).is_ok());
+    /// assert_eq!(s, "abcdecd");
+    /// assert!(s.extend_from_within(..2).is_err());
+    /// assert_eq!(s, "abcdecd");
+    /// ```
+    #[inline]
+    pub fn extend_from_within<R>(&mut self, src: R) -> Result<(), NotEnoughCapacity>
+    where
+        R: RangeBounds<usize>,
+    {
+        self.assert_sanity();
+
+        let len = self.len();
+        let (start, end) = str_buf::byte_range(&src, len);
+        // String index does all bounds checks.
+        let range_len = self[start..end].len();
+        if range_len > N - len {
+            return Err(NotEnoughCapacity::new(len + range_len, N));
+        }
+
+        self.bytes.copy_within(start..end, len);
+        self.length = L::from_usize(len + range_len);
 
         self.assert_sanity();
         Ok(())
@@ -415,7 +1134,7 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let mut s = InlineString::try_from("abc").unwrap();
+    /// let mut s: InlineString = InlineString::try_from("abc").unwrap();
     /// s.push('1');
     /// s.push('2');
     /// s.push('3');
@@ -425,21 +1144,16 @@ impl InlineString {
     pub fn push(&mut self, ch: char) -> Result<(), NotEnoughCapacity> {
         self.assert_sanity();
 
+        let len = self.len();
         let char_len = ch.len_utf8();
-        let new_length = self.len() + char_len;
+        let new_length = len + char_len;
 
-        if new_length > INLINE_STRING_CAPACITY {
-            return Err(NotEnoughCapacity);
+        if new_length > N {
+            return Err(NotEnoughCapacity::new(new_length, N));
         }
 
-        {
-            let mut slice = &mut self.bytes[self.length as usize..INLINE_STRING_CAPACITY];
-            write!(&mut slice, "{}", ch).expect(
-                "inlinable_string: internal error: should have enough space, we
-                         checked above",
-            );
-        }
-        self.length = new_length as u8;
+        ch.encode_utf8(&mut self.bytes[len..new_length]);
+        self.length = L::from_usize(new_length);
 
         self.assert_sanity();
         Ok(())
@@ -453,7 +1167,7 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let s = InlineString::try_from("hello").unwrap();
+    /// let s: InlineString = InlineString::try_from("hello").unwrap();
     /// assert_eq!(s.as_bytes(), [104, 101, 108, 108, 111]);
     /// ```
     #[inline]
@@ -476,7 +1190,7 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let mut s = InlineString::try_from("hello").unwrap();
+    /// let mut s: InlineString = InlineString::try_from("hello").unwrap();
     /// s.truncate(2);
     /// assert_eq!(s, "he");
     /// ```
@@ -487,7 +1201,7 @@ impl InlineString {
         if new_len < self.len() {
             assert!(self[..].is_char_boundary(new_len));
 
-            self.length = new_len as u8;
+            self.length = L::from_usize(new_len);
         }
     }
 
@@ -500,7 +1214,7 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let mut s = InlineString::try_from("foo").unwrap();
+    /// let mut s: InlineString = InlineString::try_from("foo").unwrap();
     /// assert_eq!(s.pop(), Some('o'));
     /// assert_eq!(s.pop(), Some('o'));
     /// assert_eq!(s.pop(), Some('f'));
@@ -510,10 +1224,10 @@ impl InlineString {
     pub fn pop(&mut self) -> Option<char> {
         self.assert_sanity();
 
-        match self.char_indices().rev().next() {
+        match self.char_indices().next_back() {
             None => None,
             Some((idx, ch)) => {
-                self.length = idx as u8;
+                self.length = L::from_usize(idx);
                 self.assert_sanity();
                 Some(ch)
             }
@@ -536,7 +1250,7 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let mut s = InlineString::try_from("foo").unwrap();
+    /// let mut s: InlineString = InlineString::try_from("foo").unwrap();
     /// assert_eq!(s.remove(0), 'f');
     /// assert_eq!(s.remove(1), 'o');
     /// assert_eq!(s.remove(0), 'o');
@@ -580,7 +1294,7 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let mut s = InlineString::try_from("α is not β!").unwrap();
+    /// let mut s: InlineString = InlineString::try_from("α is not β!").unwrap();
     /// let beta_offset = s.find('β').unwrap_or(s.len());
     ///
     /// // Remove the range up until the β from the string
@@ -597,27 +1311,144 @@ impl InlineString {
     where
         R: RangeBounds<usize>,
     {
-        use ops::Bound::*;
-
         let len = self.len();
-        let start = match range.start_bound() {
-            Included(&n) => n,
-            Excluded(&n) => n + 1,
-            Unbounded => 0,
-        };
-        let end = match range.end_bound() {
-            Included(&n) => n + 1,
-            Excluded(&n) => n,
-            Unbounded => len,
-        };
+        let (start, end) = str_buf::byte_range(&range, len);
 
         // Checking bounds.
-        let s: &str = &self;
+        let s: &str = self;
         assert!(s.is_char_boundary(end) && start <= end && s.is_char_boundary(start));
 
         // Start and end are checked, remove everything inside that range.
         self.bytes.copy_within(end.., start);
-        self.length -= (end - start) as u8;
+        self.length = L::from_usize(len - (end - start));
+    }
+
+    /// Removes all non-overlapping matches of `pat`, searching from left to
+    /// right, in a single pass over the string. An empty `pat` matches
+    /// nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::InlineString;
+    ///
+    /// let mut s: InlineString = InlineString::try_from("banana").unwrap();
+    /// s.remove_matches("ana");
+    /// assert_eq!(s, "bna");
+    /// ```
+    #[inline]
+    pub fn remove_matches(&mut self, pat: &str) {
+        self.assert_sanity();
+
+        let len = self.len();
+        let new_len = str_buf::remove_matches(&mut self.bytes[..len], pat);
+        self.length = L::from_usize(new_len);
+
+        self.assert_sanity();
+    }
+
+    /// Removes the specified range from the string buffer in bulk, returning
+    /// all removed characters as an iterator.
+    ///
+    /// The range is removed when the iterator is dropped, even if it was not
+    /// consumed. If the iterator is leaked instead, the string is left
+    /// unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the starting point or end point do not lie on a [`char`]
+    /// boundary, or if they're out of bounds.
+    ///
+    /// [`char`]: https://doc.rust-lang.org/std/primitive.char.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::InlineString;
+    ///
+    /// let mut s: InlineString = InlineString::try_from("α is not β!").unwrap();
+    /// let beta_offset = s.find('β').unwrap_or(s.len());
+    ///
+    /// let drained: String = s.drain(..beta_offset).collect();
+    /// assert_eq!(drained, "α is not ");
+    /// assert_eq!(s, "β!");
+    ///
+    /// // A full range clears the string, like `clear()` does
+    /// s.drain(..);
+    /// assert_eq!(s, "");
+    /// ```
+    #[inline]
+    pub fn drain<R>(&mut self, range: R) -> Drain<'_, N, L>
+    where
+        R: RangeBounds<usize>,
+    {
+        drain::Drain::new(self, range)
+    }
+
+    /// Returns a `fmt::Write` adapter that undoes everything it has written
+    /// when a write overflows.
+    ///
+    /// Writing to the string directly through `fmt::Write` stops at the first
+    /// piece of the output that does not fit, keeping the pieces before it.
+    /// The returned writer instead truncates the string back to the length it
+    /// had when the writer was created, then returns `fmt::Error`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use std::fmt::Write;
+    /// use inlinable_string::InlineString;
+    ///
+    /// let (major, minor) = (12, 345);
+    /// let mut s = InlineString::<8>::try_from("v").unwrap();
+    ///
+    /// // Overflows at the trailing ".0", after "12.345" has been written.
+    /// assert!(write!(s.transactional_writer(), "{}.{}.0", major, minor).is_err());
+    /// assert_eq!(s, "v");
+    ///
+    /// assert!(write!(s.transactional_writer(), "{}.{}", major, minor).is_ok());
+    /// assert_eq!(s, "v12.345");
+    /// ```
+    #[inline]
+    pub fn transactional_writer(&mut self) -> TransactionalWriter<'_, N, L> {
+        TransactionalWriter {
+            start: self.len(),
+            string: self,
+        }
+    }
+
+    /// Returns a `fmt::Write` adapter that writes as much as fits and drops
+    /// the rest.
+    ///
+    /// The output is cut at a char boundary, and everything after the cut is
+    /// dropped, even pieces that would fit into the remaining space. The
+    /// writer never returns an error; use
+    /// [`TruncatingWriter::dropped`](./struct.TruncatingWriter.html#method.dropped)
+    /// to find out how many bytes did not fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fmt::Write;
+    /// use inlinable_string::InlineString;
+    ///
+    /// let word = "größe";
+    /// let mut tag = InlineString::<6>::new();
+    /// let mut writer = tag.truncating_writer();
+    /// write!(writer, "[{}]", word).unwrap();
+    /// // "ß" takes two bytes and only one is left after "[grö".
+    /// assert_eq!(writer.dropped(), 4);
+    /// assert_eq!(tag, "[grö");
+    /// ```
+    #[inline]
+    pub fn truncating_writer(&mut self) -> TruncatingWriter<'_, N, L> {
+        TruncatingWriter {
+            string: self,
+            dropped: 0,
+        }
     }
 
     /// Inserts a character into the string buffer at byte position `idx`.
@@ -628,7 +1459,7 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let mut s = InlineString::try_from("foo").unwrap();
+    /// let mut s: InlineString = InlineString::try_from("foo").unwrap();
     /// s.insert(2, 'f');
     /// assert!(s == "fofo");
     /// ```
@@ -651,7 +1482,7 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let mut s = InlineString::try_from("foo").unwrap();
+    /// let mut s: InlineString = InlineString::try_from("foo").unwrap();
     /// s.insert_str(2, "bar");
     /// assert!(s == "fobaro");
     /// ```
@@ -661,13 +1492,13 @@ impl InlineString {
         let amt = string.len();
         let len_sum = len + amt;
 
-        if len_sum > INLINE_STRING_CAPACITY {
-            return Err(NotEnoughCapacity);
+        if len_sum > N {
+            return Err(NotEnoughCapacity::new(len_sum, N));
         }
 
         // SAFETY:
         // `idx` is a char boundary and <= `len`, thus it's also `<=` lengths' sum,
-        // lengths' sum is checked to be `<=` than `INLINE_STRING_CAPACITY`,
+        // lengths' sum is checked to be `<=` than `N`,
         // and `string` is a well-formed `str`.
         unsafe {
             assert!(self.is_char_boundary(idx));
@@ -683,6 +1514,61 @@ impl InlineString {
         Ok(())
     }
 
+    /// Removes the specified range in the string, and replaces it with the
+    /// given string. The given string doesn't need to be the same length as
+    /// the range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the starting point or end point do not lie on a [`char`]
+    /// boundary, or if they're out of bounds.
+    ///
+    /// [`char`]: https://doc.rust-lang.org/std/primitive.char.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::InlineString;
+    ///
+    /// let mut s: InlineString<13> = InlineString::try_from("α is not β!").unwrap();
+    /// assert!(s.replace_range(..2, "A").is_ok());
+    /// assert_eq!(s, "A is not β!");
+    ///
+    /// assert!(s.replace_range(..1, "Alpha").is_err());
+    /// assert_eq!(s, "A is not β!");
+    /// ```
+    #[inline]
+    pub fn replace_range<R>(
+        &mut self,
+        range: R,
+        replace_with: &str,
+    ) -> Result<(), NotEnoughCapacity>
+    where
+        R: RangeBounds<usize>,
+    {
+        self.assert_sanity();
+
+        let len = self.len();
+        let (start, end) = str_buf::byte_range(&range, len);
+
+        // String index does all bounds checks.
+        let range_len = self[start..end].len();
+        let kept_len = len - range_len;
+        if replace_with.len() > N - kept_len {
+            return Err(NotEnoughCapacity::new(kept_len + replace_with.len(), N));
+        }
+
+        // Move `[end..len]` to its new place, then copy `replace_with` in.
+        let replace_end = start + replace_with.len();
+        self.bytes.copy_within(end..len, replace_end);
+        self.bytes[start..replace_end].copy_from_slice(replace_with.as_bytes());
+        self.length = L::from_usize(kept_len + replace_with.len());
+
+        self.assert_sanity();
+        Ok(())
+    }
+
     /// Views the internal string buffer as a mutable sequence of bytes.
     ///
     /// # Safety
@@ -696,7 +1582,7 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let mut s = InlineString::try_from("hello").unwrap();
+    /// let mut s: InlineString = InlineString::try_from("hello").unwrap();
     /// unsafe {
     ///     let slice = s.as_mut_slice();
     ///     assert!(slice == &[104, 101, 108, 108, 111]);
@@ -707,7 +1593,7 @@ impl InlineString {
     #[inline]
     pub unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
         self.assert_sanity();
-        &mut self.bytes[0..self.length as usize]
+        &mut self.bytes[0..self.length.to_usize()]
     }
 
     /// Returns the number of bytes in this string.
@@ -718,13 +1604,13 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let a = InlineString::try_from("foo").unwrap();
+    /// let a: InlineString = InlineString::try_from("foo").unwrap();
     /// assert_eq!(a.len(), 3);
     /// ```
     #[inline]
     pub fn len(&self) -> usize {
         self.assert_sanity();
-        self.length as usize
+        self.length.to_usize()
     }
 
     /// Returns true if the string contains no bytes
@@ -734,7 +1620,7 @@ impl InlineString {
     /// ```
     /// use inlinable_string::InlineString;
     ///
-    /// let mut v = InlineString::new();
+    /// let mut v: InlineString = InlineString::new();
     /// assert!(v.is_empty());
     /// v.push('a');
     /// assert!(!v.is_empty());
@@ -742,7 +1628,7 @@ impl InlineString {
     #[inline]
     pub fn is_empty(&self) -> bool {
         self.assert_sanity();
-        self.length == 0
+        self.len() == 0
     }
 
     /// Truncates the string, returning it to 0 length.
@@ -753,17 +1639,38 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let mut s = InlineString::try_from("foo").unwrap();
+    /// let mut s: InlineString = InlineString::try_from("foo").unwrap();
     /// s.clear();
     /// assert!(s.is_empty());
     /// ```
     #[inline]
     pub fn clear(&mut self) {
         self.assert_sanity();
-        self.length = 0;
+        self.length = L::ZERO;
         self.assert_sanity();
     }
 
+    /// Copies this string into a [`Box`]`<`[`str`]`>` of exactly its length.
+    ///
+    /// [`Box`]: https://doc.rust-lang.org/std/boxed/struct.Box.html
+    /// [`str`]: https://doc.rust-lang.org/std/primitive.str.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::InlineString;
+    ///
+    /// let s: InlineString = InlineString::try_from("hello").unwrap();
+    /// let b = s.into_boxed_str();
+    /// assert_eq!(&*b, "hello");
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn into_boxed_str(self) -> Box<str> {
+        Box::from(self.as_str())
+    }
+
     /// Splits the string into two at the given index.
     ///
     /// Returns a new buffer. `self` contains bytes `[0, at)`, and
@@ -784,7 +1691,7 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let mut hello = InlineString::try_from("Hello, World!").unwrap();
+    /// let mut hello: InlineString = InlineString::try_from("Hello, World!").unwrap();
     /// let world = hello.split_off(7);
     /// assert_eq!(hello, "Hello, ");
     /// assert_eq!(world, "World!");
@@ -798,9 +1705,9 @@ impl InlineString {
 
         // SAFETY:
         // `s` is a part of `InlineString`, thus its length is never bigger
-        // than `INLINE_STRING_CAPACITY`.
+        // than `N`.
         let right_part = unsafe { Self::from_str_unchecked(s) };
-        self.length = at as u8;
+        self.length = L::from_usize(at);
 
         right_part
     }
@@ -817,7 +1724,7 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let mut s = InlineString::try_from("f_o_ob_ar").unwrap();
+    /// let mut s: InlineString = InlineString::try_from("f_o_ob_ar").unwrap();
     ///
     /// s.retain(|c| c != '_');
     ///
@@ -830,7 +1737,7 @@ impl InlineString {
     /// use std::convert::TryFrom;
     /// use inlinable_string::InlineString;
     ///
-    /// let mut s = InlineString::try_from("abcde").unwrap();
+    /// let mut s: InlineString = InlineString::try_from("abcde").unwrap();
     /// let keep = [false, true, true, false, true];
     /// let mut i = 0;
     /// s.retain(|_| (keep[i], i += 1).0);
@@ -875,61 +1782,682 @@ impl InlineString {
             ptr = next_ptr;
         }
 
-        buffer.length = ptr as u8;
+        buffer.length = L::from_usize(ptr);
         *self = buffer;
     }
+
+    /// Removes the characters for which `f` returns `true` and returns them,
+    /// in their original order, as a new `InlineString`. This is the
+    /// opposite of `retain`.
+    ///
+    /// Every character is visited exactly once, in the original order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::InlineString;
+    ///
+    /// let mut s: InlineString = InlineString::try_from("a1b2c3").unwrap();
+    /// let digits = s.extract_if(|ch| ch.is_ascii_digit());
+    ///
+    /// assert_eq!(s, "abc");
+    /// assert_eq!(digits, "123");
+    /// ```
+    #[inline]
+    pub fn extract_if<F>(&mut self, mut f: F) -> Self
+    where
+        F: FnMut(char) -> bool,
+    {
+        let mut extracted = Self::new();
+        self.retain(|ch| {
+            if f(ch) {
+                // The extracted characters come from `self`, so they fit.
+                let pushed = extracted.push(ch);
+                debug_assert!(pushed.is_ok());
+                false
+            } else {
+                true
+            }
+        });
+        extracted
+    }
+
+    /// Appends every character or string slice of `iter`, or returns
+    /// `NotEnoughCapacity`, leaving the string unchanged, if they don't all
+    /// fit. This is the non-panicking version of `Extend`.
+    ///
+    /// The error's `required` length only counts the items up to and
+    /// including the first one that did not fit, since `iter` is not
+    /// consumed any further.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::InlineString;
+    ///
+    /// let mut s: InlineString<8> = InlineString::new();
+    /// assert!(s.try_extend(vec!["ab", "cd"]).is_ok());
+    /// assert!(s.try_extend("efg".chars()).is_ok());
+    /// assert!(s.try_extend(&['h', 'i']).is_err());
+    /// assert_eq!(s, "abcdefg");
+    /// ```
+    #[inline]
+    pub fn try_extend<I>(&mut self, iter: I) -> Result<(), NotEnoughCapacity>
+    where
+        I: IntoIterator,
+        I::Item: Appendable,
+    {
+        let len = self.len();
+        for item in iter {
+            if let Err(error) = item.append_to(self) {
+                self.truncate(len);
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Collects the characters or string slices of `iter` into a new string,
+    /// or returns `NotEnoughCapacity` if they don't all fit. This is the
+    /// non-panicking version of `FromIterator`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::InlineString;
+    ///
+    /// let s = InlineString::<4>::try_from_iter("abc".chars()).unwrap();
+    /// assert_eq!(s, "abc");
+    /// assert!(InlineString::<4>::try_from_iter(vec!["abc", "de"]).is_err());
+    /// ```
+    #[inline]
+    pub fn try_from_iter<I>(iter: I) -> Result<Self, NotEnoughCapacity>
+    where
+        I: IntoIterator,
+        I::Item: Appendable,
+    {
+        let mut s = Self::new();
+        s.try_extend(iter)?;
+        Ok(s)
+    }
+}
+
+/// A `fmt::Write` sink that only counts the bytes written to it.
+struct LengthCounter(usize);
+
+impl fmt::Write for LengthCounter {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// Creates an `InlineString` or `InlinableString` from a string literal,
+/// checking at compile time that it fits inline.
+///
+/// `inline_str!("...")` evaluates to an `InlineString<N>`, with the capacity
+/// `N` taken from the surrounding code like for `InlineString::new()`. To
+/// name the type, give it before the literal: `inline_str!(T, "...")`, where
+/// `T` is an `InlineString` or `InlinableString` type. Either way the string
+/// is built by `from_str_const` in a `const` block, so a literal that is too
+/// long is a compile-time error, even when the macro is used outside of a
+/// `const` or `static` initializer. An `InlinableString` made this way always
+/// uses `Inline` storage.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate inlinable_string;
+///
+/// use inlinable_string::InlineString;
+///
+/// static WEEKDAYS: [InlineString<3>; 7] = [
+///     inline_str!("Mon"),
+///     inline_str!("Tue"),
+///     inline_str!("Wed"),
+///     inline_str!("Thu"),
+///     inline_str!("Fri"),
+///     inline_str!("Sat"),
+///     inline_str!("Sun"),
+/// ];
+///
+/// # fn main() {
+/// assert_eq!(WEEKDAYS[4], "Fri");
+///
+/// let wide = inline_str!(InlineString<300, u16>, "wide");
+/// assert_eq!(wide.capacity(), 300);
+///
+/// // With the `alloc` feature, it builds `InlinableString`s too.
+/// #[cfg(feature = "alloc")]
+/// {
+///     use inlinable_string::InlinableString;
+///
+///     let greeting = inline_str!(InlinableString, "hello");
+///     assert!(greeting.is_inline());
+/// }
+/// # }
+/// ```
+///
+/// A literal that does not fit fails to compile:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate inlinable_string;
+///
+/// use inlinable_string::InlineString;
+///
+/// # fn main() {
+/// let s: InlineString<4> = inline_str!("too long");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! inline_str {
+    ($s:expr) => {
+        const { $crate::InlineString::from_str_const($s) }
+    };
+    ($ty:ty, $s:expr) => {
+        const { <$ty>::from_str_const($s) }
+    };
+}
+
+/// Formats into an `InlineString`, failing if the output does not fit.
+///
+/// Takes the same arguments as `format!` and returns a
+/// `Result<InlineString, NotEnoughCapacity>`. The string is only returned if
+/// the whole output fits; otherwise the error tells how many bytes it needs.
+/// To pick another capacity or length type, give the `InlineString` type
+/// before the format string: `try_format_inline!(T, "...", args...)`.
+///
+/// See [`InlineString::try_from_fmt`](./inline_string/struct.InlineString.html#method.try_from_fmt).
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate inlinable_string;
+///
+/// use inlinable_string::InlineString;
+///
+/// # fn main() {
+/// let tag = try_format_inline!("[{:>4}]", 42).unwrap();
+/// assert_eq!(tag, "[  42]");
+///
+/// let err = try_format_inline!(InlineString<4>, "[{:>4}]", 42).unwrap_err();
+/// assert_eq!(err.required(), 6);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! try_format_inline {
+    ($fmt:literal $($args:tt)*) => {
+        <$crate::InlineString>::try_from_fmt(format_args!($fmt $($args)*))
+    };
+    ($ty:ty, $fmt:literal $($args:tt)*) => {
+        <$ty>::try_from_fmt(format_args!($fmt $($args)*))
+    };
+}
+
+/// A draining iterator over the characters of an `InlineString`.
+///
+/// This type is created by the [`drain`](./struct.InlineString.html#method.drain)
+/// method on `InlineString`. The drained range is removed from the string
+/// when it is dropped.
+pub type Drain<'a, const N: usize, L = u8> = drain::Drain<'a, InlineString<N, L>>;
+
+impl<const N: usize, L: LengthType> drain::Drainable for InlineString<N, L> {
+    #[inline]
+    fn as_str(&self) -> &str {
+        self
+    }
+
+    #[inline]
+    fn remove_range(&mut self, range: ops::Range<usize>) {
+        InlineString::remove_range(self, range);
+    }
+}
+
+/// A `fmt::Write` adapter for an `InlineString` that rolls back on overflow.
+///
+/// This struct is created by the
+/// [`transactional_writer`](./struct.InlineString.html#method.transactional_writer)
+/// method on `InlineString`.
+pub struct TransactionalWriter<'a, const N: usize, L: LengthType = u8> {
+    string: &'a mut InlineString<N, L>,
+    /// The length to roll back to.
+    start: usize,
+}
+
+impl<'a, const N: usize, L: LengthType> fmt::Write for TransactionalWriter<'a, N, L> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.string.push_str(s) {
+            Ok(()) => Ok(()),
+            Err(_) => {
+                self.string.truncate(self.start);
+                Err(fmt::Error)
+            }
+        }
+    }
+}
+
+impl<'a, const N: usize, L: LengthType> fmt::Debug for TransactionalWriter<'a, N, L> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TransactionalWriter")
+            .field("written", &&self.string[self.start..])
+            .finish()
+    }
+}
+
+/// A `fmt::Write` adapter for an `InlineString` that drops whatever does not
+/// fit.
+///
+/// This struct is created by the
+/// [`truncating_writer`](./struct.InlineString.html#method.truncating_writer)
+/// method on `InlineString`.
+pub struct TruncatingWriter<'a, const N: usize, L: LengthType = u8> {
+    string: &'a mut InlineString<N, L>,
+    dropped: usize,
+}
+
+impl<'a, const N: usize, L: LengthType> TruncatingWriter<'a, N, L> {
+    /// Returns the number of bytes that were dropped because they did not
+    /// fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fmt::Write;
+    /// use inlinable_string::InlineString;
+    ///
+    /// let mut s = InlineString::<4>::new();
+    /// let mut writer = s.truncating_writer();
+    /// writer.write_str("abc").unwrap();
+    /// assert_eq!(writer.dropped(), 0);
+    /// writer.write_str("def").unwrap();
+    /// assert_eq!(writer.dropped(), 2);
+    /// assert_eq!(s, "abcd");
+    /// ```
+    #[inline]
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Returns `true` if any output was dropped.
+    #[inline]
+    pub fn is_truncated(&self) -> bool {
+        self.dropped > 0
+    }
+}
+
+impl<'a, const N: usize, L: LengthType> fmt::Write for TruncatingWriter<'a, N, L> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.dropped > 0 {
+            self.dropped += s.len();
+            return Ok(());
+        }
+
+        let mut end = cmp::min(s.len(), N - self.string.len());
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        // `end` bytes always fit.
+        let _ = self.string.push_str(&s[..end]);
+        self.dropped = s.len() - end;
+        Ok(())
+    }
+}
+
+impl<'a, const N: usize, L: LengthType> fmt::Debug for TruncatingWriter<'a, N, L> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TruncatingWriter")
+            .field("string", &&**self.string)
+            .field("dropped", &self.dropped)
+            .finish()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{InlineString, NotEnoughCapacity, TryFrom, INLINE_STRING_CAPACITY};
+    use std::string::{String, ToString};
 
     #[test]
     fn test_push_str() {
-        let mut s = InlineString::new();
+        let mut s: InlineString = InlineString::new();
         assert!(s.push_str("small").is_ok());
         assert_eq!(s, "small");
 
         let long_str = "this is a really long string that is much larger than
                         INLINE_STRING_CAPACITY and so cannot be stored inline.";
-        assert_eq!(s.push_str(long_str), Err(NotEnoughCapacity));
+        let required = "small".len() + long_str.len();
+        assert_eq!(
+            s.push_str(long_str),
+            Err(NotEnoughCapacity::new(required, INLINE_STRING_CAPACITY))
+        );
         assert_eq!(s, "small");
     }
 
     #[test]
     fn test_push() {
-        let mut s = InlineString::new();
+        let mut s: InlineString = InlineString::new();
 
         for _ in 0..INLINE_STRING_CAPACITY {
             assert!(s.push('a').is_ok());
         }
 
-        assert_eq!(s.push('a'), Err(NotEnoughCapacity));
+        assert_eq!(
+            s.push('a'),
+            Err(NotEnoughCapacity::new(
+                INLINE_STRING_CAPACITY + 1,
+                INLINE_STRING_CAPACITY
+            ))
+        );
+    }
+
+    #[test]
+    fn test_push_multibyte() {
+        let mut s: InlineString<5> = InlineString::new();
+        assert!(s.push('é').is_ok());
+        assert!(s.push('€').is_ok());
+        assert_eq!(s, "é€");
+        assert_eq!(s.push('ß'), Err(NotEnoughCapacity::new(7, 5)));
+        assert_eq!(s, "é€");
     }
 
     #[test]
     fn test_insert() {
-        let mut s = InlineString::new();
+        let mut s: InlineString = InlineString::new();
 
         for _ in 0..INLINE_STRING_CAPACITY {
             assert!(s.insert(0, 'a').is_ok());
         }
 
-        assert_eq!(s.insert(0, 'a'), Err(NotEnoughCapacity));
+        assert_eq!(
+            s.insert(0, 'a'),
+            Err(NotEnoughCapacity::new(
+                INLINE_STRING_CAPACITY + 1,
+                INLINE_STRING_CAPACITY
+            ))
+        );
     }
 
     #[test]
     #[should_panic]
     fn insert_panic() {
-        let mut s = InlineString::try_from("щ").unwrap();
+        let mut s: InlineString = InlineString::try_from("щ").unwrap();
         let _ = s.insert(1, 'q');
     }
 
+    #[test]
+    fn test_replace_range() {
+        let mut s: InlineString<8> = InlineString::try_from("abcdef").unwrap();
+        assert!(s.replace_range(1..3, "XYZW").is_ok());
+        assert_eq!(s, "aXYZWdef");
+        assert!(s.replace_range(1..=4, "").is_ok());
+        assert_eq!(s, "adef");
+        assert!(s.replace_range(.., "12345678").is_ok());
+        assert_eq!(s, "12345678");
+        assert_eq!(
+            s.replace_range(..1, "ab"),
+            Err(NotEnoughCapacity::new(9, 8))
+        );
+        assert_eq!(s, "12345678");
+    }
+
+    #[test]
+    #[should_panic]
+    fn replace_range_panic() {
+        let mut s: InlineString = InlineString::try_from("щ").unwrap();
+        let _ = s.replace_range(..1, "q");
+    }
+
+    #[test]
+    fn test_fallible_string_mut() {
+        use {FallibleStringMut, StrBuf};
+
+        fn fill<S: FallibleStringMut>(s: &mut S) -> Result<(), S::Error> {
+            s.try_push_str("bc")?;
+            s.try_insert(0, 'a')?;
+            s.try_replace_range(1..2, "BB")?;
+            s.try_push('d')
+        }
+
+        let mut s: InlineString<5> = InlineString::new();
+        assert_eq!(fill(&mut s), Ok(()));
+        assert_eq!(s, "aBBcd");
+        assert_eq!(StrBuf::capacity(&s), 5);
+        assert_eq!(StrBuf::len(&s), 5);
+
+        let mut s: InlineString<4> = InlineString::new();
+        assert_eq!(fill(&mut s), Err(NotEnoughCapacity::new(5, 4)));
+        assert_eq!(s, "aBBc");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_not_enough_capacity() {
+        use std::error::Error;
+
+        fn push_all(s: &mut InlineString<4>, words: &[&str]) -> Result<(), Box<dyn Error>> {
+            for word in words {
+                s.push_str(word)?;
+            }
+            Ok(())
+        }
+
+        let mut s: InlineString<4> = InlineString::new();
+        let err = push_all(&mut s, &["ab", "cde"]).unwrap_err();
+        let err = err.downcast_ref::<NotEnoughCapacity>().unwrap();
+        assert_eq!((err.required(), err.capacity(), err.excess()), (5, 4, 1));
+        assert_eq!(s, "ab");
+
+        let err = InlineString::<4>::try_from("hello").unwrap_err();
+        assert_eq!(err, NotEnoughCapacity::new(5, 4));
+    }
+
+    #[test]
+    fn test_std_traits() {
+        use std::collections::{BTreeSet, HashSet};
+        use std::iter::FromIterator;
+
+        let a: InlineString = "a".parse().unwrap();
+        let b = InlineString::try_from('b').unwrap();
+        assert!(a < b);
+        assert_eq!(a.cmp(&a), ::std::cmp::Ordering::Equal);
+
+        let sorted: BTreeSet<InlineString> = vec![b.clone(), a.clone()].into_iter().collect();
+        assert!(sorted.contains("a"));
+        let hashed: HashSet<InlineString> = vec![a.clone(), b.clone()].into_iter().collect();
+        assert!(hashed.contains("b"));
+
+        assert_eq!(
+            "abcde".parse::<InlineString<4>>(),
+            Err(NotEnoughCapacity::new(5, 4))
+        );
+        assert_eq!(
+            InlineString::<1>::try_from('é'),
+            Err(NotEnoughCapacity::new(2, 1))
+        );
+
+        let mut s: InlineString<8> = InlineString::from_iter(vec!["ab", "c"]);
+        s.extend(&['d', 'e']);
+        s.extend("f".chars());
+        s += "g";
+        let s = s + "h";
+        assert_eq!(s, "abcdefgh");
+        assert_eq!(InlineString::<8>::from_iter("xyz".chars()), "xyz");
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_string_traits() {
+        let a: InlineString = InlineString::try_from("a").unwrap();
+        assert_eq!(a, String::from("a"));
+        assert_eq!(String::from("a"), a);
+
+        let s = InlineString::<4>::try_from(String::from("abcd")).unwrap();
+        assert_eq!(s, "abcd");
+        let rejected = InlineString::<4>::try_from(String::from("abcde")).unwrap_err();
+        assert_eq!(
+            rejected.into_parts(),
+            (String::from("abcde"), NotEnoughCapacity::new(5, 4))
+        );
+    }
+
+    #[test]
+    fn test_debug() {
+        let s: InlineString<4> = InlineString::try_from("a\"é").unwrap();
+        assert_eq!(format!("{:?}", s), r#""a\"é""#);
+        assert_eq!(
+            format!("{:#?}", s),
+            "InlineString {\n    value: \"a\\\"é\",\n    len: 4,\n    capacity: 4,\n}"
+        );
+        assert_eq!(format!("{:?}", Some(s)), r#"Some("a\"é")"#);
+    }
+
+    #[test]
+    fn test_try_extend() {
+        let mut s: InlineString<4> = InlineString::try_from("ab").unwrap();
+        assert_eq!(
+            s.try_extend(vec!["c", "de", "f"]),
+            Err(NotEnoughCapacity::new(5, 4))
+        );
+        assert_eq!(s, "ab");
+        assert_eq!(s.try_extend("cd".chars()), Ok(()));
+        assert_eq!(s, "abcd");
+        assert_eq!(
+            InlineString::<4>::try_from_iter("abcde".chars()),
+            Err(NotEnoughCapacity::new(5, 4))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "the result needs 5 bytes")]
+    fn extend_panic() {
+        let mut s: InlineString<4> = InlineString::new();
+        s.extend(vec!["abc", "de"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the result needs 5 bytes")]
+    fn add_panic() {
+        let s: InlineString<4> = InlineString::try_from("abc").unwrap();
+        let _ = s + "de";
+    }
+
+    #[test]
+    fn test_rejected() {
+        use FallibleStringMut;
+
+        let mut s: InlineString<4> = InlineString::new();
+        assert_eq!(s.push_str_or_reject("abc"), Ok(()));
+        let rejected = s.push_str_or_reject("de").unwrap_err();
+        assert_eq!(*rejected.input(), "de");
+        assert_eq!(*rejected.error(), NotEnoughCapacity::new(5, 4));
+        assert_eq!(rejected.to_string(), rejected.error().to_string());
+        #[cfg(feature = "std")]
+        {
+            use std::error::Error;
+            assert!(rejected.source().is_none());
+        }
+        assert_eq!(rejected.into_parts(), ("de", NotEnoughCapacity::new(5, 4)));
+
+        assert_eq!(s.push_or_reject('d'), Ok(()));
+        assert_eq!(s.push_or_reject('e').unwrap_err().into_inner(), 'e');
+        assert_eq!(s.insert_or_reject(0, 'x').unwrap_err().into_inner(), 'x');
+        assert_eq!(
+            s.insert_str_or_reject(0, "").map_err(|r| r.into_inner()),
+            Ok(())
+        );
+        assert_eq!(
+            s.insert_str_or_reject(4, "x").unwrap_err().into_inner(),
+            "x"
+        );
+        assert_eq!(s, "abcd");
+    }
+
+    #[test]
+    fn test_extract_if() {
+        let mut s: InlineString<8> = InlineString::try_from("a1é2b3").unwrap();
+        let digits = s.extract_if(|ch| ch.is_ascii_digit());
+        assert_eq!(s, "aéb");
+        assert_eq!(digits, "123");
+        assert_eq!(s.extract_if(|_| true), "aéb");
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn test_extend_from_within() {
+        let mut s: InlineString<12> = InlineString::try_from("ab€").unwrap();
+        assert_eq!(s.extend_from_within(1..), Ok(()));
+        assert_eq!(s, "ab€b€");
+        assert_eq!(s.extend_from_within(..=1), Ok(()));
+        assert_eq!(s, "ab€b€ab");
+        assert_eq!(
+            s.extend_from_within(..),
+            Err(NotEnoughCapacity::new(22, 12))
+        );
+        assert_eq!(s, "ab€b€ab");
+    }
+
+    #[test]
+    #[should_panic]
+    fn extend_from_within_panic() {
+        let mut s: InlineString<8> = InlineString::try_from("a€b").unwrap();
+        let _ = s.extend_from_within(..2);
+    }
+
+    #[test]
+    fn test_remove_matches() {
+        let mut s: InlineString<16> = InlineString::try_from("a€b€€c").unwrap();
+        s.remove_matches("€");
+        assert_eq!(s, "abc");
+        s.remove_matches("");
+        assert_eq!(s, "abc");
+        s.remove_matches("bc");
+        assert_eq!(s, "a");
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_into_boxed_str() {
+        let s: InlineString = InlineString::try_from("boxed").unwrap();
+        assert_eq!(&*s.into_boxed_str(), "boxed");
+    }
+
+    #[test]
+    fn test_drain() {
+        let mut s: InlineString<16> = InlineString::try_from("a€b🦀c").unwrap();
+        {
+            let mut drain = s.drain(1..9);
+            assert_eq!(drain.as_str(), "€b🦀");
+            assert_eq!(drain.next_back(), Some('🦀'));
+            assert_eq!(drain.next(), Some('€'));
+            assert_eq!(format!("{:?}", drain), "Drain(\"b\")");
+        }
+        assert_eq!(s, "ac");
+
+        let drain = s.drain(..);
+        ::std::mem::forget(drain);
+        assert_eq!(s, "ac");
+
+        assert_eq!(s.drain(..).collect::<String>(), "ac");
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn drain_panic() {
+        let mut s: InlineString<8> = InlineString::try_from("a€b").unwrap();
+        s.drain(2..);
+    }
+
     #[test]
     fn test_write() {
-        use fmt::{Error, Write};
+        use std::fmt::{Error, Write};
 
-        let mut s = InlineString::new();
+        let mut s: InlineString = InlineString::new();
         let mut normal_string = String::new();
 
         for _ in 0..INLINE_STRING_CAPACITY {
@@ -940,6 +2468,242 @@ mod tests {
         assert_eq!(write!(&mut s, "a"), Err(Error));
         assert_eq!(&normal_string[..], &s[..]);
     }
+
+    #[test]
+    fn test_custom_capacity() {
+        let mut s = InlineString::<8>::new();
+        assert_eq!(s.capacity(), 8);
+
+        assert!(s.push_str("utf-8").is_ok());
+        assert_eq!(s.push_str("-encoded"), Err(NotEnoughCapacity::new(13, 8)));
+        assert!(s.insert_str(0, "ok ").is_ok());
+        assert_eq!(s, "ok utf-8");
+        assert_eq!(s.push('!'), Err(NotEnoughCapacity::new(9, 8)));
+
+        let tail = s.split_off(3);
+        assert_eq!(s, "ok ");
+        assert_eq!(tail, "utf-8");
+
+        let bytes = tail.into_bytes();
+        assert_eq!(bytes, *b"utf-8\0\0\0");
+
+        assert!(InlineString::<200>::try_from(&"a".repeat(200)[..]).is_ok());
+        assert!(InlineString::<200>::try_from(&"a".repeat(201)[..]).is_err());
+    }
+
+    #[test]
+    fn test_length_type_boundaries() {
+        let full = "a".repeat(255);
+        let mut s = InlineString::<255>::try_from(&full[..]).unwrap();
+        assert_eq!(s.len(), 255);
+        assert_eq!(s.push('a'), Err(NotEnoughCapacity::new(256, 255)));
+        assert_eq!(s.pop(), Some('a'));
+        assert!(s.push('a').is_ok());
+
+        let full = "a".repeat(256);
+        let mut s = InlineString::<256, u16>::try_from(&full[..]).unwrap();
+        assert_eq!(s.len(), 256);
+        assert_eq!(s.push('a'), Err(NotEnoughCapacity::new(257, 256)));
+        s.truncate(1);
+        assert_eq!(s, "a");
+
+        let full = "a".repeat(u16::MAX as usize);
+        let s = InlineString::<{ u16::MAX as usize }, u16>::try_from(&full[..]).unwrap();
+        assert_eq!(s.len(), u16::MAX as usize);
+    }
+
+    #[test]
+    fn test_wide_length_operations() {
+        // Every operation that writes the length, run with lengths that do not
+        // fit into a single byte.
+        let chunk = "0123456789abcdeф";
+        let mut s = InlineString::<1024, u16>::new();
+        let mut expected = String::new();
+        while s.push_str(chunk).is_ok() {
+            expected.push_str(chunk);
+        }
+        assert_eq!(s, &expected[..]);
+        assert!(s.len() > 1000);
+
+        s.insert(300, 'x').unwrap();
+        expected.insert(300, 'x');
+        s.insert_str(0, "yz").unwrap();
+        expected.insert_str(0, "yz");
+        assert_eq!(s, &expected[..]);
+
+        assert_eq!(s.remove(600), expected.remove(600));
+        s.remove_range(10..520);
+        expected.drain(10..520);
+        assert_eq!(s, &expected[..]);
+
+        s.replace_range(2..4, "фф").unwrap();
+        expected.replace_range(2..4, "фф");
+        assert_eq!(s, &expected[..]);
+
+        let tail = s.split_off(300);
+        assert_eq!(tail, &expected.split_off(300)[..]);
+        assert_eq!(s, &expected[..]);
+
+        s.retain(|ch| ch != 'ф');
+        expected.retain(|ch| ch != 'ф');
+        assert_eq!(s, &expected[..]);
+        assert_eq!(s.pop(), expected.pop());
+
+        s.clear();
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn test_from_str_const() {
+        const EMPTY: InlineString<4> = InlineString::new();
+        static TABLE: [InlineString<8>; 3] = [
+            InlineString::from_str_const(""),
+            InlineString::from_str_const("naïve"),
+            InlineString::from_str_const("8 bytes!"),
+        ];
+        static WIDE: InlineString<300, u16> = InlineString::from_str_const("wide");
+
+        assert!(EMPTY.is_empty());
+        assert_eq!(TABLE[0], "");
+        assert_eq!(TABLE[1], "naïve");
+        assert_eq!(TABLE[1].len(), 6);
+        assert_eq!(TABLE[2], "8 bytes!");
+        assert_eq!(WIDE, "wide");
+        assert_eq!(WIDE.len(), 4);
+        assert_eq!(WIDE.capacity(), 300);
+        assert_eq!(
+            InlineString::<300, u16>::from_str_const(&"x".repeat(300)).len(),
+            300
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_str_const_overflow() {
+        let _ = InlineString::<4>::from_str_const("too long");
+    }
+
+    #[test]
+    fn test_inline_str() {
+        static TABLE: [InlineString<5>; 2] = [inline_str!("ab"), inline_str!("hello")];
+        assert_eq!(TABLE[0], "ab");
+        assert_eq!(TABLE[1], "hello");
+
+        let s: InlineString = inline_str!("inferred");
+        assert_eq!(s, "inferred");
+        assert_eq!(s.capacity(), INLINE_STRING_CAPACITY);
+
+        let s = inline_str!(InlineString<300, u16>, "explicit");
+        assert_eq!(s, "explicit");
+        assert_eq!(s.capacity(), 300);
+    }
+
+    #[test]
+    fn test_try_from_fmt() {
+        let s = InlineString::<8>::try_from_fmt(format_args!("{}:{}", "a", 1)).unwrap();
+        assert_eq!(s, "a:1");
+        let s = InlineString::<8>::try_from_fmt(format_args!("8 bytes!")).unwrap();
+        assert_eq!(s, "8 bytes!");
+
+        assert_eq!(
+            InlineString::<8>::try_from_fmt(format_args!("{}{}", "1234", "56789")),
+            Err(NotEnoughCapacity::new(9, 8))
+        );
+        assert_eq!(
+            InlineString::<8>::try_from_fmt(format_args!("9 bytes!!")),
+            Err(NotEnoughCapacity::new(9, 8))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "a formatting trait implementation returned an error")]
+    fn test_try_from_fmt_trait_error() {
+        use core::fmt;
+
+        struct Failing;
+        impl fmt::Display for Failing {
+            fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+                Err(fmt::Error)
+            }
+        }
+
+        let _ = InlineString::<8>::try_from_fmt(format_args!("{}", Failing));
+    }
+
+    #[test]
+    fn test_try_format_inline() {
+        let s = try_format_inline!("{:03}", 7).unwrap();
+        assert_eq!(s, "007");
+        assert_eq!(s.capacity(), INLINE_STRING_CAPACITY);
+
+        let s = try_format_inline!(InlineString<300, u16>, "{:>300}", "x").unwrap();
+        assert_eq!(s.len(), 300);
+
+        let s = try_format_inline!("comma: {}", 1,).unwrap();
+        assert_eq!(s, "comma: 1");
+
+        assert_eq!(
+            try_format_inline!(InlineString<4>, "{}{}", "ab", "cde"),
+            Err(NotEnoughCapacity::new(5, 4))
+        );
+    }
+
+    #[test]
+    fn test_transactional_writer() {
+        use core::fmt::Write;
+
+        let (cd, ef, hi) = ("cd", "ef", "hi");
+        let (h, i) = ('h', 'i');
+        let mut s = InlineString::<8>::try_from("ab").unwrap();
+        {
+            let mut writer = s.transactional_writer();
+            assert!(write!(writer, "{}{}", cd, ef).is_ok());
+            assert!(write!(writer, "g{}", hi).is_err());
+        }
+        // Rolls back to where the writer started, not to the last `write!`.
+        assert_eq!(s, "ab");
+
+        assert!(write!(s.transactional_writer(), "{}{}g{}", cd, ef, h).is_ok());
+        assert_eq!(s, "abcdefgh");
+        assert!(write!(s.transactional_writer(), "").is_ok());
+        assert!(write!(s.transactional_writer(), "{}", i).is_err());
+        assert_eq!(s, "abcdefgh");
+    }
+
+    #[test]
+    fn test_truncating_writer() {
+        use core::fmt::Write;
+
+        let (a, bc, de, f) = ('a', "bc", "dé", "f");
+        let mut s = InlineString::<5>::try_from("a").unwrap();
+        {
+            let mut writer = s.truncating_writer();
+            assert!(write!(writer, "{}", bc).is_ok());
+            assert!(!writer.is_truncated());
+            // 'é' is two bytes, but only one is left after "abcd".
+            assert!(write!(writer, "{}{}", de, f).is_ok());
+            assert!(writer.is_truncated());
+            assert_eq!(writer.dropped(), 3);
+            // Later pieces are dropped even if they would fit.
+            assert!(write!(writer, "{}", f).is_ok());
+            assert_eq!(writer.dropped(), 4);
+        }
+        assert_eq!(s, "abcd");
+
+        let mut s = InlineString::<3>::new();
+        let mut writer = s.truncating_writer();
+        assert!(write!(writer, "{}{}", a, bc).is_ok());
+        assert_eq!(writer.dropped(), 0);
+        assert_eq!(s, "abc");
+    }
+
+    #[test]
+    fn test_eq_across_capacities() {
+        let small = InlineString::<8>::try_from("label").unwrap();
+        let big = InlineString::<64>::try_from("label").unwrap();
+        assert_eq!(small, big);
+        assert_eq!(big, small);
+    }
 }
 
 #[cfg(test)]
@@ -1,9 +1,15 @@
+use alloc::string::String;
+use core::fmt;
+use core::marker::PhantomData;
 use serde::de::{Deserialize, Deserializer, Error as DeError, Visitor};
 use serde::{Serialize, Serializer};
-use std::fmt;
-use InlinableString;
+use {GrowthPolicy, HeapStorage, InlinableString};
 
-impl Serialize for InlinableString {
+impl<const N: usize, H, P> Serialize for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
@@ -12,15 +18,23 @@ impl Serialize for InlinableString {
     }
 }
 
-impl<'de> Deserialize<'de> for InlinableString {
+impl<'de, const N: usize, H, P> Deserialize<'de> for InlinableString<N, H, P>
+where
+    H: HeapStorage,
+    P: GrowthPolicy,
+{
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        struct InlinableStringVisitor;
+        struct InlinableStringVisitor<const N: usize, H, P>(PhantomData<(H, P)>);
 
-        impl<'de> Visitor<'de> for InlinableStringVisitor {
-            type Value = InlinableString;
+        impl<'de, const N: usize, H, P> Visitor<'de> for InlinableStringVisitor<N, H, P>
+        where
+            H: HeapStorage,
+            P: GrowthPolicy,
+        {
+            type Value = InlinableString<N, H, P>;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
                 formatter.write_str("a string")
@@ -41,7 +55,7 @@ impl<'de> Deserialize<'de> for InlinableString {
             }
         }
 
-        deserializer.deserialize_str(InlinableStringVisitor)
+        deserializer.deserialize_str(InlinableStringVisitor(PhantomData))
     }
 }
 
@@ -52,8 +66,16 @@ mod tests {
 
     #[test]
     fn test_ser_de() {
-        let s = InlinableString::from("small");
+        let s: InlinableString = InlinableString::from("small");
 
         assert_tokens(&s, &[Token::String("small")]);
     }
+
+    #[test]
+    fn test_ser_de_custom_capacity() {
+        let s: InlinableString<8> = InlinableString::from("too long for eight bytes");
+
+        assert!(s.is_heap());
+        assert_tokens(&s, &[Token::String("too long for eight bytes")]);
+    }
 }
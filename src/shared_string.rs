@@ -0,0 +1,687 @@
+// Copyright 2015, The inlinable_string crate Developers. See the COPYRIGHT file
+// at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT
+// or http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A heap-allocated string whose clones share a single reference-counted
+//! buffer, and the `SharedInlinableString` type built on top of it.
+//!
+//! Cloning a `SharedString` only bumps a reference count. The first mutation
+//! through `StringExt` of a string whose buffer is shared copies the contents
+//! into a buffer of its own (copy on write), so that further mutations happen
+//! in place.
+//!
+//! # Examples
+//!
+//! ```
+//! use inlinable_string::{SharedInlinableString, StringExt};
+//!
+//! let a: SharedInlinableString = SharedInlinableString::from("a string too long to be stored inline");
+//! let mut b = a.clone();
+//! assert!(b.is_shared());
+//! assert_eq!(a.as_ptr(), b.as_ptr());
+//!
+//! // Writing to `b` copies it; `a` is left untouched.
+//! b.push_str("!");
+//! assert!(!b.is_shared());
+//! assert_eq!(a, "a string too long to be stored inline");
+//! assert_eq!(b, "a string too long to be stored inline!");
+//!
+//! // Short strings are still stored inline.
+//! let c: SharedInlinableString = SharedInlinableString::from("small");
+//! assert!(c.is_inline());
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::borrow::{Borrow, BorrowMut};
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use core::fmt;
+use core::hash;
+use core::ops::{self, RangeBounds};
+use heap_storage::PackedString;
+use string_ext;
+use {
+    FallibleStringMut, GrowthPolicy, HeapStorage, InlinableString, NeverDemote, StrBuf, StringExt,
+    StringStorage, TryReserveError, INLINABLE_STRING_CAPACITY,
+};
+
+/// An `InlinableString` whose heap-allocated strings are reference counted,
+/// so that cloning them is O(1) once they are shared.
+///
+/// See the [module level documentation](./index.html) for more.
+pub type SharedInlinableString<const N: usize = INLINABLE_STRING_CAPACITY, P = NeverDemote> =
+    InlinableString<N, SharedString, P>;
+
+/// A heap-allocated string kept in an `Arc<String>`, which its clones share.
+///
+/// # Cloning
+///
+/// Cloning a `SharedString` bumps a reference count, no matter how the string
+/// was created. Mutating a string whose buffer is shared with one of its
+/// clones copies it first:
+///
+/// ```
+/// use inlinable_string::{SharedString, StringExt};
+///
+/// let mut s = SharedString::new();
+/// s.push_str("built piece by piece");
+///
+/// let mut t = s.clone();
+/// assert!(s.is_shared() && t.is_shared());
+/// assert_eq!(s.as_ptr(), t.as_ptr());
+///
+/// t.push('!');
+/// assert!(!s.is_shared() && !t.is_shared());
+/// assert_eq!(s, "built piece by piece");
+/// assert_eq!(t, "built piece by piece!");
+/// ```
+///
+/// See the [module level documentation](./index.html) for more.
+#[derive(Clone)]
+pub struct SharedString(Arc<String>);
+
+impl SharedString {
+    /// Returns a mutable reference to the string, copying the contents out
+    /// of the shared buffer first if a clone still refers to it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::SharedString;
+    ///
+    /// let mut s = SharedString::from("shared");
+    /// let t = s.clone();
+    /// s.to_mut().push_str(" no more");
+    /// assert_eq!(s, "shared no more");
+    /// assert_eq!(t, "shared");
+    /// ```
+    #[inline]
+    pub fn to_mut(&mut self) -> &mut String {
+        Arc::make_mut(&mut self.0)
+    }
+
+    /// Like `to_mut`, but leaves room for `additional` more bytes when the
+    /// contents are copied, and returns an error instead of aborting if that
+    /// copy cannot be allocated.
+    fn try_to_mut(&mut self, additional: usize) -> Result<&mut String, TryReserveError> {
+        if Arc::get_mut(&mut self.0).is_none() {
+            let capacity = string_ext::required_capacity(self.len(), additional)?;
+            let mut owned = <String as StringExt>::try_with_capacity(capacity)?;
+            owned.push_str(&self.0);
+            self.0 = Arc::new(owned);
+        }
+        Ok(Arc::make_mut(&mut self.0))
+    }
+
+    /// Returns `true` if a clone of the string still refers to its buffer,
+    /// so that the next mutation copies it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::SharedString;
+    ///
+    /// let s = SharedString::from("shared");
+    /// assert!(!s.is_shared());
+    ///
+    /// let t = s.clone();
+    /// assert!(s.is_shared());
+    /// drop(t);
+    /// assert!(!s.is_shared());
+    /// ```
+    #[inline]
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.0) > 1
+    }
+}
+
+// A shared string packs into the pointer of its `Arc<String>` and the length
+// of the string, with a zero capacity.
+unsafe impl HeapStorage for SharedString {
+    type Packed = PackedString;
+    type Allocator = Infallible;
+
+    #[inline]
+    fn allocate(capacity: usize, _: Option<&Infallible>) -> SharedString {
+        SharedString::with_capacity(capacity)
+    }
+
+    #[inline]
+    fn try_allocate(
+        capacity: usize,
+        _: Option<&Infallible>,
+    ) -> Result<SharedString, TryReserveError> {
+        StringExt::try_with_capacity(capacity).map(|s| SharedString(Arc::new(s)))
+    }
+
+    #[inline]
+    fn clone_allocator(&self) -> Option<Infallible> {
+        None
+    }
+
+    #[inline]
+    fn pack(self) -> (PackedString, u8) {
+        let len = self.len();
+        PackedString::new(Arc::into_raw(self.0) as *mut u8, len, 0)
+    }
+
+    #[inline]
+    unsafe fn unpack(packed: PackedString, _bits: u8) -> SharedString {
+        SharedString(Arc::from_raw(packed.ptr() as *const String))
+    }
+}
+
+impl<const N: usize, P> InlinableString<N, SharedString, P>
+where
+    P: GrowthPolicy,
+{
+    /// Returns `true` if the string is heap-allocated and a clone of it
+    /// still refers to its buffer, so that the next mutation copies it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{SharedInlinableString, StringExt};
+    ///
+    /// let mut s = SharedInlinableString::<8>::from("a heap-allocated string");
+    /// assert!(!s.is_shared());
+    ///
+    /// let t = s.clone();
+    /// assert!(s.is_shared());
+    ///
+    /// s.push('!');
+    /// assert!(!s.is_shared() && !t.is_shared());
+    /// ```
+    pub fn is_shared(&self) -> bool {
+        self.with_heap(SharedString::is_shared).unwrap_or(false)
+    }
+}
+
+impl<const N: usize, P> From<Arc<String>> for InlinableString<N, SharedString, P>
+where
+    P: GrowthPolicy,
+{
+    #[inline]
+    fn from(string: Arc<String>) -> Self {
+        if string.len() <= N {
+            InlinableString::from(string.as_str())
+        } else {
+            InlinableString::from(StringStorage::Heap(SharedString(string)))
+        }
+    }
+}
+
+impl From<&str> for SharedString {
+    #[inline]
+    fn from(string: &str) -> SharedString {
+        SharedString(Arc::new(String::from(string)))
+    }
+}
+
+impl From<String> for SharedString {
+    #[inline]
+    fn from(string: String) -> SharedString {
+        SharedString(Arc::new(string))
+    }
+}
+
+impl From<Arc<String>> for SharedString {
+    #[inline]
+    fn from(string: Arc<String>) -> SharedString {
+        SharedString(string)
+    }
+}
+
+impl From<SharedString> for String {
+    #[inline]
+    fn from(string: SharedString) -> String {
+        Arc::try_unwrap(string.0).unwrap_or_else(|shared| String::clone(&shared))
+    }
+}
+
+impl Default for SharedString {
+    #[inline]
+    fn default() -> SharedString {
+        SharedString::new()
+    }
+}
+
+impl ops::Deref for SharedString {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl ops::DerefMut for SharedString {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut str {
+        self.to_mut()
+    }
+}
+
+impl AsRef<str> for SharedString {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl Borrow<str> for SharedString {
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl BorrowMut<str> for SharedString {
+    fn borrow_mut(&mut self) -> &mut str {
+        self
+    }
+}
+
+impl fmt::Debug for SharedString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl fmt::Display for SharedString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl hash::Hash for SharedString {
+    #[inline]
+    fn hash<Hs: hash::Hasher>(&self, hasher: &mut Hs) {
+        (**self).hash(hasher)
+    }
+}
+
+impl PartialOrd for SharedString {
+    fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
+        Some(Ord::cmp(self, rhs))
+    }
+}
+
+impl Ord for SharedString {
+    #[inline]
+    fn cmp(&self, rhs: &Self) -> Ordering {
+        Ord::cmp(&**self, &**rhs)
+    }
+}
+
+impl PartialEq for SharedString {
+    #[inline]
+    fn eq(&self, rhs: &Self) -> bool {
+        PartialEq::eq(&**self, &**rhs)
+    }
+}
+
+impl Eq for SharedString {}
+
+macro_rules! impl_eq {
+    ($lhs:ty, $rhs: ty) => {
+        impl<'a> PartialEq<$rhs> for $lhs {
+            #[inline]
+            fn eq(&self, other: &$rhs) -> bool {
+                PartialEq::eq(&self[..], &other[..])
+            }
+        }
+
+        impl<'a> PartialEq<$lhs> for $rhs {
+            #[inline]
+            fn eq(&self, other: &$lhs) -> bool {
+                PartialEq::eq(&self[..], &other[..])
+            }
+        }
+    };
+}
+
+impl_eq! { SharedString, str }
+impl_eq! { SharedString, String }
+impl_eq! { SharedString, &'a str }
+impl_eq! { Cow<'a, str>, SharedString }
+
+impl StrBuf for SharedString {
+    #[inline]
+    fn as_str(&self) -> &str {
+        self
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        if self.is_shared() {
+            self.len()
+        } else {
+            self.0.capacity()
+        }
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+}
+
+impl FallibleStringMut for SharedString {
+    type Error = TryReserveError;
+
+    #[inline]
+    fn try_push_str(&mut self, string: &str) -> Result<(), TryReserveError> {
+        string_ext::reserve_and_push_str(self, string)
+    }
+
+    #[inline]
+    fn try_insert_str(&mut self, idx: usize, string: &str) -> Result<(), TryReserveError> {
+        string_ext::reserve_and_insert_str(self, idx, string)
+    }
+
+    #[inline]
+    fn try_replace_range<R>(&mut self, range: R, replace_with: &str) -> Result<(), TryReserveError>
+    where
+        R: RangeBounds<usize>,
+    {
+        string_ext::reserve_and_replace_range(self, range, replace_with)
+    }
+}
+
+impl StringExt for SharedString {
+    #[inline]
+    fn with_capacity(capacity: usize) -> Self {
+        SharedString(Arc::new(String::with_capacity(capacity)))
+    }
+
+    #[inline]
+    unsafe fn from_utf8_unchecked(bytes: Vec<u8>) -> Self {
+        SharedString::from(String::from_utf8_unchecked(bytes))
+    }
+
+    #[inline]
+    fn push_str(&mut self, string: &str) {
+        self.to_mut().push_str(string)
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        self.to_mut().reserve(additional)
+    }
+
+    #[inline]
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        StringExt::try_reserve(self.try_to_mut(additional)?, additional)
+    }
+
+    #[inline]
+    fn reserve_exact(&mut self, additional: usize) {
+        self.to_mut().reserve_exact(additional)
+    }
+
+    #[inline]
+    fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        StringExt::try_reserve_exact(self.try_to_mut(additional)?, additional)
+    }
+
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        if let Some(s) = Arc::get_mut(&mut self.0) {
+            s.shrink_to_fit()
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, ch: char) {
+        self.to_mut().push(ch)
+    }
+
+    #[inline]
+    fn truncate(&mut self, new_len: usize) {
+        if new_len < self.len() {
+            self.to_mut().truncate(new_len)
+        }
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<char> {
+        if self.is_empty() {
+            return None;
+        }
+        self.to_mut().pop()
+    }
+
+    #[inline]
+    fn remove(&mut self, idx: usize) -> char {
+        self.to_mut().remove(idx)
+    }
+
+    #[inline]
+    fn remove_range<R>(&mut self, range: R)
+    where
+        R: RangeBounds<usize>,
+    {
+        self.to_mut().drain(range);
+    }
+
+    #[inline]
+    fn insert(&mut self, idx: usize, ch: char) {
+        self.to_mut().insert(idx, ch)
+    }
+
+    #[inline]
+    fn insert_str(&mut self, idx: usize, string: &str) {
+        self.to_mut().insert_str(idx, string)
+    }
+
+    #[inline]
+    unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.to_mut().as_mut_vec()
+    }
+
+    #[inline]
+    fn split_off(&mut self, at: usize) -> Self {
+        assert!(self.is_char_boundary(at));
+        let other = SharedString::from(&self[at..]);
+        self.truncate(at);
+        other
+    }
+
+    #[inline]
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(char) -> bool,
+    {
+        self.to_mut().retain(f)
+    }
+
+    #[inline]
+    fn replace_range<R>(&mut self, range: R, replace_with: &str)
+    where
+        R: RangeBounds<usize>,
+    {
+        self.to_mut().replace_range(range, replace_with)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SharedInlinableString, SharedString};
+    use std::string::String;
+    use std::sync::Arc;
+    use {FallibleStringMut, StrBuf, StringExt, StringStorage, TryReserveError};
+
+    const LONG_STR: &str = "this is a really long string that is much larger than
+                        INLINE_STRING_CAPACITY and so cannot be stored inline.";
+
+    fn into_arc(s: SharedInlinableString) -> Arc<String> {
+        match s.into_storage() {
+            StringStorage::Heap(SharedString(a)) => a,
+            s => panic!("expected a heap-allocated string, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn test_size() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<SharedString>(), size_of::<usize>());
+        assert_eq!(size_of::<SharedInlinableString>(), size_of::<String>());
+    }
+
+    #[test]
+    fn test_pack() {
+        let shared = Arc::new(String::from(LONG_STR));
+        let s = SharedInlinableString::from(StringStorage::Heap(SharedString(shared.clone())));
+        assert_eq!(Arc::strong_count(&shared), 2);
+        assert_eq!(s.as_ptr(), shared.as_ptr());
+        assert!(Arc::ptr_eq(&into_arc(s), &shared));
+
+        let empty = SharedString::new();
+        let mut s: SharedInlinableString = SharedInlinableString::from(StringStorage::Heap(empty));
+        assert!(s.is_heap() && !s.is_shared());
+        s.push_str(LONG_STR);
+        assert_eq!(s, LONG_STR);
+    }
+
+    #[test]
+    fn test_clone_shares_allocation() {
+        let s: SharedInlinableString = SharedInlinableString::from(LONG_STR);
+        let t = s.clone();
+        assert!(s.is_shared() && t.is_shared());
+        assert_eq!(s.as_ptr(), t.as_ptr());
+        assert_eq!(Arc::strong_count(&into_arc(s)), 2);
+    }
+
+    #[test]
+    fn test_inline_stays_inline() {
+        let s: SharedInlinableString = SharedInlinableString::from("small");
+        let mut t = s.clone();
+        t.push_str("!");
+        assert!(s.is_inline());
+        assert!(t.is_inline());
+        assert_eq!(s, "small");
+        assert_eq!(t, "small!");
+    }
+
+    #[test]
+    fn test_copy_on_write() {
+        let s: SharedInlinableString = SharedInlinableString::from(LONG_STR);
+
+        let mut t = s.clone();
+        t.push_str("!");
+        assert_eq!(t, String::from(LONG_STR) + "!");
+
+        let mut t = s.clone();
+        t.insert(0, '¡');
+        assert_eq!(t, String::from("¡") + LONG_STR);
+
+        let mut t = s.clone();
+        t.retain(|c| c != ' ');
+        assert_eq!(t, LONG_STR.replace(' ', ""));
+
+        let mut t = s.clone();
+        t.replace_range(..4, "THIS");
+        assert_eq!(t, LONG_STR.replacen("this", "THIS", 1));
+
+        assert_eq!(s, LONG_STR);
+        assert_eq!(Arc::strong_count(&into_arc(s)), 1);
+    }
+
+    #[test]
+    fn test_clone_of_built_string_is_shared() {
+        let mut s: SharedInlinableString = SharedInlinableString::new();
+        s.push_str(LONG_STR);
+        assert!(s.is_heap() && !s.is_shared());
+
+        let t = s.clone();
+        let u = t.clone();
+        assert!(s.is_shared() && t.is_shared());
+        assert_eq!(s.as_ptr(), t.as_ptr());
+        assert_eq!(t.as_ptr(), u.as_ptr());
+        assert_eq!(Arc::strong_count(&into_arc(s)), 3);
+        assert_eq!(u, LONG_STR);
+    }
+
+    #[test]
+    fn test_mutating_unshared_string_does_not_copy() {
+        let mut s: SharedInlinableString = SharedInlinableString::with_capacity(200);
+        s.push_str(LONG_STR);
+        let t = s.clone();
+        drop(t);
+
+        let ptr = s.as_ptr();
+        s.push('!');
+        assert_eq!(s.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_non_mutating_operations_keep_sharing() {
+        let mut s = SharedString::from(LONG_STR);
+        let t = s.clone();
+        s.truncate(LONG_STR.len());
+        s.shrink_to_fit();
+        assert!(s.is_shared());
+        assert_eq!(s.capacity(), LONG_STR.len());
+
+        let mut empty = SharedString::from("");
+        let _u = empty.clone();
+        assert_eq!(empty.pop(), None);
+        assert!(empty.is_shared());
+        drop(t);
+    }
+
+    #[test]
+    fn test_try_reserve_copies_fallibly() {
+        let mut s = SharedString::from(LONG_STR);
+        let t = s.clone();
+        assert_eq!(
+            s.try_reserve(isize::MAX as usize / 2),
+            Err(TryReserveError::AllocError)
+        );
+        assert!(s.is_shared());
+
+        assert_eq!(s.try_push_str("!"), Ok(()));
+        assert!(!s.is_shared());
+        assert_eq!(s, String::from(LONG_STR) + "!");
+        assert_eq!(t, LONG_STR);
+    }
+
+    #[test]
+    fn test_split_off() {
+        let mut s = SharedString::from("hello world");
+        let t = s.split_off(5);
+        assert_eq!(s, "hello");
+        assert_eq!(t, " world");
+    }
+
+    #[test]
+    fn test_from_arc() {
+        let arc = Arc::new(String::from(LONG_STR));
+        let s: SharedInlinableString = SharedInlinableString::from(arc.clone());
+        assert!(Arc::ptr_eq(&into_arc(s), &arc));
+
+        let s: SharedInlinableString = SharedInlinableString::from(Arc::new(String::from("small")));
+        assert!(s.is_inline());
+    }
+
+    #[test]
+    fn test_into_string() {
+        let s: SharedInlinableString = SharedInlinableString::from(LONG_STR);
+        let t = s.clone();
+        assert_eq!(String::from(s), LONG_STR);
+
+        let ptr = t.as_ptr();
+        let t = String::from(t);
+        assert_eq!(t, LONG_STR);
+        assert_eq!(t.as_ptr(), ptr);
+    }
+
+    crate::string_ext_conformance_tests!(conformance_shared_string, SharedString);
+    crate::string_ext_conformance_tests!(conformance, SharedInlinableString);
+}
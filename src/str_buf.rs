@@ -0,0 +1,466 @@
+// Copyright 2015, The inlinable_string crate Developers. See the COPYRIGHT file
+// at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT
+// or http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The lower layers of the string trait hierarchy, which fixed-capacity
+//! strings can implement too.
+//!
+//! * `StrBuf` gives read-only access to a string buffer and its capacity.
+//! * `FallibleStringMut` adds mutations that report an error instead of
+//!   growing without bound: `InlineString` returns `NotEnoughCapacity` once
+//!   it is full, while growable strings return a `TryReserveError` when
+//!   memory cannot be allocated.
+//!   Its `*_or_reject` methods hand the input that did not fit back in a
+//!   `Rejected` error, so that it can be stored elsewhere.
+//! * [`StringExt`](../string_ext/trait.StringExt.html) sits on top as the
+//!   growable layer, and is only available with the `alloc` feature.
+//!
+//! Both traits only need `core`.
+//!
+//! # Examples
+//!
+//! ```
+//! use inlinable_string::{FallibleStringMut, InlineString};
+//!
+//! fn greet<S: FallibleStringMut>(s: &mut S, name: &str) -> Result<(), S::Error> {
+//!     s.try_push_str("hello, ")?;
+//!     s.try_push_str(name)
+//! }
+//!
+//! let mut fixed: InlineString<8> = InlineString::new();
+//! assert!(greet(&mut fixed, "world").is_err());
+//! assert_eq!(fixed, "hello, ");
+//!
+//! // Growable strings need the `alloc` feature.
+//! #[cfg(feature = "alloc")]
+//! {
+//!     use inlinable_string::InlinableString;
+//!
+//!     let mut growable: InlinableString = InlinableString::default();
+//!     assert!(greet(&mut growable, "world").is_ok());
+//!     assert_eq!(growable, "hello, world");
+//!
+//!     let mut std_string = String::new();
+//!     assert!(greet(&mut std_string, "world").is_ok());
+//! }
+//! ```
+
+use core::fmt;
+use core::ops::RangeBounds;
+
+/// Read-only access to a string buffer.
+///
+/// See the [module level documentation](./index.html) for more.
+pub trait StrBuf {
+    /// Extracts a string slice containing the entire string buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::{InlineString, StrBuf};
+    ///
+    /// let s: InlineString = InlineString::try_from("foo").unwrap();
+    ///
+    /// assert_eq!("foo", s.as_str());
+    /// ```
+    fn as_str(&self) -> &str;
+
+    /// Returns the number of bytes that this string buffer can hold without
+    /// reallocating, or at all for fixed-capacity strings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlineString, StrBuf};
+    ///
+    /// let s: InlineString<10> = InlineString::new();
+    /// assert_eq!(s.capacity(), 10);
+    /// ```
+    fn capacity(&self) -> usize;
+
+    /// Returns the number of bytes in this string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::{InlineString, StrBuf};
+    ///
+    /// let a: InlineString = InlineString::try_from("foo").unwrap();
+    /// assert_eq!(a.len(), 3);
+    /// ```
+    #[inline]
+    fn len(&self) -> usize {
+        self.as_str().len()
+    }
+
+    /// Returns true if the string contains no bytes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{FallibleStringMut, InlineString, StrBuf};
+    ///
+    /// let mut v: InlineString = InlineString::new();
+    /// assert!(v.is_empty());
+    /// v.try_push('a').unwrap();
+    /// assert!(!v.is_empty());
+    /// ```
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Works with the underlying buffer as a byte slice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::{InlineString, StrBuf};
+    ///
+    /// let s: InlineString = InlineString::try_from("hello").unwrap();
+    /// assert_eq!(s.as_bytes(), [104, 101, 108, 108, 111]);
+    /// ```
+    #[inline]
+    fn as_bytes(&self) -> &[u8] {
+        self.as_str().as_bytes()
+    }
+}
+
+/// Mutations of a string buffer that fail, leaving the string unchanged,
+/// when it cannot hold the result.
+///
+/// See the [module level documentation](./index.html) for more.
+pub trait FallibleStringMut: StrBuf {
+    /// The error returned when the string cannot hold the result: for
+    /// example `NotEnoughCapacity` for `InlineString` and `TryReserveError`
+    /// for every `StringExt`.
+    type Error;
+
+    /// Pushes the given string onto this string buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::inline_string::NotEnoughCapacity;
+    /// use inlinable_string::{FallibleStringMut, InlineString};
+    ///
+    /// let mut s: InlineString<6> = InlineString::new();
+    /// assert_eq!(s.try_push_str("foo"), Ok(()));
+    /// assert_eq!(s.try_push_str("barbaz"), Err(NotEnoughCapacity::new(9, 6)));
+    /// assert_eq!(s, "foo");
+    /// ```
+    fn try_push_str(&mut self, string: &str) -> Result<(), Self::Error>;
+
+    /// Adds the given character to the end of the string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::{FallibleStringMut, InlineString};
+    ///
+    /// let mut s: InlineString = InlineString::try_from("abc").unwrap();
+    /// s.try_push('1').unwrap();
+    /// assert_eq!(s, "abc1");
+    /// ```
+    #[inline]
+    fn try_push(&mut self, ch: char) -> Result<(), Self::Error> {
+        self.try_push_str(ch.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Inserts a string into the string buffer at byte position `idx`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use inlinable_string::{FallibleStringMut, InlineString};
+    ///
+    /// let mut s: InlineString = InlineString::try_from("foo").unwrap();
+    /// s.try_insert_str(2, "bar").unwrap();
+    /// assert_eq!(s, "fobaro");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `idx` does not lie on a character boundary or is out of bounds, then
+    /// this function will panic.
+    fn try_insert_str(&mut self, idx: usize, string: &str) -> Result<(), Self::Error>;
+
+    /// Inserts a character into the string buffer at byte position `idx`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{FallibleStringMut, InlineString};
+    ///
+    /// let mut s: InlineString<4> = InlineString::new();
+    /// s.try_push_str("foo").unwrap();
+    /// s.try_insert(2, 'f').unwrap();
+    /// assert_eq!(s, "fofo");
+    /// assert!(s.try_insert(0, 'x').is_err());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `idx` does not lie on a character boundary or is out of bounds, then
+    /// this function will panic.
+    #[inline]
+    fn try_insert(&mut self, idx: usize, ch: char) -> Result<(), Self::Error> {
+        self.try_insert_str(idx, ch.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Removes the specified range in the string, and replaces it with the
+    /// given string. The given string doesn't need to be the same length as
+    /// the range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the starting point or end point do not lie on a `char`
+    /// boundary, or if they're out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::inline_string::NotEnoughCapacity;
+    /// use inlinable_string::{FallibleStringMut, InlineString};
+    ///
+    /// let mut s: InlineString<9> = InlineString::new();
+    /// s.try_push_str("α and β").unwrap();
+    /// assert_eq!(s.try_replace_range(..2, "a"), Ok(()));
+    /// assert_eq!(s, "a and β");
+    /// assert_eq!(s.try_replace_range(..1, "alpha"), Err(NotEnoughCapacity::new(12, 9)));
+    /// assert_eq!(s, "a and β");
+    /// ```
+    fn try_replace_range<R>(&mut self, range: R, replace_with: &str) -> Result<(), Self::Error>
+    where
+        R: RangeBounds<usize>;
+
+    /// Pushes the given string onto this string buffer, or hands it back
+    /// together with the error if it does not fit, so that the caller can
+    /// put it somewhere else.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{FallibleStringMut, InlineString};
+    ///
+    /// let mut first: InlineString<8> = InlineString::new();
+    /// let mut second: InlineString<8> = InlineString::new();
+    /// for word in &["one ", "two ", "three"] {
+    ///     if let Err(rejected) = first.push_str_or_reject(word) {
+    ///         assert_eq!(rejected.error().excess(), 5);
+    ///         second.push_str(rejected.into_inner()).unwrap();
+    ///     }
+    /// }
+    /// assert_eq!(first, "one two ");
+    /// assert_eq!(second, "three");
+    /// ```
+    #[inline]
+    fn push_str_or_reject<'s>(
+        &mut self,
+        string: &'s str,
+    ) -> Result<(), Rejected<&'s str, Self::Error>> {
+        self.try_push_str(string)
+            .map_err(|error| Rejected::new(string, error))
+    }
+
+    /// Adds the given character to the end of the string, or hands it back
+    /// together with the error if it does not fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{FallibleStringMut, InlineString};
+    ///
+    /// let mut s: InlineString<2> = InlineString::new();
+    /// assert!(s.push_or_reject('a').is_ok());
+    /// let rejected = s.push_or_reject('é').unwrap_err();
+    /// assert_eq!(*rejected.input(), 'é');
+    /// assert_eq!(rejected.error().required(), 3);
+    /// ```
+    #[inline]
+    fn push_or_reject(&mut self, ch: char) -> Result<(), Rejected<char, Self::Error>> {
+        self.try_push(ch).map_err(|error| Rejected::new(ch, error))
+    }
+
+    /// Inserts a string into the string buffer at byte position `idx`, or
+    /// hands it back together with the error if it does not fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{FallibleStringMut, InlineString};
+    ///
+    /// let mut s: InlineString<4> = InlineString::new();
+    /// s.try_push_str("ad").unwrap();
+    /// let rejected = s.insert_str_or_reject(1, "bcd").unwrap_err();
+    /// assert_eq!(rejected.into_inner(), "bcd");
+    /// assert_eq!(s, "ad");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `idx` does not lie on a character boundary or is out of bounds, then
+    /// this function will panic.
+    #[inline]
+    fn insert_str_or_reject<'s>(
+        &mut self,
+        idx: usize,
+        string: &'s str,
+    ) -> Result<(), Rejected<&'s str, Self::Error>> {
+        self.try_insert_str(idx, string)
+            .map_err(|error| Rejected::new(string, error))
+    }
+
+    /// Inserts a character into the string buffer at byte position `idx`, or
+    /// hands it back together with the error if it does not fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{FallibleStringMut, InlineString};
+    ///
+    /// let mut s: InlineString<1> = InlineString::new();
+    /// s.try_push('b').unwrap();
+    /// let (ch, error) = s.insert_or_reject(0, 'a').unwrap_err().into_parts();
+    /// assert_eq!(ch, 'a');
+    /// assert_eq!(error.required(), 2);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `idx` does not lie on a character boundary or is out of bounds, then
+    /// this function will panic.
+    #[inline]
+    fn insert_or_reject(
+        &mut self,
+        idx: usize,
+        ch: char,
+    ) -> Result<(), Rejected<char, Self::Error>> {
+        self.try_insert(idx, ch)
+            .map_err(|error| Rejected::new(ch, error))
+    }
+}
+
+/// The error returned by the `*_or_reject` methods of `FallibleStringMut`:
+/// the input that did not fit, together with the reason why.
+///
+/// See the [module level documentation](./index.html) for more.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rejected<T, E> {
+    input: T,
+    error: E,
+}
+
+impl<T, E> Rejected<T, E> {
+    /// Creates an error that hands `input` back because of `error`.
+    #[inline]
+    pub fn new(input: T, error: E) -> Self {
+        Rejected { input, error }
+    }
+
+    /// Returns the input that was rejected.
+    #[inline]
+    pub fn input(&self) -> &T {
+        &self.input
+    }
+
+    /// Returns the reason why the input was rejected.
+    #[inline]
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// Takes the rejected input back.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.input
+    }
+
+    /// Splits this error into the rejected input and the reason why.
+    #[inline]
+    pub fn into_parts(self) -> (T, E) {
+        (self.input, self.error)
+    }
+}
+
+impl<T, E: fmt::Display> fmt::Display for Rejected<T, E> {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(fmt)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, E> std::error::Error for Rejected<T, E>
+where
+    T: fmt::Debug,
+    E: std::error::Error + 'static,
+{
+    #[inline]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // `Display` already shows the error itself.
+        self.error.source()
+    }
+}
+
+/// Resolves `range` over a string of `len` bytes to a pair of byte offsets.
+/// Bounds are not checked.
+#[inline]
+pub(crate) fn byte_range<R>(range: &R, len: usize) -> (usize, usize)
+where
+    R: RangeBounds<usize>,
+{
+    use core::ops::Bound::*;
+
+    let start = match range.start_bound() {
+        Included(&n) => n,
+        Excluded(&n) => n + 1,
+        Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Included(&n) => n + 1,
+        Excluded(&n) => n,
+        Unbounded => len,
+    };
+    (start, end)
+}
+
+/// Removes every non-overlapping match of `pat`, from left to right, from
+/// the UTF-8 string in `bytes` and returns the new length. The removed
+/// matches make room at the end of `bytes`, which is zeroed so that `bytes`
+/// stays valid UTF-8.
+pub(crate) fn remove_matches(bytes: &mut [u8], pat: &str) -> usize {
+    let len = bytes.len();
+    if pat.is_empty() {
+        return len;
+    }
+
+    let mut read = 0;
+    let mut write = 0;
+    loop {
+        // SAFETY: `read` is either 0 or just past a match, so it lies on a
+        // char boundary, and the bytes from `read` on were not written to
+        // yet, since `write` never passes the start of the last match.
+        let rest = unsafe { core::str::from_utf8_unchecked(&bytes[read..]) };
+        let kept_end = rest.find(pat).map_or(len, |idx| read + idx);
+        bytes.copy_within(read..kept_end, write);
+        write += kept_end - read;
+        if kept_end == len {
+            break;
+        }
+        read = kept_end + pat.len();
+    }
+    for byte in &mut bytes[write..] {
+        *byte = 0;
+    }
+    write
+}
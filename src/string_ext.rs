@@ -11,25 +11,130 @@
 //!
 //! See the [crate level documentation](./../index.html) for more.
 
-use std::borrow::{Borrow, BorrowMut, Cow};
-use std::cmp::PartialEq;
-use std::fmt::Display;
-use std::ops::RangeBounds;
-use std::str;
-use std::string::{FromUtf16Error, FromUtf8Error};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::{FromUtf16Error, FromUtf8Error, String};
+use alloc::vec::Vec;
+use core::borrow::BorrowMut;
+use core::cmp::{self, PartialEq};
+use core::fmt::{self, Display};
+use core::ops::{Range, RangeBounds};
+use core::str;
+use drain;
+use str_buf::{self, FallibleStringMut, StrBuf};
+
+#[cfg(any(test, feature = "conformance"))]
+#[macro_use]
+pub mod conformance;
+
+/// The error type for the fallible allocation methods of `StringExt`, such as
+/// `try_reserve` and `try_push_str`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TryReserveError {
+    /// The requested capacity overflows `usize` or exceeds `isize::MAX`
+    /// bytes.
+    CapacityOverflow,
+    /// The allocator failed to provide the requested memory.
+    AllocError,
+}
+
+impl Display for TryReserveError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TryReserveError::CapacityOverflow => {
+                "memory allocation failed because the computed capacity exceeded the maximum"
+                    .fmt(fmt)
+            }
+            TryReserveError::AllocError => {
+                "memory allocation failed because the memory allocator returned an error".fmt(fmt)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TryReserveError {}
+
+/// Returns the capacity needed to hold `additional` more bytes on top of
+/// `len`, or `CapacityOverflow` if no string could hold that many bytes.
+#[inline]
+pub(crate) fn required_capacity(len: usize, additional: usize) -> Result<usize, TryReserveError> {
+    match len.checked_add(additional) {
+        Some(required) if required <= isize::MAX as usize => Ok(required),
+        _ => Err(TryReserveError::CapacityOverflow),
+    }
+}
+
+/// Implements `FallibleStringMut::try_push_str` for a growable string by
+/// reserving the room it needs up front, so that `push_str` can't fail.
+#[inline]
+pub fn reserve_and_push_str<S>(s: &mut S, string: &str) -> Result<(), TryReserveError>
+where
+    S: StringExt,
+{
+    s.try_reserve(string.len())?;
+    s.push_str(string);
+    Ok(())
+}
+
+/// Implements `FallibleStringMut::try_insert_str` for a growable string by
+/// reserving the room it needs up front, so that `insert_str` can't fail.
+#[inline]
+pub fn reserve_and_insert_str<S>(s: &mut S, idx: usize, string: &str) -> Result<(), TryReserveError>
+where
+    S: StringExt,
+{
+    s.try_reserve(string.len())?;
+    s.insert_str(idx, string);
+    Ok(())
+}
+
+/// Implements `FallibleStringMut::try_replace_range` for a growable string
+/// by reserving the room it needs up front, so that `replace_range` can't
+/// fail.
+#[inline]
+pub fn reserve_and_replace_range<S, R>(
+    s: &mut S,
+    range: R,
+    replace_with: &str,
+) -> Result<(), TryReserveError>
+where
+    S: StringExt,
+    R: RangeBounds<usize>,
+{
+    let len = s.len();
+    let (start, end) = str_buf::byte_range(&range, len);
+    // String index does all bounds checks.
+    let range_len = s.as_str()[start..end].len();
+    s.try_reserve(replace_with.len().saturating_sub(range_len))?;
+    s.replace_range(start..end, replace_with);
+    Ok(())
+}
 
 /// A trait that exists to abstract string operations over any number of
 /// concrete string type implementations.
 ///
+/// `StringExt` is the growable layer on top of
+/// [`StrBuf`](../str_buf/trait.StrBuf.html) and
+/// [`FallibleStringMut`](../str_buf/trait.FallibleStringMut.html), whose
+/// fallible mutations report a `TryReserveError` when memory cannot be
+/// allocated.
+///
+/// When implementing `StringExt` for your own type, the `conformance`
+/// feature provides a `string_ext::conformance` module that checks it
+/// behaves like `String`.
+///
 /// See the [crate level documentation](./../index.html) for more.
-pub trait StringExt
+pub trait StringExt: FallibleStringMut<Error = TryReserveError>
 where
     for<'a> Self: Sized
         + Display
         + PartialEq<str>
         + PartialEq<String>
         + PartialEq<&'a str>
-        + PartialEq<Cow<'a, str>>,
+        + PartialEq<Cow<'a, str>>
+        + BorrowMut<str>,
 {
     /// Creates a new string buffer initialized with the empty string.
     ///
@@ -38,7 +143,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let s = InlinableString::new();
+    /// let s: InlinableString = InlinableString::new();
     /// ```
     #[inline]
     fn new() -> Self {
@@ -47,7 +152,7 @@ where
 
     /// Creates a new string buffer with the given capacity. The string will be
     /// able to hold at least `capacity` bytes without reallocating. If
-    /// `capacity` is less than or equal to `INLINE_STRING_CAPACITY`, the string
+    /// `capacity` is less than or equal to `INLINABLE_STRING_CAPACITY`, the string
     /// will not heap allocate.
     ///
     /// # Examples
@@ -55,10 +160,31 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let s = InlinableString::with_capacity(10);
+    /// let s: InlinableString = InlinableString::with_capacity(10);
     /// ```
     fn with_capacity(capacity: usize) -> Self;
 
+    /// Creates a new string buffer with the given capacity, returning an error
+    /// instead of aborting if the memory cannot be allocated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StrBuf, StringExt, TryReserveError};
+    ///
+    /// let s: InlinableString = InlinableString::try_with_capacity(100).unwrap();
+    /// assert!(s.capacity() >= 100);
+    ///
+    /// let err = <InlinableString as StringExt>::try_with_capacity(usize::MAX).unwrap_err();
+    /// assert_eq!(err, TryReserveError::CapacityOverflow);
+    /// ```
+    #[inline]
+    fn try_with_capacity(capacity: usize) -> Result<Self, TryReserveError> {
+        let mut s = Self::new();
+        s.try_reserve_exact(capacity)?;
+        Ok(s)
+    }
+
     /// Returns the vector as a string buffer, if possible, taking care not to
     /// copy it.
     ///
@@ -73,11 +199,11 @@ where
     /// use inlinable_string::{InlinableString, StringExt};
     ///
     /// let hello_vec = vec![104, 101, 108, 108, 111];
-    /// let s = InlinableString::from_utf8(hello_vec).unwrap();
+    /// let s: InlinableString = InlinableString::from_utf8(hello_vec).unwrap();
     /// assert_eq!(s, "hello");
     ///
     /// let invalid_vec = vec![240, 144, 128];
-    /// let s = InlinableString::from_utf8(invalid_vec).err().unwrap();
+    /// let s = <InlinableString as StringExt>::from_utf8(invalid_vec).err().unwrap();
     /// let err = s.utf8_error();
     /// assert_eq!(s.into_bytes(), [240, 144, 128]);
     /// ```
@@ -95,11 +221,11 @@ where
     /// use inlinable_string::{InlinableString, StringExt};
     ///
     /// let input = b"Hello \xF0\x90\x80World";
-    /// let output = InlinableString::from_utf8_lossy(input);
+    /// let output = <InlinableString as StringExt>::from_utf8_lossy(input);
     /// assert_eq!(output, "Hello \u{FFFD}World");
     /// ```
     #[inline]
-    fn from_utf8_lossy(v: &[u8]) -> Cow<str> {
+    fn from_utf8_lossy(v: &[u8]) -> Cow<'_, str> {
         String::from_utf8_lossy(v)
     }
 
@@ -114,12 +240,12 @@ where
     /// // 𝄞music
     /// let mut v = &mut [0xD834, 0xDD1E, 0x006d, 0x0075,
     ///                   0x0073, 0x0069, 0x0063];
-    /// assert_eq!(InlinableString::from_utf16(v).unwrap(),
-    ///            InlinableString::from("𝄞music"));
+    /// assert_eq!(<InlinableString as StringExt>::from_utf16(v).unwrap(),
+    ///            "𝄞music");
     ///
     /// // 𝄞mu<invalid>ic
     /// v[4] = 0xD800;
-    /// assert!(InlinableString::from_utf16(v).is_err());
+    /// assert!(<InlinableString as StringExt>::from_utf16(v).is_err());
     /// ```
     #[inline]
     fn from_utf16(v: &[u16]) -> Result<Self, FromUtf16Error> {
@@ -139,14 +265,109 @@ where
     ///           0x0073, 0xDD1E, 0x0069, 0x0063,
     ///           0xD834];
     ///
-    /// assert_eq!(InlinableString::from_utf16_lossy(v),
-    ///            InlinableString::from("𝄞mus\u{FFFD}ic\u{FFFD}"));
+    /// assert_eq!(<InlinableString as StringExt>::from_utf16_lossy(v),
+    ///            "𝄞mus\u{FFFD}ic\u{FFFD}");
     /// ```
     #[inline]
     fn from_utf16_lossy(v: &[u16]) -> Self {
         from_string(String::from_utf16_lossy(v))
     }
 
+    /// Decodes a UTF-16LE encoded byte slice `v` into a string, returning an
+    /// error if `v` contains any invalid data, including a trailing odd
+    /// byte.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StringExt};
+    ///
+    /// // 𝄞music
+    /// let v = &[0x34, 0xD8, 0x1E, 0xDD, 0x6d, 0x00, 0x75, 0x00,
+    ///           0x73, 0x00, 0x69, 0x00, 0x63, 0x00];
+    /// let s: InlinableString = InlinableString::from_utf16le(v).unwrap();
+    /// assert_eq!(s, "𝄞music");
+    /// assert!(s.is_inline());
+    ///
+    /// // 𝄞mu<invalid>ic
+    /// let v = &[0x34, 0xD8, 0x1E, 0xDD, 0x6d, 0x00, 0x75, 0x00,
+    ///           0x00, 0xD8, 0x69, 0x00, 0x63, 0x00];
+    /// assert!(InlinableString::<15>::from_utf16le(v).is_err());
+    /// ```
+    #[inline]
+    fn from_utf16le(v: &[u8]) -> Result<Self, FromUtf16Error> {
+        decode_utf16_bytes(v, u16::from_le_bytes, None).ok_or_else(from_utf16_error)
+    }
+
+    /// Decodes a UTF-16LE encoded byte slice `v` into a string, replacing
+    /// invalid data, including a trailing odd byte, with the replacement
+    /// character (U+FFFD).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StringExt};
+    ///
+    /// // 𝄞mus<invalid>ic<odd byte>
+    /// let v = &[0x34, 0xD8, 0x1E, 0xDD, 0x6d, 0x00, 0x75, 0x00,
+    ///           0x73, 0x00, 0x1E, 0xDD, 0x69, 0x00, 0x63, 0x00,
+    ///           0x34];
+    /// let s: InlinableString = InlinableString::from_utf16le_lossy(v);
+    /// assert_eq!(s, "𝄞mus\u{FFFD}ic\u{FFFD}");
+    /// ```
+    #[inline]
+    fn from_utf16le_lossy(v: &[u8]) -> Self {
+        let lossy = decode_utf16_bytes(v, u16::from_le_bytes, Some(char::REPLACEMENT_CHARACTER));
+        // Decoding only fails when invalid data is not replaced.
+        lossy.unwrap()
+    }
+
+    /// Decodes a UTF-16BE encoded byte slice `v` into a string, returning an
+    /// error if `v` contains any invalid data, including a trailing odd
+    /// byte.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StringExt};
+    ///
+    /// // 𝄞music
+    /// let v = &[0xD8, 0x34, 0xDD, 0x1E, 0x00, 0x6d, 0x00, 0x75,
+    ///           0x00, 0x73, 0x00, 0x69, 0x00, 0x63];
+    /// let s: InlinableString = InlinableString::from_utf16be(v).unwrap();
+    /// assert_eq!(s, "𝄞music");
+    ///
+    /// // An odd number of bytes
+    /// assert!(InlinableString::<15>::from_utf16be(&v[..13]).is_err());
+    /// ```
+    #[inline]
+    fn from_utf16be(v: &[u8]) -> Result<Self, FromUtf16Error> {
+        decode_utf16_bytes(v, u16::from_be_bytes, None).ok_or_else(from_utf16_error)
+    }
+
+    /// Decodes a UTF-16BE encoded byte slice `v` into a string, replacing
+    /// invalid data, including a trailing odd byte, with the replacement
+    /// character (U+FFFD).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StringExt};
+    ///
+    /// // 𝄞mus<invalid>ic<odd byte>
+    /// let v = &[0xD8, 0x34, 0xDD, 0x1E, 0x00, 0x6d, 0x00, 0x75,
+    ///           0x00, 0x73, 0xDD, 0x1E, 0x00, 0x69, 0x00, 0x63,
+    ///           0xD8];
+    /// let s: InlinableString = InlinableString::from_utf16be_lossy(v);
+    /// assert_eq!(s, "𝄞mus\u{FFFD}ic\u{FFFD}");
+    /// ```
+    #[inline]
+    fn from_utf16be_lossy(v: &[u8]) -> Self {
+        let lossy = decode_utf16_bytes(v, u16::from_be_bytes, Some(char::REPLACEMENT_CHARACTER));
+        // Decoding only fails when invalid data is not replaced.
+        lossy.unwrap()
+    }
+
     /// Creates a new string from a length, capacity, and pointer.
     ///
     /// # Safety
@@ -183,7 +404,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let s = InlinableString::from("hello");
+    /// let s: InlinableString = InlinableString::from("hello");
     /// let bytes = s.into_bytes();
     /// assert_eq!(bytes, [104, 101, 108, 108, 111]);
     /// ```
@@ -195,6 +416,43 @@ where
         Into::into(self).into_bytes()
     }
 
+    /// Appends this string, encoded as UTF-16LE, to the byte vector `out`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StringExt};
+    ///
+    /// let s: InlinableString = InlinableString::from("a𝄞");
+    /// let mut out = vec![0xFF, 0xFE];
+    /// s.encode_utf16le_into(&mut out);
+    /// assert_eq!(out, [0xFF, 0xFE, 0x61, 0x00, 0x34, 0xD8, 0x1E, 0xDD]);
+    ///
+    /// let decoded: InlinableString = InlinableString::from_utf16le(&out[2..]).unwrap();
+    /// assert_eq!(decoded, s);
+    /// ```
+    #[inline]
+    fn encode_utf16le_into(&self, out: &mut Vec<u8>) {
+        encode_utf16_bytes(self.as_str(), out, u16::to_le_bytes)
+    }
+
+    /// Appends this string, encoded as UTF-16BE, to the byte vector `out`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StringExt};
+    ///
+    /// let s: InlinableString = InlinableString::from("a𝄞");
+    /// let mut out = Vec::new();
+    /// s.encode_utf16be_into(&mut out);
+    /// assert_eq!(out, [0x00, 0x61, 0xD8, 0x34, 0xDD, 0x1E]);
+    /// ```
+    #[inline]
+    fn encode_utf16be_into(&self, out: &mut Vec<u8>) {
+        encode_utf16_bytes(self.as_str(), out, u16::to_be_bytes)
+    }
+
     /// Pushes the given string onto this string buffer.
     ///
     /// # Examples
@@ -202,7 +460,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let mut s = InlinableString::from("foo");
+    /// let mut s: InlinableString = InlinableString::from("foo");
     /// s.push_str("bar");
     /// assert_eq!(s, "foobar");
     /// ```
@@ -212,18 +470,55 @@ where
         self.insert_str(len, string);
     }
 
-    /// Returns the number of bytes that this string buffer can hold without
-    /// reallocating.
+    /// Copies the bytes in the `src` range to the end of the string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range has `start_bound > end_bound`, if the range is
+    /// bounded on either end and does not lie on a [`char`] boundary, or if
+    /// it's out of bounds.
+    ///
+    /// [`char`]: https://doc.rust-lang.org/std/primitive.char.html
     ///
     /// # Examples
     ///
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let s = InlinableString::with_capacity(10);
-    /// assert!(s.capacity() >= 10);
+    /// let mut s: InlinableString = InlinableString::from("abcde");
+    /// s.extend_from_within(2..);
+    /// assert_eq!(s, "abcdecde");
+    ///
+    /// s.extend_from_within(..2);
+    /// assert_eq!(s, "abcdecdeab");
     /// ```
-    fn capacity(&self) -> usize;
+    #[inline]
+    fn extend_from_within<R>(&mut self, src: R)
+    where
+        R: RangeBounds<usize>,
+    {
+        let (start, end) = str_buf::byte_range(&src, self.len());
+        // String index does all bounds checks.
+        let _ = &self.as_str()[start..end];
+
+        self.reserve(end - start);
+        // `self` can't be borrowed while pushing to it, so copy the range in
+        // small chunks that end on char boundaries.
+        let mut buf = [0; 64];
+        let mut copied = start;
+        while copied < end {
+            let mut chunk_end = cmp::min(copied + buf.len(), end);
+            while !self.as_str().is_char_boundary(chunk_end) {
+                chunk_end -= 1;
+            }
+            let chunk = &mut buf[..chunk_end - copied];
+            chunk.copy_from_slice(&self.as_bytes()[copied..chunk_end]);
+            // SAFETY: `chunk` was copied from a string, between two char
+            // boundaries.
+            self.push_str(unsafe { str::from_utf8_unchecked(chunk) });
+            copied = chunk_end;
+        }
+    }
 
     /// Reserves capacity for at least `additional` more bytes to be inserted
     /// in the given `InlinableString`. The collection may reserve more space to avoid
@@ -236,14 +531,42 @@ where
     /// # Examples
     ///
     /// ```
-    /// use inlinable_string::{InlinableString, StringExt};
+    /// use inlinable_string::{InlinableString, StrBuf, StringExt};
     ///
-    /// let mut s = InlinableString::new();
+    /// let mut s: InlinableString = InlinableString::new();
     /// s.reserve(10);
     /// assert!(s.capacity() >= 10);
     /// ```
     fn reserve(&mut self, additional: usize);
 
+    /// Tries to reserve capacity for at least `additional` more bytes, like
+    /// `reserve`, but returns an error instead of panicking or aborting if
+    /// the capacity overflows or the allocator fails. The string is left
+    /// unchanged on error.
+    ///
+    /// The provided implementation only detects capacity overflow and then
+    /// calls `reserve`. Implementations that can observe allocation failure
+    /// should override it, along with `try_reserve_exact`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StrBuf, StringExt, TryReserveError};
+    ///
+    /// let mut s: InlinableString = InlinableString::from("foo");
+    /// s.try_reserve(100).unwrap();
+    /// assert!(s.capacity() >= 103);
+    ///
+    /// assert_eq!(s.try_reserve(usize::MAX), Err(TryReserveError::CapacityOverflow));
+    /// assert_eq!(s, "foo");
+    /// ```
+    #[inline]
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        required_capacity(self.len(), additional)?;
+        self.reserve(additional);
+        Ok(())
+    }
+
     /// Reserves the minimum capacity for exactly `additional` more bytes to be
     /// inserted in the given `InlinableString`. Does nothing if the capacity is already
     /// sufficient.
@@ -259,66 +582,103 @@ where
     /// # Examples
     ///
     /// ```
-    /// use inlinable_string::{InlinableString, StringExt};
+    /// use inlinable_string::{InlinableString, StrBuf, StringExt};
     ///
-    /// let mut s = InlinableString::new();
+    /// let mut s: InlinableString = InlinableString::new();
     /// s.reserve_exact(10);
     /// assert!(s.capacity() >= 10);
     /// ```
     fn reserve_exact(&mut self, additional: usize);
 
+    /// Tries to reserve the minimum capacity for exactly `additional` more
+    /// bytes, like `reserve_exact`, but returns an error instead of panicking
+    /// or aborting if the capacity overflows or the allocator fails. The
+    /// string is left unchanged on error.
+    ///
+    /// The provided implementation only detects capacity overflow and then
+    /// calls `reserve_exact`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StrBuf, StringExt};
+    ///
+    /// let mut s: InlinableString = InlinableString::new();
+    /// s.try_reserve_exact(100).unwrap();
+    /// assert!(s.capacity() >= 100);
+    /// ```
+    #[inline]
+    fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        required_capacity(self.len(), additional)?;
+        self.reserve_exact(additional);
+        Ok(())
+    }
+
     /// Shrinks the capacity of this string buffer to match its length. If the
-    /// string's length is less than `INLINE_STRING_CAPACITY` and the string is
+    /// string's length is less than `INLINABLE_STRING_CAPACITY` and the string is
     /// heap-allocated, then it is demoted to inline storage.
     ///
     /// # Examples
     ///
     /// ```
-    /// use inlinable_string::{InlinableString, StringExt};
+    /// use inlinable_string::{InlinableString, StrBuf, StringExt};
     ///
-    /// let mut s = InlinableString::from("foo");
+    /// let mut s: InlinableString = InlinableString::from("foo");
     /// s.reserve(100);
     /// assert!(s.capacity() >= 100);
     /// s.shrink_to_fit();
-    /// assert_eq!(s.capacity(), inlinable_string::INLINE_STRING_CAPACITY);
+    /// assert_eq!(s.capacity(), inlinable_string::INLINABLE_STRING_CAPACITY);
     /// ```
     fn shrink_to_fit(&mut self);
 
-    /// Adds the given character to the end of the string.
+    /// Shrinks the capacity of this string buffer with a lower bound.
+    ///
+    /// The capacity will remain at least as large as both the length and the
+    /// supplied value. If the current capacity is less than the lower limit,
+    /// this is a no-op.
+    ///
+    /// The provided implementation shrinks to fit and then reserves the
+    /// missing capacity again, so implementors are encouraged to override it.
     ///
     /// # Examples
     ///
     /// ```
-    /// use inlinable_string::{InlinableString, StringExt};
+    /// use inlinable_string::{InlinableString, StrBuf, StringExt};
     ///
-    /// let mut s = InlinableString::from("abc");
-    /// s.push('1');
-    /// s.push('2');
-    /// s.push('3');
-    /// assert_eq!(s, "abc123");
+    /// let mut s: InlinableString = InlinableString::with_capacity(100);
+    /// s.push_str("a string that does not fit inline");
+    ///
+    /// s.shrink_to(50);
+    /// assert!(s.capacity() >= 50);
+    /// s.shrink_to(0);
+    /// assert!(s.capacity() >= s.len());
     /// ```
     #[inline]
-    fn push(&mut self, ch: char) {
-        let len = self.len();
-        self.insert(len, ch);
+    fn shrink_to(&mut self, min_capacity: usize) {
+        if self.capacity() > min_capacity {
+            self.shrink_to_fit();
+            let len = self.len();
+            self.reserve_exact(min_capacity.saturating_sub(len));
+        }
     }
 
-    /// Works with the underlying buffer as a byte slice.
+    /// Adds the given character to the end of the string.
     ///
     /// # Examples
     ///
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let s = InlinableString::from("hello");
-    /// assert_eq!(s.as_bytes(), [104, 101, 108, 108, 111]);
+    /// let mut s: InlinableString = InlinableString::from("abc");
+    /// s.push('1');
+    /// s.push('2');
+    /// s.push('3');
+    /// assert_eq!(s, "abc123");
     /// ```
     #[inline]
-    fn as_bytes(&self) -> &[u8]
-    where
-        Self: Borrow<str>,
-    {
-        self.borrow().as_bytes()
+    fn push(&mut self, ch: char) {
+        let len = self.len();
+        self.insert(len, ch);
     }
 
     /// Shortens a string to the specified length.
@@ -336,7 +696,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let mut s = InlinableString::from("hello");
+    /// let mut s: InlinableString = InlinableString::from("hello");
     /// s.truncate(2);
     /// assert_eq!(s, "he");
     /// ```
@@ -350,7 +710,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let mut s = InlinableString::from("foo");
+    /// let mut s: InlinableString = InlinableString::from("foo");
     /// assert_eq!(s.pop(), Some('o'));
     /// assert_eq!(s.pop(), Some('o'));
     /// assert_eq!(s.pop(), Some('f'));
@@ -376,7 +736,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let mut s = InlinableString::from("foo");
+    /// let mut s: InlinableString = InlinableString::from("foo");
     /// assert_eq!(s.remove(0), 'f');
     /// assert_eq!(s.remove(1), 'o');
     /// assert_eq!(s.remove(0), 'o');
@@ -399,7 +759,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let mut s = InlinableString::from("α is alpha, β is beta");
+    /// let mut s: InlinableString = InlinableString::from("α is alpha, β is beta");
     /// let beta_offset = s.find('β').unwrap_or(s.len());
     ///
     /// // Remove the range up until the β from the string
@@ -416,33 +776,90 @@ where
     where
         R: RangeBounds<usize>,
     {
-        use ops::Bound::*;
-
         let len = self.len();
-        let start = match range.start_bound() {
-            Included(&n) => n,
-            Excluded(&n) => n + 1,
-            Unbounded => 0,
-        };
-        let end = match range.end_bound() {
-            Included(&n) => n + 1,
-            Excluded(&n) => n,
-            Unbounded => len,
-        };
+        let (start, end) = str_buf::byte_range(&range, len);
 
         // Checking bounds.
         assert!(start <= end);
-
-        let diff = end - start;
-
-        let mut sum = 0;
-        while sum < diff {
-            sum += self.remove(start).len_utf8();
+        assert!(self.as_str().is_char_boundary(start));
+        assert!(self.as_str().is_char_boundary(end));
+
+        let new_len = len - (end - start);
+        // SAFETY: `start` and `end` are on `char` boundaries, so moving the
+        // bytes after the range over it leaves `[..new_len]` valid UTF-8, and
+        // the bytes after it are set to zero, so the whole buffer is valid
+        // UTF-8 again before it is truncated.
+        unsafe {
+            let bytes = self.as_mut_slice();
+            bytes.copy_within(end..len, start);
+            for byte in &mut bytes[new_len..] {
+                *byte = 0;
+            }
         }
+        self.truncate(new_len);
+    }
 
-        // Sanity check: number of deleted bytes must be equal
-        // to the range length.
-        assert_eq!(diff, sum);
+    /// Removes all non-overlapping matches of `pat`, searching from left to
+    /// right, in a single pass over the string. An empty `pat` matches
+    /// nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StringExt};
+    ///
+    /// let mut s: InlinableString = InlinableString::from("Trees are not green, the sky is not blue.");
+    /// s.remove_matches("not ");
+    /// assert_eq!(s, "Trees are green, the sky is blue.");
+    ///
+    /// let mut s: InlinableString = InlinableString::from("banana");
+    /// s.remove_matches("ana");
+    /// assert_eq!(s, "bna");
+    /// ```
+    #[inline]
+    fn remove_matches(&mut self, pat: &str) {
+        // SAFETY: `str_buf::remove_matches` keeps the bytes valid UTF-8.
+        let new_len = str_buf::remove_matches(unsafe { self.as_mut_slice() }, pat);
+        self.truncate(new_len);
+    }
+
+    /// Removes the specified range from the string in bulk, returning all
+    /// removed characters as an iterator.
+    ///
+    /// The range is removed with `remove_range` when the iterator is dropped,
+    /// even if it was not consumed, so an `InlinableString` whose growth
+    /// policy demotes strings moves back inline afterwards. If the iterator
+    /// is leaked instead, the string is left unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the starting point or end point do not lie on a [`char`]
+    /// boundary, or if they're out of bounds.
+    ///
+    /// [`char`]: https://doc.rust-lang.org/std/primitive.char.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StringExt};
+    ///
+    /// let mut s: InlinableString = InlinableString::from("α is alpha, β is beta");
+    /// let beta_offset = s.find('β').unwrap_or(s.len());
+    ///
+    /// let drained: String = s.drain(..beta_offset).collect();
+    /// assert_eq!(drained, "α is alpha, ");
+    /// assert_eq!(s, "β is beta");
+    ///
+    /// // A full range clears the string, like `clear()` does
+    /// s.drain(..);
+    /// assert_eq!(s, "");
+    /// ```
+    #[inline]
+    fn drain<R>(&mut self, range: R) -> Drain<'_, Self>
+    where
+        R: RangeBounds<usize>,
+    {
+        drain::Drain::new(self, range)
     }
 
     /// Inserts a character into the string buffer at byte position `idx`.
@@ -457,7 +874,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let mut s = InlinableString::from("foo");
+    /// let mut s: InlinableString = InlinableString::from("foo");
     /// s.insert(2, 'f');
     /// assert!(s == "fofo");
     /// ```
@@ -484,7 +901,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let mut s = InlinableString::from("foo");
+    /// let mut s: InlinableString = InlinableString::from("foo");
     /// s.insert_str(2, "bar");
     /// assert!(s == "fobaro");
     /// ```
@@ -494,6 +911,7 @@ where
     /// If `idx` does not lie on a character boundary or is out of bounds, then
     /// this function will panic.
     fn insert_str(&mut self, idx: usize, string: &str);
+
     /* It looks like `insert_str` is better manually implemented,
      * while provided `insert` is mostly okay.
     {
@@ -517,51 +935,19 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let mut s = InlinableString::from("hello");
+    /// let mut s: InlinableString = InlinableString::from("hello");
     /// unsafe {
     ///     let slice = s.as_mut_slice();
-    ///     assert!(slice == &[104, 101, 108, 108, 111]);
+    ///     assert!(slice == [104, 101, 108, 108, 111]);
     ///     slice.reverse();
     /// }
     /// assert_eq!(s, "olleh");
     /// ```
     #[inline]
-    unsafe fn as_mut_slice(&mut self) -> &mut [u8]
-    where
-        Self: BorrowMut<str>,
-    {
+    unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
         self.borrow_mut().as_bytes_mut()
     }
 
-    /// Returns the number of bytes in this string.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use inlinable_string::{InlinableString, StringExt};
-    ///
-    /// let a = InlinableString::from("foo");
-    /// assert_eq!(a.len(), 3);
-    /// ```
-    fn len(&self) -> usize;
-
-    /// Returns true if the string contains no bytes
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use inlinable_string::{InlinableString, StringExt};
-    ///
-    /// let mut v = InlinableString::new();
-    /// assert!(v.is_empty());
-    /// v.push('a');
-    /// assert!(!v.is_empty());
-    /// ```
-    #[inline]
-    fn is_empty(&self) -> bool {
-        self.len() == 0
-    }
-
     /// Truncates the string, returning it to 0 length.
     ///
     /// # Examples
@@ -569,7 +955,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let mut s = InlinableString::from("foo");
+    /// let mut s: InlinableString = InlinableString::from("foo");
     /// s.clear();
     /// assert!(s.is_empty());
     /// ```
@@ -578,7 +964,7 @@ where
         self.truncate(0);
     }
 
-    /// Extracts a string slice containing the entire string buffer.
+    /// Converts this extandable string into a mutable string slice.
     ///
     /// # Examples
     ///
@@ -587,40 +973,44 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let s = InlinableString::from("foo");
+    /// let mut s: InlinableString = InlinableString::from("foobar");
+    /// let s_mut_str = s.as_mut_str();
+    ///
+    /// s_mut_str.make_ascii_uppercase();
     ///
-    /// assert_eq!("foo", s.as_str());
+    /// assert_eq!("FOOBAR", s_mut_str);
     /// ```
     #[inline]
-    fn as_str(&self) -> &str
-    where
-        Self: Borrow<str>,
-    {
-        self.borrow()
+    fn as_mut_str(&mut self) -> &mut str {
+        self.borrow_mut()
     }
 
-    /// Converts this extandable string into a mutable string slice.
+    /// Divides the string into two mutable string slices at byte index `mid`.
     ///
-    /// # Examples
+    /// The first slice contains bytes `[0, mid)` and the second one bytes
+    /// `[mid, len)`. Unlike `split_off`, the string itself is left intact.
     ///
-    /// Basic usage:
+    /// # Panics
     ///
-    /// ```
-    /// use inlinable_string::{InlinableString, StringExt};
+    /// Panics if `mid` is not on a UTF-8 code point boundary, or if it is
+    /// past the end of the last code point of the string.
     ///
-    /// let mut s = InlinableString::from("foobar");
-    /// let s_mut_str = s.as_mut_str();
+    /// # Examples
     ///
-    /// s_mut_str.make_ascii_uppercase();
+    /// ```
+    /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// assert_eq!("FOOBAR", s_mut_str);
+    /// let mut s: InlinableString = InlinableString::from("Per Martin-Löf");
+    /// {
+    ///     let (first, last) = s.split_at_mut(3);
+    ///     first.make_ascii_uppercase();
+    ///     last.make_ascii_lowercase();
+    /// }
+    /// assert_eq!(s, "PER martin-löf");
     /// ```
     #[inline]
-    fn as_mut_str(&mut self) -> &mut str
-    where
-        Self: BorrowMut<str>,
-    {
-        self.borrow_mut()
+    fn split_at_mut(&mut self, mid: usize) -> (&mut str, &mut str) {
+        self.as_mut_str().split_at_mut(mid)
     }
 
     /// Converts this `String` into a [`Box`]`<`[`str`]`>`.
@@ -637,7 +1027,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let s = InlinableString::from("hello");
+    /// let s: InlinableString = InlinableString::from("hello");
     ///
     /// let b = s.into_boxed_str();
     /// ```
@@ -650,6 +1040,31 @@ where
         <String>::into_boxed_str(s)
     }
 
+    /// Consumes and leaks the string, returning a mutable reference to its
+    /// contents, `&'a mut str`.
+    ///
+    /// The leaked memory is never freed, so this is mainly useful for data
+    /// that lives for the remainder of the program's life. Any excess
+    /// capacity is dropped first, and inline strings are copied to the heap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StringExt};
+    ///
+    /// let s: InlinableString = InlinableString::from("hello");
+    /// let leaked: &'static mut str = s.leak();
+    /// leaked.make_ascii_uppercase();
+    /// assert_eq!(leaked, "HELLO");
+    /// ```
+    #[inline]
+    fn leak<'a>(self) -> &'a mut str
+    where
+        Self: Into<String>,
+    {
+        Box::leak(self.into_boxed_str())
+    }
+
     /// Splits the string into two at the given index.
     ///
     /// Returns a new buffer. `self` contains bytes `[0, at)`, and
@@ -669,7 +1084,7 @@ where
     /// # fn main() {
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let mut hello = InlinableString::from("Hello, World!");
+    /// let mut hello: InlinableString = InlinableString::from("Hello, World!");
     /// let world = hello.split_off(7);
     /// assert_eq!(hello, "Hello, ");
     /// assert_eq!(world, "World!");
@@ -689,7 +1104,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let mut s = InlinableString::from("f_o_ob_ar");
+    /// let mut s: InlinableString = InlinableString::from("f_o_ob_ar");
     ///
     /// s.retain(|c| c != '_');
     ///
@@ -701,7 +1116,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let mut s = InlinableString::from("abcde");
+    /// let mut s: InlinableString = InlinableString::from("abcde");
     /// let keep = [false, true, true, false, true];
     /// let mut i = 0;
     /// s.retain(|_| (keep[i], i += 1).0);
@@ -712,27 +1127,95 @@ where
     where
         F: FnMut(char) -> bool,
     {
-        /// Insanely ineffective implementation,
-        /// yet it is done "in-place" if you don't count
-        /// a lot of stack space.
-        #[inline]
-        fn recursive_retain<SE, F>(self_: &mut SE, f: &mut F)
-        where
-            F: FnMut(char) -> bool,
-            SE: StringExt,
-        {
-            match self_.pop() {
-                Some(ch) => {
-                    recursive_retain(self_, f);
-                    if f(ch) {
-                        self_.push(ch);
+        /// Puts the string back together once `retain` is done, or when `f`
+        /// panics: moves the characters that were not visited yet next to
+        /// the retained ones, and cuts off the rest.
+        struct Compact<'a, S: StringExt> {
+            s: &'a mut S,
+            /// The byte index of the next character to visit.
+            idx: usize,
+            /// The number of bytes removed so far.
+            deleted: usize,
+            len: usize,
+        }
+
+        impl<'a, S: StringExt> Drop for Compact<'a, S> {
+            fn drop(&mut self) {
+                let new_len = self.len - self.deleted;
+                // SAFETY: `[..new_len]` ends up holding the retained and the
+                // unvisited characters, and the bytes after it are set to
+                // zero, so the whole buffer is valid UTF-8 again before it
+                // is truncated.
+                unsafe {
+                    let bytes = self.s.as_mut_slice();
+                    bytes.copy_within(self.idx..self.len, self.idx - self.deleted);
+                    for byte in &mut bytes[new_len..] {
+                        *byte = 0;
                     }
                 }
-                None => (),
+                self.s.truncate(new_len);
             }
         }
 
-        recursive_retain(self, &mut f);
+        let len = self.len();
+        let mut compact = Compact {
+            s: self,
+            idx: 0,
+            deleted: 0,
+            len,
+        };
+
+        while compact.idx < compact.len {
+            // SAFETY: the bytes from `idx` on have not been moved yet, so
+            // they still are the valid UTF-8 that the string started with.
+            // Retained characters are only ever moved to the front.
+            let bytes = unsafe { compact.s.as_mut_slice() };
+            let rest = unsafe { str::from_utf8_unchecked(&bytes[compact.idx..compact.len]) };
+            let ch = rest.chars().next().unwrap();
+            let ch_len = ch.len_utf8();
+
+            if !f(ch) {
+                compact.deleted += ch_len;
+            } else if compact.deleted > 0 {
+                let idx = compact.idx;
+                bytes.copy_within(idx..idx + ch_len, idx - compact.deleted);
+            }
+            compact.idx += ch_len;
+        }
+    }
+
+    /// Removes the characters for which `f` returns `true` and returns them,
+    /// in their original order, as a new string. This is the opposite of
+    /// `retain`.
+    ///
+    /// Every character is visited exactly once, in the original order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inlinable_string::{InlinableString, StringExt};
+    ///
+    /// let mut s: InlinableString = InlinableString::from("a1b2c3");
+    /// let digits = s.extract_if(|ch| ch.is_ascii_digit());
+    ///
+    /// assert_eq!(s, "abc");
+    /// assert_eq!(digits, "123");
+    /// ```
+    #[inline]
+    fn extract_if<F>(&mut self, mut f: F) -> Self
+    where
+        F: FnMut(char) -> bool,
+    {
+        let mut extracted = Self::new();
+        self.retain(|ch| {
+            if f(ch) {
+                extracted.push(ch);
+                false
+            } else {
+                true
+            }
+        });
+        extracted
     }
 
     /// Removes the specified range in the string,
@@ -753,7 +1236,7 @@ where
     /// ```
     /// use inlinable_string::{InlinableString, StringExt};
     ///
-    /// let mut s = InlinableString::from("α is alpha, β is beta");
+    /// let mut s: InlinableString = InlinableString::from("α is alpha, β is beta");
     /// let beta_offset = s.find('β').unwrap_or(s.len());
     ///
     /// // Replace the range up until the β from the string
@@ -765,22 +1248,77 @@ where
     where
         R: RangeBounds<usize>,
     {
-        use ops::Bound::*;
+        let (start, end) = str_buf::byte_range(&range, self.len());
+        self.remove_range(start..end);
+        self.insert_str(start, replace_with);
+    }
+}
 
-        let start = match range.start_bound() {
-            Included(&n) => n,
-            Excluded(&n) => n + 1,
-            Unbounded => 0,
-        };
+/// A draining iterator over the characters of a `StringExt`.
+///
+/// This type is created by the [`drain`](./trait.StringExt.html#method.drain)
+/// method on `StringExt`. The drained range is removed from the string when
+/// it is dropped.
+pub type Drain<'a, S> = drain::Drain<'a, S>;
 
-        self.remove_range(range);
-        self.insert_str(start, replace_with);
+impl<S: StringExt> drain::Drainable for S {
+    #[inline]
+    fn as_str(&self) -> &str {
+        StrBuf::as_str(self)
+    }
+
+    #[inline]
+    fn remove_range(&mut self, range: Range<usize>) {
+        StringExt::remove_range(self, range);
+    }
+}
+
+/// Decodes UTF-16 from `v`, reading each code unit from two bytes with
+/// `from_bytes`. Invalid data, including a trailing odd byte, is replaced
+/// with `replacement`, or makes decoding fail if there is none.
+#[inline]
+fn decode_utf16_bytes<S: StringExt>(
+    v: &[u8],
+    from_bytes: fn([u8; 2]) -> u16,
+    replacement: Option<char>,
+) -> Option<S> {
+    let units = v.chunks_exact(2);
+    let odd_byte = !units.remainder().is_empty();
+    if odd_byte && replacement.is_none() {
+        return None;
+    }
+
+    // Every code unit takes at least one byte in UTF-8.
+    let mut s = S::with_capacity(v.len() / 2);
+    let units = units.map(|unit| from_bytes([unit[0], unit[1]]));
+    for ch in char::decode_utf16(units) {
+        s.push(ch.ok().or(replacement)?);
+    }
+    if odd_byte {
+        s.push(replacement?);
+    }
+    Some(s)
+}
+
+/// `FromUtf16Error` can't be constructed directly, so let `String` make one.
+#[cold]
+fn from_utf16_error() -> FromUtf16Error {
+    String::from_utf16(&[0xD800]).unwrap_err()
+}
+
+/// Appends `s` to `out` as UTF-16, writing each code unit with `to_bytes`.
+#[inline]
+fn encode_utf16_bytes(s: &str, out: &mut Vec<u8>, to_bytes: fn(u16) -> [u8; 2]) {
+    // A UTF-16 encoding is never more than twice as long as the UTF-8 one.
+    out.reserve(2 * s.len());
+    for unit in s.encode_utf16() {
+        out.extend_from_slice(&to_bytes(unit));
     }
 }
 
 /// Internal function to decrease the numbers of unsafe.
 #[inline]
-fn from_string<S: StringExt>(s: String) -> S {
+pub(crate) fn from_string<S: StringExt>(s: String) -> S {
     // SAFETY:
     // `s` is a well-formed string, turned into bytes.
     unsafe { S::from_utf8_unchecked(<String>::into_bytes(s)) }
@@ -833,8 +1371,11 @@ impl StringExt for String {
     }
 
     #[inline]
-    fn capacity(&self) -> usize {
-        String::capacity(self)
+    fn extend_from_within<R>(&mut self, src: R)
+    where
+        R: RangeBounds<usize>,
+    {
+        String::extend_from_within(self, src)
     }
 
     #[inline]
@@ -842,24 +1383,36 @@ impl StringExt for String {
         String::reserve(self, additional)
     }
 
+    #[inline]
+    fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        required_capacity(self.len(), additional)?;
+        String::try_reserve(self, additional).map_err(|_| TryReserveError::AllocError)
+    }
+
     #[inline]
     fn reserve_exact(&mut self, additional: usize) {
         String::reserve_exact(self, additional)
     }
 
+    #[inline]
+    fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        required_capacity(self.len(), additional)?;
+        String::try_reserve_exact(self, additional).map_err(|_| TryReserveError::AllocError)
+    }
+
     #[inline]
     fn shrink_to_fit(&mut self) {
         String::shrink_to_fit(self)
     }
 
     #[inline]
-    fn push(&mut self, ch: char) {
-        String::push(self, ch)
+    fn shrink_to(&mut self, min_capacity: usize) {
+        String::shrink_to(self, min_capacity)
     }
 
     #[inline]
-    fn as_bytes(&self) -> &[u8] {
-        String::as_bytes(self)
+    fn push(&mut self, ch: char) {
+        String::push(self, ch)
     }
 
     #[inline]
@@ -901,8 +1454,8 @@ impl StringExt for String {
     }
 
     #[inline]
-    fn len(&self) -> usize {
-        String::len(self)
+    fn leak<'a>(self) -> &'a mut str {
+        String::leak(self)
     }
 
     #[inline]
@@ -927,24 +1480,70 @@ impl StringExt for String {
     }
 }
 
+impl StrBuf for String {
+    #[inline]
+    fn as_str(&self) -> &str {
+        String::as_str(self)
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        String::capacity(self)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        String::len(self)
+    }
+}
+
+impl FallibleStringMut for String {
+    type Error = TryReserveError;
+
+    #[inline]
+    fn try_push_str(&mut self, string: &str) -> Result<(), TryReserveError> {
+        reserve_and_push_str(self, string)
+    }
+
+    #[inline]
+    fn try_insert_str(&mut self, idx: usize, string: &str) -> Result<(), TryReserveError> {
+        reserve_and_insert_str(self, idx, string)
+    }
+
+    #[inline]
+    fn try_replace_range<R>(&mut self, range: R, replace_with: &str) -> Result<(), TryReserveError>
+    where
+        R: RangeBounds<usize>,
+    {
+        reserve_and_replace_range(self, range, replace_with)
+    }
+}
+
 /// Implementation of some traits from stdlib for `String` type.
 /// This is 1.41.0+ code; before 1.41 orphan rules were too strict.
 mod string_impls {
-    use crate::{InlinableString, InlineString};
+    use crate::inline_string::LengthType;
+    use crate::{GrowthPolicy, HeapStorage, InlinableString, InlineString, StringStorage};
+    use alloc::string::String;
 
-    impl From<InlineString> for String {
+    impl<const N: usize, L: LengthType> From<InlineString<N, L>> for String {
         #[inline]
-        fn from(s: InlineString) -> String {
+        fn from(s: InlineString<N, L>) -> String {
             String::from(&*s)
         }
     }
 
-    impl From<InlinableString> for String {
+    impl<const N: usize, H, P> From<InlinableString<N, H, P>> for String
+    where
+        H: HeapStorage + Into<String>,
+        P: GrowthPolicy,
+    {
         #[inline]
-        fn from(s: InlinableString) -> String {
-            match s {
-                InlinableString::Heap(s) => s,
-                InlinableString::Inline(s) => String::from(s),
+        fn from(s: InlinableString<N, H, P>) -> String {
+            match s.into_storage() {
+                StringStorage::Heap(s) => s.into(),
+                StringStorage::Inline(s) => String::from(s),
+                StringStorage::Static(s) => String::from(s),
             }
         }
     }
@@ -953,12 +1552,14 @@ mod string_impls {
 #[cfg(test)]
 mod provided_methods_tests {
 
-    use super::StringExt;
+    use super::{FallibleStringMut, StrBuf, StringExt, TryReserveError};
+    use std::string::String;
+    use std::vec::Vec;
     use std::{
         borrow::{Borrow, BorrowMut, Cow},
         cmp::PartialEq,
         fmt,
-        ops::{Deref, DerefMut},
+        ops::{Deref, DerefMut, RangeBounds},
     };
 
     #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -1029,9 +1630,6 @@ mod provided_methods_tests {
         unsafe fn from_utf8_unchecked(bytes: Vec<u8>) -> Self {
             Self(String::from_utf8_unchecked(bytes))
         }
-        fn capacity(&self) -> usize {
-            self.0.capacity()
-        }
         fn reserve(&mut self, additional: usize) {
             self.0.reserve(additional)
         }
@@ -1053,14 +1651,41 @@ mod provided_methods_tests {
         fn insert_str(&mut self, idx: usize, string: &str) {
             self.0.insert_str(idx, string)
         }
-        fn len(&self) -> usize {
-            self.0.len()
-        }
         fn split_off(&mut self, at: usize) -> Self {
             Self(self.0.split_off(at))
         }
     }
 
+    impl StrBuf for ReqImpl {
+        fn as_str(&self) -> &str {
+            &self.0
+        }
+        fn capacity(&self) -> usize {
+            self.0.capacity()
+        }
+    }
+
+    impl FallibleStringMut for ReqImpl {
+        type Error = TryReserveError;
+
+        fn try_push_str(&mut self, string: &str) -> Result<(), TryReserveError> {
+            super::reserve_and_push_str(self, string)
+        }
+        fn try_insert_str(&mut self, idx: usize, string: &str) -> Result<(), TryReserveError> {
+            super::reserve_and_insert_str(self, idx, string)
+        }
+        fn try_replace_range<R>(
+            &mut self,
+            range: R,
+            replace_with: &str,
+        ) -> Result<(), TryReserveError>
+        where
+            R: RangeBounds<usize>,
+        {
+            super::reserve_and_replace_range(self, range, replace_with)
+        }
+    }
+
     #[test]
     fn test_as_bytes() {
         let s = ReqImpl::from("hello");
@@ -1072,7 +1697,7 @@ mod provided_methods_tests {
         let mut s = ReqImpl::from("hello");
         unsafe {
             let slice = s.as_mut_slice();
-            assert!(slice == &[104, 101, 108, 108, 111]);
+            assert!(slice == [104, 101, 108, 108, 111]);
             slice.reverse();
         }
         assert_eq!(s, "olleh");
@@ -1208,6 +1833,23 @@ mod provided_methods_tests {
         assert_eq!(s, "foobar");
     }
 
+    #[test]
+    fn test_try_push_str() {
+        let mut s = ReqImpl::from("foo");
+        assert_eq!(s.try_push_str("bar"), Ok(()));
+        assert_eq!(s.try_push('!'), Ok(()));
+        assert_eq!(s.try_insert_str(0, "¡"), Ok(()));
+        assert_eq!(s, "¡foobar!");
+        assert_eq!(
+            s.try_reserve(usize::MAX - 1),
+            Err(TryReserveError::CapacityOverflow)
+        );
+        assert_eq!(
+            ReqImpl::try_with_capacity(isize::MAX as usize + 1),
+            Err(TryReserveError::CapacityOverflow)
+        );
+    }
+
     #[test]
     fn test_remove_range() {
         let mut s = ReqImpl::from("α is alpha, β is beta");
@@ -1221,6 +1863,20 @@ mod provided_methods_tests {
         // A full range clears the string
         s.remove_range(..);
         assert_eq!(s, "");
+
+        let mut s = ReqImpl::from("aé€b");
+        s.remove_range(..1);
+        assert_eq!(s, "é€b");
+        s.remove_range(2..5);
+        assert_eq!(s, "éb");
+    }
+
+    #[test]
+    fn test_remove_range_long_string() {
+        // Removing one character at a time used to take quadratic time here.
+        let mut s = ReqImpl::from(&*"aé".repeat(1 << 20));
+        s.remove_range(3..(3 << 20) - 3);
+        assert_eq!(s, "aéaé");
     }
 
     #[test]
@@ -1247,24 +1903,162 @@ mod provided_methods_tests {
         s.retain(|_| (keep[i], i += 1).0);
         assert_eq!(s, "bce");
     }
+
+    #[test]
+    fn test_retain_long_string() {
+        // One stack frame per character used to overflow the stack here.
+        let mut s = ReqImpl::from(&*"aé".repeat(1 << 20));
+        s.retain(|ch| ch != 'a');
+        assert_eq!(s.len(), 2 << 20);
+        assert!(s.chars().all(|ch| ch == 'é'));
+    }
+
+    #[test]
+    fn test_retain_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mut s = ReqImpl::from("a_é_b_€_c");
+        let mut visited = 0;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            s.retain(|ch| {
+                visited += 1;
+                assert!(visited < 5);
+                ch != '_'
+            })
+        }));
+        assert!(result.is_err());
+        // The visited characters are filtered, the others are left alone.
+        assert_eq!(s, "aéb_€_c");
+    }
+
+    #[test]
+    fn test_extract_if() {
+        let mut s = ReqImpl::from("ß1€2🦀3");
+        let mut visited = String::new();
+        let digits = s.extract_if(|ch| {
+            visited.push(ch);
+            ch.is_ascii_digit()
+        });
+        assert_eq!(digits, "123");
+        assert_eq!(s, "ß€🦀");
+        assert_eq!(visited, "ß1€2🦀3");
+    }
+
+    #[test]
+    fn test_utf16_bytes() {
+        let mut le = Vec::new();
+        ReqImpl::from("a𝄞").encode_utf16le_into(&mut le);
+        assert_eq!(le, [0x61, 0x00, 0x34, 0xD8, 0x1E, 0xDD]);
+        let mut be = Vec::new();
+        ReqImpl::from("a𝄞").encode_utf16be_into(&mut be);
+        assert_eq!(be, [0x00, 0x61, 0xD8, 0x34, 0xDD, 0x1E]);
+
+        assert_eq!(ReqImpl::from_utf16le(&le).unwrap(), "a𝄞");
+        assert_eq!(ReqImpl::from_utf16be(&be).unwrap(), "a𝄞");
+        // A lone surrogate, then an odd byte.
+        assert!(ReqImpl::from_utf16le(&le[..4]).is_err());
+        assert!(ReqImpl::from_utf16be(&be[..5]).is_err());
+        assert_eq!(ReqImpl::from_utf16le_lossy(&le[..4]), "a\u{FFFD}");
+        assert_eq!(ReqImpl::from_utf16be_lossy(&be[..5]), "a\u{FFFD}\u{FFFD}");
+    }
+
+    #[test]
+    fn test_extend_from_within() {
+        // Longer than the chunks that the provided method copies through.
+        let sample = "ab€🦀".repeat(20);
+        let mut s = ReqImpl::from(&*sample);
+        s.extend_from_within(1..);
+        assert_eq!(s, format!("{}{}", sample, &sample[1..]));
+
+        let mut s = ReqImpl::from("hello");
+        s.extend_from_within(..0);
+        assert_eq!(s, "hello");
+    }
+
+    #[test]
+    #[should_panic]
+    fn extend_from_within_panic() {
+        let mut s = ReqImpl::from("a€b");
+        s.extend_from_within(2..);
+    }
+
+    #[test]
+    fn test_shrink_to() {
+        let mut s = ReqImpl::from("hello");
+        s.reserve(100);
+        s.shrink_to(10);
+        assert!(s.capacity() >= 10);
+        assert!(s.capacity() < 100);
+        s.shrink_to(0);
+        assert!(s.capacity() >= 5);
+        assert_eq!(s, "hello");
+    }
+
+    #[test]
+    fn test_remove_matches() {
+        let mut s = ReqImpl::from("a€🦀€b€");
+        s.remove_matches("€");
+        assert_eq!(s, "a🦀b");
+        s.remove_matches("");
+        assert_eq!(s, "a🦀b");
+        s.remove_matches("a🦀b");
+        assert_eq!(s, "");
+    }
+
+    #[test]
+    fn test_split_at_mut() {
+        let mut s = ReqImpl::from("abc€");
+        {
+            let (head, tail) = s.split_at_mut(1);
+            head.make_ascii_uppercase();
+            assert_eq!(tail, "bc€");
+        }
+        assert_eq!(s, "Abc€");
+    }
+
+    #[test]
+    fn test_leak() {
+        let leaked = ReqImpl::from("leaked").leak();
+        leaked.make_ascii_uppercase();
+        assert_eq!(leaked, "LEAKED");
+    }
+
+    #[test]
+    fn test_drain() {
+        let mut s = ReqImpl::from("αβγδ");
+        {
+            let mut drain = s.drain(2..6);
+            assert_eq!(drain.size_hint(), (1, Some(4)));
+            assert_eq!(drain.next(), Some('β'));
+            assert_eq!(format!("{:?}", drain), "Drain(\"γ\")");
+        }
+        assert_eq!(s, "αδ");
+
+        let drain = s.drain(..);
+        ::std::mem::forget(drain);
+        assert_eq!(s, "αδ");
+    }
+
+    string_ext_conformance_tests!(conformance, ReqImpl);
 }
 
 #[cfg(test)]
 mod std_string_stringext_sanity_tests {
     // Sanity tests for std::string::String's StringExt implementation.
 
-    use super::StringExt;
+    use super::{FallibleStringMut, StrBuf, StringExt, TryReserveError};
+    use std::string::String;
 
     #[test]
     fn test_new() {
         let s = <String as StringExt>::new();
-        assert!(StringExt::is_empty(&s));
+        assert!(StrBuf::is_empty(&s));
     }
 
     #[test]
     fn test_with_capacity() {
         let s = <String as StringExt>::with_capacity(10);
-        assert!(StringExt::capacity(&s) >= 10);
+        assert!(StrBuf::capacity(&s) >= 10);
     }
 
     #[test]
@@ -1321,6 +2115,27 @@ mod std_string_stringext_sanity_tests {
         assert!(String::capacity(&s) >= 100);
     }
 
+    #[test]
+    fn test_try_reserve() {
+        let mut s = String::from("foo");
+        assert_eq!(StringExt::try_reserve(&mut s, 100), Ok(()));
+        assert!(String::capacity(&s) >= 103);
+        assert_eq!(
+            StringExt::try_reserve(&mut s, usize::MAX),
+            Err(TryReserveError::CapacityOverflow)
+        );
+        assert_eq!(
+            StringExt::try_reserve_exact(&mut s, isize::MAX as usize / 2),
+            Err(TryReserveError::AllocError)
+        );
+        assert_eq!(
+            FallibleStringMut::try_push_str(&mut s, "bar")
+                .and_then(|_| FallibleStringMut::try_push(&mut s, '!')),
+            Ok(())
+        );
+        assert_eq!(s, "foobar!");
+    }
+
     #[test]
     fn test_shrink_to_fit() {
         let mut s = <String as StringExt>::with_capacity(100);
@@ -1381,10 +2196,26 @@ mod std_string_stringext_sanity_tests {
         assert_eq!(s, "foobar");
     }
 
+    #[test]
+    fn test_extract_if() {
+        let mut s = String::from("--f-oo-b-a-r---");
+        assert_eq!(s.extract_if(|ch| ch == '-'), "---------");
+        assert_eq!(s, "foobar");
+    }
+
     #[test]
     fn test_replace_range() {
         let mut s = String::from("foobar");
         StringExt::replace_range(&mut s, 1..5, "qwerty");
         assert_eq!(s, "fqwertyr");
     }
+
+    #[test]
+    fn test_remove_matches() {
+        let mut s = String::from("foo bar foo");
+        StringExt::remove_matches(&mut s, "foo");
+        assert_eq!(s, " bar ");
+    }
+
+    string_ext_conformance_tests!(conformance, String);
 }
@@ -0,0 +1,727 @@
+// Copyright 2015, The inlinable_string crate Developers. See the COPYRIGHT file
+// at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT
+// or http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! A behavioral test suite for `StringExt` implementations.
+//!
+//! Each check runs the same operations on a `std::string::String` and on the
+//! string type under test, over a set of [`SAMPLES`](./constant.SAMPLES.html)
+//! with multi-byte characters, and at byte indices inside and around every
+//! character of them, as well as past the end. The type under test has to
+//! return the same results, end up with the same contents, and panic exactly
+//! when `String` panics. After every operation it also has to uphold the
+//! `StrBuf` invariants: `len`, `as_bytes` and `Display` agree with `as_str`,
+//! and `capacity` is never less than `len`.
+//!
+//! The checks panic with a description of the first mismatch they find.
+//! Expected panics are caught with `std::panic::catch_unwind`, so their
+//! messages still reach the panic hook, and the suite needs `std`.
+//!
+//! This module is only available with the `conformance` feature.
+//!
+//! # Examples
+//!
+//! Run the whole suite as one test with `check_all`, or generate one test per
+//! group of checks with the
+//! [`string_ext_conformance_tests!`](../../macro.string_ext_conformance_tests.html)
+//! macro:
+//!
+//! ```
+//! #[macro_use]
+//! extern crate inlinable_string;
+//!
+//! use inlinable_string::string_ext::conformance;
+//! use inlinable_string::InlinableString;
+//!
+//! # fn main() {
+//! conformance::check_all::<InlinableString>();
+//! # }
+//!
+//! #[cfg(test)]
+//! mod tests {
+//!     use inlinable_string::InlinableString;
+//!
+//!     string_ext_conformance_tests!(inlinable_string_conformance, InlinableString);
+//! }
+//! ```
+
+use std::borrow::ToOwned;
+use std::cmp;
+use std::fmt::Debug;
+use std::panic::{self, AssertUnwindSafe};
+use std::str;
+use std::string::{String, ToString};
+use std::vec::Vec;
+use {FallibleStringMut, StringExt, TryReserveError};
+
+/// Runs the same operation on a `String` and on an `S` built from `sample`,
+/// and checks that both return the same result and contents, or both panic.
+macro_rules! compare {
+    ($S:ident, $sample:expr, $op:expr, |$s:ident| $body:expr) => {
+        compare::<$S, _, _, _, _>($sample, $op, |$s: &mut String| $body, |$s: &mut $S| $body)
+    };
+}
+
+/// The strings that every check runs on.
+///
+/// They cover the empty string, one to four byte characters, an embedded NUL
+/// byte, and a string longer than any inline capacity the crate's types use
+/// by default.
+pub const SAMPLES: &[&str] = &[
+    "",
+    "a",
+    "abc",
+    "héllo",
+    "日本語",
+    "🦀🦀",
+    "a\u{0}b",
+    "mixed: ß, € and 𝄞",
+    "a string that is long enough to never fit inline: ÿ, ✓ and 🦀",
+];
+
+const CHARS: &[char] = &['a', '\u{0}', 'ß', '€', '🦀'];
+
+/// Longer than any inline capacity the crate's types use by default.
+const LONG_REPLACEMENT: &str = "ÿ🦀ÿ🦀ÿ🦀ÿ🦀ÿ🦀";
+
+const INVALID_UTF8: &[&[u8]] = &[
+    // Lone continuation byte.
+    b"\x80",
+    // Truncated multi-byte sequences.
+    b"a\xC3",
+    b"\xE6\x97",
+    b"\xF0\x9F\xA6",
+    // Overlong encoding of '/'.
+    b"\xC0\xAF",
+    // Encoded surrogate.
+    b"\xED\xA0\x80",
+    // Code point past U+10FFFF.
+    b"\xF4\x90\x80\x80",
+    // Bytes that never appear in UTF-8.
+    b"\xFE\xFF",
+    b"valid, then \xFF invalid",
+];
+
+const UTF16: &[&[u16]] = &[
+    &[],
+    &[0x61, 0x62],
+    &[0xE9, 0x20AC],
+    // A surrogate pair for U+1F980.
+    &[0xD83E, 0xDD80],
+    // Unpaired surrogates.
+    &[0xD83E],
+    &[0xDD80, 0x61],
+    &[0x61, 0xD83E, 0x62],
+];
+
+/// Runs every check of this module against `S`.
+///
+/// # Panics
+///
+/// Panics if `S` behaves differently from `std::string::String`.
+pub fn check_all<S>()
+where
+    S: StringExt + Into<String>,
+{
+    check_constructors::<S>();
+    check_capacity::<S>();
+    check_push_pop::<S>();
+    check_insert_remove::<S>();
+    check_ranges::<S>();
+    check_retain::<S>();
+    check_conversions::<S>();
+}
+
+/// Checks `new`, `with_capacity`, `try_with_capacity`, the UTF-8 and UTF-16
+/// decoding constructors, including on invalid input, and the UTF-16
+/// encoders.
+///
+/// # Panics
+///
+/// Panics if `S` behaves differently from `std::string::String`.
+pub fn check_constructors<S: StringExt>() {
+    let s = S::new();
+    assert_invariants(&s, "", "new()");
+
+    for &capacity in &[0, 1, 15, 16, 100] {
+        let context = format!("with_capacity({})", capacity);
+        let s = S::with_capacity(capacity);
+        assert_invariants(&s, "", &context);
+        assert!(s.capacity() >= capacity, "{}: capacity too small", context);
+
+        let s = S::try_with_capacity(capacity).expect("try_with_capacity failed");
+        assert_invariants(&s, "", &context);
+        assert!(
+            s.capacity() >= capacity,
+            "try_{}: capacity too small",
+            context
+        );
+    }
+    assert_eq!(
+        S::try_with_capacity(usize::MAX).err(),
+        Some(TryReserveError::CapacityOverflow),
+        "try_with_capacity(usize::MAX)"
+    );
+
+    for sample in SAMPLES {
+        let context = format!("from_utf8({:?})", sample);
+        let s = S::from_utf8(sample.as_bytes().to_vec()).expect(&context);
+        assert_invariants(&s, sample, &context);
+
+        let s = unsafe { S::from_utf8_unchecked(sample.as_bytes().to_vec()) };
+        assert_invariants(&s, sample, &format!("from_utf8_unchecked({:?})", sample));
+
+        assert_eq!(
+            S::from_utf8_lossy(sample.as_bytes()),
+            String::from_utf8_lossy(sample.as_bytes())
+        );
+
+        let utf16: Vec<u16> = sample.encode_utf16().collect();
+        let context = format!("from_utf16({:?})", utf16);
+        let s = S::from_utf16(&utf16).expect(&context);
+        assert_invariants(&s, sample, &context);
+        assert_invariants(&S::from_utf16_lossy(&utf16), sample, &context);
+
+        let mut le = Vec::new();
+        build::<S>(sample).encode_utf16le_into(&mut le);
+        let mut be = Vec::new();
+        build::<S>(sample).encode_utf16be_into(&mut be);
+        let expected_le: Vec<u8> = utf16.iter().flat_map(|unit| unit.to_le_bytes()).collect();
+        let expected_be: Vec<u8> = utf16.iter().flat_map(|unit| unit.to_be_bytes()).collect();
+        assert_eq!(le, expected_le, "encode_utf16le_into() on {:?}", sample);
+        assert_eq!(be, expected_be, "encode_utf16be_into() on {:?}", sample);
+
+        let context = format!("from_utf16le({:?})", le);
+        assert_invariants(&S::from_utf16le(&le).expect(&context), sample, &context);
+        assert_invariants(&S::from_utf16le_lossy(&le), sample, &context);
+        let context = format!("from_utf16be({:?})", be);
+        assert_invariants(&S::from_utf16be(&be).expect(&context), sample, &context);
+        assert_invariants(&S::from_utf16be_lossy(&be), sample, &context);
+
+        if let Some(&last) = utf16.last() {
+            // Drop the last byte, leaving an odd one behind.
+            let odd = &le[..le.len() - 1];
+            let context = format!("from_utf16le({:?})", odd);
+            assert!(
+                S::from_utf16le(odd).is_err(),
+                "{}: accepted odd length",
+                context
+            );
+            let mut expected = String::from_utf16_lossy(&utf16[..utf16.len() - 1]);
+            expected.push('\u{FFFD}');
+            assert_invariants(&S::from_utf16le_lossy(odd), &expected, &context);
+            let odd = &be[..be.len() - 1];
+            let context = format!("from_utf16be({:?}) ending in {:#x}", odd, last);
+            assert!(
+                S::from_utf16be(odd).is_err(),
+                "{}: accepted odd length",
+                context
+            );
+            assert_invariants(&S::from_utf16be_lossy(odd), &expected, &context);
+        }
+    }
+
+    for &bytes in INVALID_UTF8 {
+        let context = format!("from_utf8({:?})", bytes);
+        let expected = String::from_utf8(bytes.to_vec()).unwrap_err();
+        match S::from_utf8(bytes.to_vec()) {
+            Ok(s) => panic!("{}: accepted invalid UTF-8 as {:?}", context, s.as_str()),
+            Err(e) => {
+                assert_eq!(e.utf8_error(), expected.utf8_error(), "{}", context);
+                assert_eq!(e.into_bytes(), bytes, "{}: returned other bytes", context);
+            }
+        }
+
+        let lossy = S::from_utf8_lossy(bytes);
+        assert_eq!(lossy, String::from_utf8_lossy(bytes), "lossy {}", context);
+    }
+
+    for &utf16 in UTF16 {
+        let context = format!("from_utf16({:?})", utf16);
+        match (String::from_utf16(utf16), S::from_utf16(utf16)) {
+            (Ok(expected), Ok(s)) => assert_invariants(&s, &expected, &context),
+            (Err(_), Err(_)) => {}
+            (Ok(_), Err(_)) => panic!("{}: rejected valid UTF-16", context),
+            (Err(_), Ok(s)) => panic!("{}: accepted invalid UTF-16 as {:?}", context, s.as_str()),
+        }
+
+        let expected = String::from_utf16_lossy(utf16);
+        assert_invariants(
+            &S::from_utf16_lossy(utf16),
+            &expected,
+            &format!("lossy {}", context),
+        );
+
+        let le: Vec<u8> = utf16.iter().flat_map(|unit| unit.to_le_bytes()).collect();
+        let be: Vec<u8> = utf16.iter().flat_map(|unit| unit.to_be_bytes()).collect();
+        let valid = String::from_utf16(utf16).is_ok();
+        assert_eq!(
+            S::from_utf16le(&le).is_ok(),
+            valid,
+            "from_utf16le({:?})",
+            le
+        );
+        assert_eq!(
+            S::from_utf16be(&be).is_ok(),
+            valid,
+            "from_utf16be({:?})",
+            be
+        );
+        assert_invariants(&S::from_utf16le_lossy(&le), &expected, "lossy from_utf16le");
+        assert_invariants(&S::from_utf16be_lossy(&be), &expected, "lossy from_utf16be");
+    }
+}
+
+type Reserve<S> = fn(&mut S, usize) -> Result<(), TryReserveError>;
+
+/// Checks `reserve`, `reserve_exact`, their fallible versions,
+/// `shrink_to_fit` and `shrink_to`: they must never change the contents, and
+/// must leave room for at least the requested number of bytes.
+///
+/// # Panics
+///
+/// Panics if `S` behaves differently from `std::string::String`.
+pub fn check_capacity<S: StringExt>() {
+    let reserves: [(&str, Reserve<S>); 4] = [
+        ("reserve", |s, additional| {
+            s.reserve(additional);
+            Ok(())
+        }),
+        ("reserve_exact", |s, additional| {
+            s.reserve_exact(additional);
+            Ok(())
+        }),
+        ("try_reserve", |s, additional| s.try_reserve(additional)),
+        ("try_reserve_exact", |s, additional| {
+            s.try_reserve_exact(additional)
+        }),
+    ];
+
+    for sample in SAMPLES {
+        for &(name, reserve) in &reserves {
+            for &additional in &[0, 1, 10, 100] {
+                let context = format!("{}({}) on {:?}", name, additional, sample);
+                let mut s = build::<S>(sample);
+                assert_eq!(reserve(&mut s, additional), Ok(()), "{}", context);
+                assert_invariants(&s, sample, &context);
+                let capacity = s.capacity();
+                let required = sample.len() + additional;
+                assert!(capacity >= required, "{}: capacity too small", context);
+            }
+        }
+
+        let context = format!("try_reserve(usize::MAX) on {:?}", sample);
+        let mut s = build::<S>(sample);
+        assert_eq!(
+            s.try_reserve(usize::MAX),
+            Err(TryReserveError::CapacityOverflow),
+            "{}",
+            context
+        );
+        assert_invariants(&s, sample, &context);
+        assert_eq!(
+            s.try_reserve_exact(usize::MAX),
+            Err(TryReserveError::CapacityOverflow),
+            "{}",
+            context
+        );
+        assert_invariants(&s, sample, &context);
+
+        let context = format!("shrink_to_fit() on {:?}", sample);
+        let mut s = build::<S>(sample);
+        s.reserve(100);
+        s.shrink_to_fit();
+        assert_invariants(&s, sample, &context);
+
+        for &min_capacity in &[0, 10, 100, 1000] {
+            let context = format!("shrink_to({}) on {:?}", min_capacity, sample);
+            let mut s = build::<S>(sample);
+            s.reserve(200);
+            let capacity = s.capacity();
+            s.shrink_to(min_capacity);
+            assert_invariants(&s, sample, &context);
+            let required = cmp::min(capacity, cmp::max(sample.len(), min_capacity));
+            assert!(s.capacity() >= required, "{}: capacity too small", context);
+        }
+    }
+}
+
+/// Checks `push`, `push_str`, their fallible versions, `pop` and `clear`.
+///
+/// # Panics
+///
+/// Panics if `S` behaves differently from `std::string::String`.
+pub fn check_push_pop<S: StringExt>() {
+    for sample in SAMPLES {
+        for &ch in CHARS {
+            compare!(S, sample, format!("push({:?})", ch), |s| s.push(ch));
+            compare!(S, sample, format!("try_push({:?})", ch), |s| s.try_push(ch));
+        }
+        for other in SAMPLES {
+            let op = format!("push_str({:?})", other);
+            compare!(S, sample, op, |s| s.push_str(other));
+            let op = format!("try_push_str({:?})", other);
+            compare!(S, sample, op, |s| s.try_push_str(other));
+        }
+
+        compare!(S, sample, "pop() until empty", |s| {
+            let mut popped = Vec::new();
+            while let Some(ch) = s.pop() {
+                popped.push(ch);
+            }
+            popped
+        });
+        compare!(S, sample, "clear()", |s| s.clear());
+    }
+}
+
+/// Checks the operations taking a single byte index, inside and outside of
+/// characters and past the end: `truncate`, `remove`, `insert`,
+/// `insert_str`, their fallible versions and `split_off`.
+///
+/// # Panics
+///
+/// Panics if `S` behaves differently from `std::string::String`.
+pub fn check_insert_remove<S: StringExt>() {
+    for sample in SAMPLES {
+        for idx in indices(sample) {
+            compare!(S, sample, format!("truncate({})", idx), |s| s.truncate(idx));
+            compare!(S, sample, format!("remove({})", idx), |s| s.remove(idx));
+            let op = format!("split_off({})", idx);
+            compare!(S, sample, op, |s| s.split_off(idx).to_string());
+
+            for &ch in CHARS {
+                let op = format!("insert({}, {:?})", idx, ch);
+                compare!(S, sample, op, |s| s.insert(idx, ch));
+                let op = format!("try_insert({}, {:?})", idx, ch);
+                compare!(S, sample, op, |s| s.try_insert(idx, ch));
+            }
+            for &string in &["", "ÿ", LONG_REPLACEMENT] {
+                let op = format!("insert_str({}, {:?})", idx, string);
+                compare!(S, sample, op, |s| s.insert_str(idx, string));
+                let op = format!("try_insert_str({}, {:?})", idx, string);
+                compare!(S, sample, op, |s| s.try_insert_str(idx, string));
+            }
+        }
+    }
+}
+
+/// Checks `remove_range`, `replace_range` and `try_replace_range` with every
+/// kind of range, including ranges that split a character, reversed ranges
+/// and out of bounds ranges.
+///
+/// # Panics
+///
+/// Panics if `S` behaves differently from `std::string::String`.
+pub fn check_ranges<S: StringExt>() {
+    for sample in SAMPLES {
+        let indices = indices(sample);
+        for &i in &indices {
+            let op = format!("remove_range({}..)", i);
+            compare!(S, sample, op, |s| s.remove_range(i..));
+            let op = format!("remove_range(..{})", i);
+            compare!(S, sample, op, |s| s.remove_range(..i));
+            let op = format!("remove_range(..={})", i);
+            compare!(S, sample, op, |s| s.remove_range(..=i));
+            let op = format!("replace_range({}.., \"€\")", i);
+            compare!(S, sample, op, |s| s.replace_range(i.., "€"));
+            let op = format!("replace_range(..{}, \"€\")", i);
+            compare!(S, sample, op, |s| s.replace_range(..i, "€"));
+            let op = format!("extend_from_within({}..)", i);
+            compare!(S, sample, op, |s| s.extend_from_within(i..));
+            let op = format!("split_at_mut({})", i);
+            compare!(S, sample, op, |s| {
+                let (head, tail) = s.split_at_mut(i);
+                head.make_ascii_uppercase();
+                (head.to_owned(), tail.to_owned())
+            });
+
+            for &j in &indices {
+                let op = format!("remove_range({}..{})", i, j);
+                compare!(S, sample, op, |s| s.remove_range(i..j));
+                let op = format!("remove_range({}..={})", i, j);
+                compare!(S, sample, op, |s| s.remove_range(i..=j));
+                for &string in &["", LONG_REPLACEMENT] {
+                    let op = format!("replace_range({}..{}, {:?})", i, j, string);
+                    compare!(S, sample, op, |s| s.replace_range(i..j, string));
+                }
+                let op = format!("try_replace_range({}..{}, \"€\")", i, j);
+                compare!(S, sample, op, |s| s.try_replace_range(i..j, "€"));
+                let op = format!("extend_from_within({}..{})", i, j);
+                compare!(S, sample, op, |s| s.extend_from_within(i..j));
+                let op = format!("drain({}..{})", i, j);
+                compare!(S, sample, op, |s| s.drain(i..j).collect::<String>());
+                let op = format!("drain({}..{}) partially consumed", i, j);
+                compare!(S, sample, op, |s| {
+                    let mut drain = s.drain(i..j);
+                    let front = drain.next();
+                    let back = drain.next_back();
+                    (front, back, drain.as_str().to_owned())
+                });
+            }
+        }
+        compare!(S, sample, "remove_range(..)", |s| s.remove_range(..));
+        compare!(S, sample, "drain(..).rev()", |s| s
+            .drain(..)
+            .rev()
+            .collect::<String>());
+        let op = format!("replace_range(.., {:?})", LONG_REPLACEMENT);
+        compare!(S, sample, op, |s| s.replace_range(.., LONG_REPLACEMENT));
+    }
+}
+
+/// Checks that `retain` and `extract_if` keep the right characters and visit
+/// every character exactly once, in order, and that `remove_matches` removes
+/// the same matches as `str::replace` with an empty string.
+///
+/// # Panics
+///
+/// Panics if `S` behaves differently from `std::string::String`.
+pub fn check_retain<S: StringExt>() {
+    for sample in SAMPLES {
+        compare!(S, sample, "retain(|_| true)", |s| s.retain(|_| true));
+        compare!(S, sample, "retain(|_| false)", |s| s.retain(|_| false));
+        compare!(S, sample, "retain(|ch| ch.is_ascii())", |s| {
+            s.retain(|ch| ch.is_ascii())
+        });
+        compare!(S, sample, "retain(every other char)", |s| {
+            let mut visited = Vec::new();
+            s.retain(|ch| {
+                visited.push(ch);
+                visited.len() % 2 == 0
+            });
+            visited
+        });
+
+        compare!(S, sample, "extract_if(|ch| !ch.is_ascii())", |s| {
+            s.extract_if(|ch| !ch.is_ascii()).to_string()
+        });
+        compare!(S, sample, "extract_if(every other char)", |s| {
+            let mut visited = Vec::new();
+            let extracted = s.extract_if(|ch| {
+                visited.push(ch);
+                visited.len() % 2 == 0
+            });
+            (visited, extracted.to_string())
+        });
+
+        let mut patterns = vec!["", "a", "ab", "🦀", "b", "ÿ", "🦀ÿ", *sample];
+        patterns.extend(sample.char_indices().map(|(idx, _)| &sample[idx..]));
+        for pat in patterns {
+            let context = format!("remove_matches({:?}) on {:?}", pat, sample);
+            let mut s = build::<S>(sample);
+            s.remove_matches(pat);
+            assert_invariants(&s, &sample.replace(pat, ""), &context);
+        }
+    }
+}
+
+/// Checks `into_bytes`, `into_boxed_str` and `as_mut_str`.
+///
+/// # Panics
+///
+/// Panics if `S` behaves differently from `std::string::String`.
+pub fn check_conversions<S>()
+where
+    S: StringExt + Into<String>,
+{
+    for sample in SAMPLES {
+        assert_eq!(
+            build::<S>(sample).into_bytes(),
+            sample.as_bytes(),
+            "into_bytes() on {:?}",
+            sample
+        );
+        assert_eq!(
+            &*build::<S>(sample).into_boxed_str(),
+            *sample,
+            "into_boxed_str() on {:?}",
+            sample
+        );
+        assert_eq!(
+            Into::<String>::into(build::<S>(sample)),
+            *sample,
+            "into() on {:?}",
+            sample
+        );
+
+        let mut s = build::<S>(sample);
+        s.as_mut_str().make_ascii_uppercase();
+        let context = format!("as_mut_str() on {:?}", sample);
+        assert_invariants(&s, &sample.to_ascii_uppercase(), &context);
+    }
+}
+
+fn compare<S, C, T, F, G>(sample: &str, op: C, on_string: F, on_s: G)
+where
+    S: StringExt,
+    C: AsRef<str>,
+    T: PartialEq + Debug,
+    F: FnOnce(&mut String) -> T,
+    G: FnOnce(&mut S) -> T,
+{
+    let op = op.as_ref();
+    let expected = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut s = String::from(sample);
+        let result = on_string(&mut s);
+        (result, s)
+    }));
+    let actual = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut s = build::<S>(sample);
+        let result = on_s(&mut s);
+        (result, s)
+    }));
+
+    let context = format!("{} on {:?}", op, sample);
+    match (expected, actual) {
+        (Ok((expected, expected_s)), Ok((result, s))) => {
+            assert_eq!(result, expected, "{}: returned a different result", context);
+            assert_invariants(&s, &expected_s, &context);
+        }
+        (Err(_), Err(_)) => {}
+        (Ok(_), Err(_)) => panic!("{}: panicked, but `String` does not", context),
+        (Err(_), Ok(_)) => panic!("{}: did not panic, but `String` does", context),
+    }
+}
+
+/// Returns the byte indices to run operations at: all of them up to one past
+/// the end for short samples, and for longer ones the ends, the middle, and
+/// the indices in and around each multi-byte character.
+fn indices(sample: &str) -> Vec<usize> {
+    let len = sample.len();
+    if len <= 24 {
+        return (0..len + 2).collect();
+    }
+
+    let mut indices = vec![0, 1, len / 2, len, len + 1];
+    for (idx, ch) in sample.char_indices() {
+        if ch.len_utf8() > 1 {
+            indices.extend(idx..idx + ch.len_utf8() + 1);
+        }
+    }
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+fn build<S: StringExt>(sample: &str) -> S {
+    let mut s = S::new();
+    s.push_str(sample);
+    s
+}
+
+fn assert_invariants<S: StringExt>(s: &S, expected: &str, context: &str) {
+    assert_eq!(s.as_str(), expected, "{}: wrong contents", context);
+    assert!(
+        *s == *expected,
+        "{}: `PartialEq<str>` disagrees with `as_str`",
+        context
+    );
+    let owned = String::from(expected);
+    assert!(
+        *s == owned,
+        "{}: `PartialEq<String>` disagrees with `as_str`",
+        context
+    );
+    assert_eq!(s.len(), expected.len(), "{}: wrong `len`", context);
+    assert_eq!(
+        s.is_empty(),
+        expected.is_empty(),
+        "{}: wrong `is_empty`",
+        context
+    );
+    assert_eq!(
+        s.as_bytes(),
+        expected.as_bytes(),
+        "{}: wrong `as_bytes`",
+        context
+    );
+    assert!(
+        str::from_utf8(s.as_bytes()).is_ok(),
+        "{}: invalid UTF-8",
+        context
+    );
+    assert_eq!(
+        s.to_string(),
+        expected,
+        "{}: wrong `Display` output",
+        context
+    );
+    assert!(
+        s.capacity() >= s.len(),
+        "{}: `capacity` is less than `len`",
+        context
+    );
+}
+
+/// Generates a test module running the
+/// [`string_ext::conformance`](./string_ext/conformance/index.html) suite
+/// against a `StringExt` implementation, with one test per group of checks.
+///
+/// The first argument names the generated module, the second is the type to
+/// test, which has to be nameable from the module the macro is invoked in.
+/// Only available with the `conformance` feature.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate inlinable_string;
+///
+/// # fn main() {}
+/// #[cfg(test)]
+/// mod tests {
+///     use inlinable_string::InlinableString;
+///
+///     string_ext_conformance_tests!(conformance, InlinableString);
+/// }
+/// ```
+#[macro_export]
+macro_rules! string_ext_conformance_tests {
+    ($name:ident, $ty:ty) => {
+        mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+            use $crate::string_ext::conformance;
+
+            #[test]
+            fn constructors() {
+                conformance::check_constructors::<$ty>();
+            }
+
+            #[test]
+            fn capacity() {
+                conformance::check_capacity::<$ty>();
+            }
+
+            #[test]
+            fn push_pop() {
+                conformance::check_push_pop::<$ty>();
+            }
+
+            #[test]
+            fn insert_remove() {
+                conformance::check_insert_remove::<$ty>();
+            }
+
+            #[test]
+            fn ranges() {
+                conformance::check_ranges::<$ty>();
+            }
+
+            #[test]
+            fn retain() {
+                conformance::check_retain::<$ty>();
+            }
+
+            #[test]
+            fn conversions() {
+                conformance::check_conversions::<$ty>();
+            }
+        }
+    };
+}